@@ -0,0 +1,30 @@
+#include <cstdlib>
+
+#include "adjudication.h"
+#include "tablebase.h"
+
+namespace adjudication {
+std::string Adjudicator::addScore(const Position& position, int score) {
+    // The player who made the move is not the one to move in the position after it
+    if (position.activeColor == Color::WHITE) score = -score;
+
+    _winPlies = score >= _rules.winScore ? _winPlies + 1 : 0;
+    _lossPlies = score <= -_rules.winScore ? _lossPlies + 1 : 0;
+    bool drawn = std::abs(score) <= _rules.drawScore &&
+        position.fullmoveNumber >= _rules.drawMoveNumber;
+    _drawPlies = drawn ? _drawPlies + 1 : 0;
+
+    if (auto wdl = _rules.tablebase ? tablebase::probe(position) : std::nullopt) {
+        if (*wdl == tablebase::WDL::DRAW) return "1/2-1/2";
+        bool whiteWins = (*wdl == tablebase::WDL::WIN) == (position.activeColor == Color::WHITE);
+        return whiteWins ? "1-0" : "0-1";
+    }
+
+    // A number of moves counts moves by both players, as the scores of both engines must agree
+    if (_rules.winMoves && _winPlies >= 2 * _rules.winMoves) return "1-0";
+    if (_rules.winMoves && _lossPlies >= 2 * _rules.winMoves) return "0-1";
+    if (_rules.drawMoves && _drawPlies >= 2 * _rules.drawMoves) return "1/2-1/2";
+    if (_rules.maxMoves && position.fullmoveNumber > _rules.maxMoves) return "1/2-1/2";
+    return "*";
+}
+}  // namespace adjudication
@@ -0,0 +1,57 @@
+#include <string>
+
+#include "common.h"
+
+#pragma once
+
+/**
+ * Policies for ending engine games early, as used by match runners, GUIs and servers, so games
+ * that are clearly decided or drawn don't have to be played out. Scores are in centipawns, as
+ * reported by the engines after each move.
+ */
+namespace adjudication {
+
+/**
+ * The rules for adjudication. A game is won when the scores of both engines favor the same side
+ * by at least the win score for the given number of consecutive moves by each side, and drawn when
+ * they are within the draw score for the given number of moves, starting at the given move number.
+ * Games reaching the maximum number of moves are drawn. A number of moves of 0 disables a rule.
+ * With the tablebase rule, games end with the outcome of the tablebases once they cover the
+ * position, see tablebase::probe.
+ */
+struct Rules {
+    int winMoves = 0;
+    int winScore = 1000;
+    int drawMoves = 0;
+    int drawScore = 10;
+    int drawMoveNumber = 40;  // Fullmove number from which draws are adjudicated
+    int maxMoves = 0;         // Fullmove number after which the game is drawn
+    bool tablebase = false;   // Whether to adjudicate positions in the tablebases
+};
+
+/**
+ * Tracks the scores reported during a game, and decides when it may be adjudicated per the rules.
+ */
+class Adjudicator {
+public:
+    explicit Adjudicator(const Rules& rules) : _rules(rules) {}
+
+    /**
+     * Records the score of a move, from the point of view of the player who made it, given the
+     * position after the move. Returns the result of the game when it can be adjudicated, like
+     * "1-0" or "1/2-1/2" as in PGN, or "*" if the game should continue.
+     */
+    std::string addScore(const Position& position, int score);
+
+    /**
+     * Forgets the scores, as when a move without a score, like a book move, breaks the sequence.
+     */
+    void reset() { _winPlies = _lossPlies = _drawPlies = 0; }
+
+private:
+    Rules _rules;
+    int _winPlies = 0;   // Consecutive plies favoring white by at least the win score
+    int _lossPlies = 0;  // Consecutive plies favoring black by at least the win score
+    int _drawPlies = 0;  // Consecutive plies within the draw score
+};
+}  // namespace adjudication
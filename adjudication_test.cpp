@@ -0,0 +1,110 @@
+#include <cassert>
+#include <iostream>
+#include <string>
+
+#include "adjudication.h"
+#include "fen.h"
+#include "tablebase.h"
+
+/**
+ * Returns the position after a move, for the purpose of adjudication: the other player to move,
+ * and the fullmove number increased after black moves.
+ */
+Position next(Position position) {
+    if (position.activeColor == Color::BLACK) ++position.fullmoveNumber;
+    position.activeColor = !position.activeColor;
+    return position;
+}
+
+/**
+ * Plays moves from the position with the given scores, from the point of view of the side to
+ * move, and returns the first result that isn't "*", or "*" if there is none.
+ */
+std::string play(adjudication::Adjudicator& adjudicator,
+                 Position& position,
+                 std::initializer_list<int> scores) {
+    for (auto score : scores) {
+        position = next(position);
+        auto result = adjudicator.addScore(position, score);
+        if (result != "*") return result;
+    }
+    return "*";
+}
+
+void testWin() {
+    adjudication::Rules rules;
+    rules.winMoves = 2;
+    rules.winScore = 500;
+    adjudication::Adjudicator adjudicator(rules);
+    auto position = fen::parsePosition(fen::initialPosition);
+
+    // Both engines must agree for two moves each, so white winning and black losing
+    assert(play(adjudicator, position, {600, -600, 600}) == "*");
+    assert(play(adjudicator, position, {-600}) == "1-0");
+
+    // A score that doesn't agree restarts the count
+    adjudicator.reset();
+    assert(play(adjudicator, position, {-700, 700, -700, 100, -700, 700, -700}) == "*");
+    assert(play(adjudicator, position, {700}) == "0-1");
+
+    std::cout << "All win tests passed!" << std::endl;
+}
+
+void testDraw() {
+    adjudication::Rules rules;
+    rules.drawMoves = 3;
+    rules.drawScore = 20;
+    rules.drawMoveNumber = 2;
+    adjudication::Adjudicator adjudicator(rules);
+    auto position = fen::parsePosition(fen::initialPosition);
+
+    // Scores before the draw move number don't count
+    assert(play(adjudicator, position, {0, 0, 0, 0, 0}) == "*");
+    assert(play(adjudicator, position, {-20, 10}) == "1/2-1/2");
+
+    // Without rules, games are never adjudicated, except for the maximum number of moves
+    adjudication::Adjudicator none({});
+    position = fen::parsePosition(fen::initialPosition);
+    assert(play(none, position, {0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9999, -9999}) == "*");
+    rules = {};
+    rules.maxMoves = 3;
+    adjudication::Adjudicator maxMoves(rules);
+    position = fen::parsePosition(fen::initialPosition);
+    assert(play(maxMoves, position, {0, 0, 0, 0, 0}) == "*");
+    assert(play(maxMoves, position, {0}) == "1/2-1/2");
+
+    std::cout << "All draw tests passed!" << std::endl;
+}
+
+void testTablebase() {
+    adjudication::Rules rules;
+    rules.tablebase = true;
+    adjudication::Adjudicator adjudicator(rules);
+    tablebase::add(*tablebase::generate("KQvK"));
+
+    // Black to move loses, or draws by capturing the undefended queen, whatever the scores
+    auto position = fen::parsePosition("4k3/8/8/8/8/8/8/3QK3 b - - 0 60");
+    assert(adjudicator.addScore(position, -50) == "1-0");
+    position = fen::parsePosition("4k3/3Q4/8/8/8/8/8/K7 b - - 0 60");
+    assert(adjudicator.addScore(position, 900) == "1/2-1/2");
+    position = fen::parsePosition("3qk3/8/8/8/8/8/8/4K3 w - - 0 60");
+    assert(adjudicator.addScore(position, 0) == "0-1");
+
+    // Positions without a table, or without the rule, continue
+    position = fen::parsePosition("4k3/8/8/8/8/8/8/3RK3 b - - 0 60");
+    assert(adjudicator.addScore(position, 500) == "*");
+    adjudication::Adjudicator none({});
+    position = fen::parsePosition("4k3/8/8/8/8/8/8/3QK3 b - - 0 60");
+    assert(none.addScore(position, 900) == "*");
+    tablebase::clear();
+
+    std::cout << "All tablebase tests passed!" << std::endl;
+}
+
+int main() {
+    testWin();
+    testDraw();
+    testTablebase();
+    std::cout << "All adjudication tests passed!" << std::endl;
+    return 0;
+}
@@ -0,0 +1,75 @@
+#include <sstream>
+#include <string>
+
+#include "analysis.h"
+#include "fen.h"
+#include "moves.h"
+#include "san.h"
+
+namespace {
+/**
+ * Returns the key of the position in the cache, which ignores unusable en passant targets.
+ */
+std::string key(Position position) {
+    normalizeEnPassant(position);
+    return fen::to_epd(position);
+}
+}  // namespace
+
+const AnalysisCache::Entry* AnalysisCache::find(const Position& position) const {
+    auto it = _entries.find(key(position));
+    return it == _entries.end() ? nullptr : &it->second;
+}
+
+bool AnalysisCache::store(const Position& position, const Entry& entry) {
+    if (entry.pv.empty() || !(entry.pv.front() == entry.best.move)) return false;
+    auto& stored = _entries[key(position)];
+    if (stored.depth > entry.depth) return false;
+    stored = entry;
+    return true;
+}
+
+bool AnalysisCache::read(std::istream& in) {
+    std::string line;
+    while (std::getline(in, line)) {
+        if (line.empty()) continue;
+        std::stringstream ss(line);
+        std::string placement, color, castling, enPassant, san;
+        Entry entry;
+        if (!(ss >> placement >> color >> castling >> enPassant >> entry.depth >>
+              entry.best.evaluation >> entry.best.depth))
+            return false;
+
+        auto position = fen::parsePosition(placement + " " + color + " " + castling + " " +
+                                           enPassant + " 0 1");
+        auto next = position;
+        while (ss >> san) {
+            auto move = san::parseMove(next, san);
+            if (!move) return false;
+            entry.pv.push_back(move);
+            next = applyMove(next, move);
+        }
+        if (entry.pv.empty()) return false;
+
+        // The check and mate flags of the best move follow from the position after it
+        auto after = applyMove(position, entry.pv.front());
+        entry.best.move = entry.pv.front();
+        entry.best.check = isInCheck(after);
+        entry.best.mate = allLegalMoves(after).empty();
+        _entries[key(position)] = entry;
+    }
+    return true;
+}
+
+void AnalysisCache::write(std::ostream& out) const {
+    for (auto& [epd, entry] : _entries) {
+        auto position = fen::parsePosition(epd + " 0 1");
+        out << epd << " " << entry.depth << " " << entry.best.evaluation << " "
+            << entry.best.depth;
+        for (auto move : entry.pv) {
+            out << " " << san::to_string(position, move);
+            position = applyMove(position, move);
+        }
+        out << std::endl;
+    }
+}
@@ -0,0 +1,50 @@
+#include <iostream>
+#include <map>
+#include <string>
+
+#include "common.h"
+#include "eval.h"
+
+#pragma once
+
+/**
+ * A store of analysis results mapping positions to the best line found, the depth searched and
+ * the evaluation, so analysis can be saved and resumed later, as when preparing openings. Each
+ * position keeps only its deepest analysis.
+ */
+class AnalysisCache {
+public:
+    struct Entry {
+        int depth = 0;       // Depth of the search, as in the last completed iteration
+        EvaluatedMove best;  // Best move, with its evaluation from the side to move
+        MoveVector pv;       // Principal variation, starting with the best move
+    };
+
+    /**
+     * Returns the analysis of the position, or nullptr if it is not in the cache.
+     */
+    const Entry* find(const Position& position) const;
+
+    /**
+     * Stores the analysis of the position, unless the cache has a deeper one. The principal
+     * variation must start with the best move, and consist of legal moves. Returns true if stored.
+     */
+    bool store(const Position& position, const Entry& entry);
+
+    /**
+     * Reads entries, one per line, each consisting of an EPD position, the depth, the evaluation,
+     * the depth of the evaluation, and the principal variation in SAN notation. Returns false if a
+     * line could not be parsed, in which case the entries read so far are kept.
+     */
+    bool read(std::istream& in);
+
+    /**
+     * Writes all entries in the format accepted by read.
+     */
+    void write(std::ostream& out) const;
+
+    size_t size() const { return _entries.size(); }
+
+private:
+    std::map<std::string, Entry> _entries;  // Keyed by EPD
+};
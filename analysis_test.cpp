@@ -0,0 +1,96 @@
+#include <cassert>
+#include <iostream>
+#include <sstream>
+#include <string>
+
+#include "analysis.h"
+#include "fen.h"
+#include "moves.h"
+
+namespace {
+Move move(const Position& position, const std::string& uci) {
+    for (auto& [move, newPosition] : allLegalMoves(position))
+        if (std::string(move) == uci) return move;
+    assert(false);
+    return {};
+}
+
+AnalysisCache::Entry entry(int depth, float evaluation, MoveVector pv) {
+    return {depth, {pv.front(), false, false, evaluation, depth}, pv};
+}
+}  // namespace
+
+void testStore() {
+    AnalysisCache cache;
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(!cache.find(position));
+
+    auto e4 = move(position, "e2e4");
+    auto e5 = move(applyMove(position, e4), "e7e5");
+    assert(cache.store(position, entry(4, 0.25, {e4, e5})));
+    assert(cache.find(position)->depth == 4 && cache.find(position)->pv.size() == 2);
+
+    // Only deeper or equally deep analysis replaces the stored one
+    auto d4 = move(position, "d2d4");
+    assert(!cache.store(position, entry(3, 0.5, {d4})));
+    assert(cache.find(position)->best.move == e4);
+    assert(cache.store(position, entry(6, 0.5, {d4})));
+    assert(cache.find(position)->best.move == d4 && cache.size() == 1);
+
+    // The variation must start with the best move
+    AnalysisCache::Entry mismatch = {8, {e4, false, false, 0, 8}, {d4}};
+    assert(!cache.store(position, mismatch) && !cache.store(position, {8, {}, {}}));
+
+    // Clocks and unusable en passant targets don't matter
+    auto other = fen::parsePosition("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 5 20");
+    assert(cache.find(other));
+    position = applyMove(position, e4);
+    assert(position.enPassantTarget && !cache.find(position));
+    assert(cache.store(position, entry(2, 0, {e5})));
+    position.enPassantTarget = std::nullopt;
+    assert(cache.find(position));
+    std::cout << "All store tests passed!" << std::endl;
+}
+
+void testReadWrite() {
+    AnalysisCache cache;
+    auto position = fen::parsePosition("6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1");
+    assert(cache.store(position, entry(1, bestEval, {move(position, "e7g7")})));
+    position = fen::parsePosition(fen::initialPosition);
+    auto e4 = move(position, "e2e4");
+    auto c5 = move(applyMove(position, e4), "c7c5");
+    assert(cache.store(position, entry(5, 0.3, {e4, c5})));
+
+    std::stringstream out;
+    cache.write(out);
+    assert(out.str() ==
+           "6k1/4Q3/5K2/8/8/8/8/8 w - - 1 999 1 Qg7#\n"
+           "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 5 0.3 5 e4 c5\n");
+
+    AnalysisCache copy;
+    std::istringstream in(out.str());
+    assert(copy.read(in) && copy.size() == 2);
+    auto mate = copy.find(fen::parsePosition("6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1"));
+    assert(mate && mate->best.check && mate->best.mate && mate->best.evaluation == bestEval);
+    std::stringstream again;
+    copy.write(again);
+    assert(again.str() == out.str());
+
+    // Illegal moves, missing fields or an empty variation fail, keeping earlier entries
+    for (auto invalid : {"8/8/8/8/8/8/8/8 w - - 1 0 1 Qg7#",
+                         "6k1/4Q3/5K2/8/8/8/8/8 w - - 1 999",
+                         "6k1/4Q3/5K2/8/8/8/8/8 w - - 1 999 1",
+                         "6k1/4Q3/5K2/8/8/8/8/8 w - - 1 999 1 Qg7# Kh8"}) {
+        AnalysisCache partial;
+        std::istringstream lines(out.str() + invalid + "\n");
+        assert(!partial.read(lines) && partial.size() == 2);
+    }
+    std::cout << "All read and write tests passed!" << std::endl;
+}
+
+int main() {
+    testStore();
+    testReadWrite();
+    std::cout << "All analysis tests passed!" << std::endl;
+    return 0;
+}
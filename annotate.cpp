@@ -0,0 +1,347 @@
+#include <algorithm>
+#include <array>
+#include <cmath>
+#include <cstdio>
+#include <cstdlib>  // For std::exit
+#include <fstream>
+#include <iomanip>
+#include <iostream>
+#include <string>
+#include <vector>
+
+#include "engine.h"
+#include "fen.h"
+#include "moves.h"
+#include "pgn.h"
+#include "san.h"
+
+/**
+ * Annotates the games of a PGN file read from standard input, writing them to standard output.
+ * Each position of the main line is searched to the given depth. Every move gets a comment with the
+ * evaluation after it, from white's point of view, and moves losing too much compared to the best
+ * move get a NAG, with the best line added as a variation. Existing comments, NAGs and variations
+ * are kept. A summary of the average centipawn loss and accuracy of each player is written to
+ * standard error, and with --json also to a file as a JSON array with an object per game. With
+ * --verify, moves are scanned at the given depth, and only suspicious ones are searched at the
+ * verify depth, see Profile.
+ */
+
+// Centipawn losses from which moves are marked as inaccuracies, mistakes and blunders
+static constexpr int kInaccuracyLoss = 50;
+static constexpr int kMistakeLoss = 100;
+static constexpr int kBlunderLoss = 300;
+
+// Numeric annotation glyphs, see the PGN standard
+static constexpr int kMistakeNAG = 2;     // ?
+static constexpr int kBlunderNAG = 4;     // ??
+static constexpr int kInaccuracyNAG = 6;  // ?!
+
+// Evaluations are capped for computing losses, so missing a faster mate in a won position, or
+// playing on in a lost one, doesn't count as a blunder
+static constexpr int kMaxScore = 1000;
+
+// Change in evaluation between a move and the reply, from which the move is searched again when
+// verifying suspicious moves
+static constexpr int kSuspiciousSwing = 150;
+
+/**
+ * Returns the evaluation of a move by the given side as text from white's point of view, like
+ * "+0.35", "-1.20" or "#-3" for black mating in 3.
+ */
+std::string formatEvaluation(const EvaluatedMove& evaluation, Color side) {
+    auto sign = side == Color::WHITE ? 1 : -1;
+    if (auto mate = search::mateDistance(evaluation)) return "#" + std::to_string(sign * mate);
+    char text[16];
+    std::snprintf(text, sizeof(text), "%+.2f", sign * evaluation.evaluation + 0.0f);
+    return text;
+}
+
+/**
+ * Returns the evaluation of a move in centipawns for the side making it, capped at kMaxScore.
+ */
+int centipawns(const EvaluatedMove& evaluation) {
+    return std::clamp(int(evaluation.evaluation * 100), -kMaxScore, kMaxScore);
+}
+
+/**
+ * Returns the accuracy of a move in percent, from the drop in expected score compared to the best
+ * move: 100 for the best move, decreasing exponentially to below 50 for a drop of 16 points.
+ */
+float moveAccuracy(const EvaluatedMove& best, const EvaluatedMove& played, const Board& board) {
+    auto expectedScore = [&](const EvaluatedMove& move) {
+        return 100 * winDrawLoss(centipawns(move) / 100.0f, board).expectedScore();
+    };
+    auto drop = std::max(0.0f, expectedScore(best) - expectedScore(played));
+    return std::clamp(103.1668f * std::exp(-0.04354f * drop) - 3.1669f, 0.0f, 100.0f);
+}
+
+/**
+ * The quality of the moves of one player in a game.
+ */
+struct PlayerStats {
+    int moves = 0;
+    int totalLoss = 0;        // In centipawns
+    float totalAccuracy = 0;  // Sum of the accuracies of the moves, in percent
+    int verified = 0;         // Number of suspicious moves searched again at a higher depth
+
+    float averageLoss() const { return moves ? float(totalLoss) / moves : 0; }
+    float accuracy() const { return moves ? totalAccuracy / moves : 100; }
+};
+using GameStats = std::array<PlayerStats, 2>;  // Indexed by color
+
+/**
+ * Searches the position to the given depth, considering only the given moves if there are any.
+ */
+Engine::Result analyze(Engine& engine,
+                       const Position& position,
+                       const std::vector<Position>& history,
+                       int depth,
+                       const MoveVector& moves = {}) {
+    search::Limits limits;
+    limits.depth = depth;
+    limits.searchMoves = moves;
+    engine.setPosition(position, history);
+    return engine.go(limits);
+}
+
+/**
+ * Returns a variation with the moves in SAN, starting in the position, with the comment after its
+ * last move.
+ */
+pgn::GameNode variation(Position position, const MoveVector& moves, const std::string& comment) {
+    pgn::GameNode first;
+    auto node = &first;
+    for (size_t ply = 0; ply < moves.size(); ++ply) {
+        if (ply) node = &node->children.emplace_back();
+        node->move = san::to_string(position, moves[ply]);
+        position = applyMove(position, moves[ply]);
+    }
+    node->after.push_back({comment});
+    return first;
+}
+
+/**
+ * The depths for analyzing a game. Quickly scanning at a low depth, and verifying only suspicious
+ * moves at a higher depth, is much faster than searching all moves deeply, while the moves that
+ * matter for the annotations still get a reliable evaluation.
+ */
+struct Profile {
+    int depth = 4;        // Depth for searching all moves
+    int verifyDepth = 0;  // Depth for searching suspicious moves again, if more than depth
+};
+
+/**
+ * The analysis of a move of the main line, with the best move and the played move searched to the
+ * same depth, so their evaluations compare.
+ */
+struct MoveAnalysis {
+    Engine::Result best;
+    Engine::Result played;
+};
+
+/**
+ * Returns the analysis of the move played in the position at the given ply of the main line.
+ */
+MoveAnalysis analyzeMove(Engine& engine,
+                         const std::vector<Position>& positions,
+                         size_t ply,
+                         Move move,
+                         int depth) {
+    std::vector<Position> history(positions.begin(), positions.begin() + ply);
+    MoveAnalysis analysis;
+    analysis.best = analyze(engine, positions[ply], history, depth);
+    analysis.played = move == analysis.best.best.move
+        ? analysis.best
+        : analyze(engine, positions[ply], history, depth, {move});
+    return analysis;
+}
+
+/**
+ * Returns true if the move at the given ply may have been evaluated wrongly: either it loses
+ * enough to get a NAG, or the evaluation swings after the reply, suggesting the search missed
+ * something beyond its horizon. Evaluations of successive plies normally differ by about a pawn,
+ * as the side moving last gets to capture, so smaller swings are ignored.
+ */
+bool isSuspicious(const std::vector<MoveAnalysis>& analyses, size_t ply) {
+    auto& played = analyses[ply].played.best;
+    if (centipawns(analyses[ply].best.best) - centipawns(played) >= kInaccuracyLoss) return true;
+    if (ply + 1 == analyses.size()) return false;
+    auto swing = centipawns(analyses[ply + 1].best.best) + centipawns(played);
+    return std::abs(swing) >= kSuspiciousSwing;
+}
+
+/**
+ * Annotates the main line of the game. Returns false, leaving the rest of the game unannotated, if
+ * it contains an illegal move. A move's loss is the difference between the evaluations of the best
+ * move and the played move, from the point of view of the player. Adds the losses and accuracies of
+ * the moves to the stats of their player.
+ */
+bool annotate(pgn::Game& game, Engine& engine, const Profile& profile, GameStats& stats) {
+    std::vector<Position> positions = {game.startPosition()};
+    MoveVector moves;
+    bool legal = true;
+    for (auto node = &game.root; !node->children.empty(); node = &node->children.front()) {
+        auto& played = node->children.front();
+        auto move = san::parseMove(positions.back(), played.move);
+        if (!move) {
+            std::cerr << "Illegal move \"" << played.move << "\" in "
+                      << fen::to_string(positions.back()) << std::endl;
+            legal = false;
+            break;
+        }
+        moves.push_back(move);
+        positions.push_back(applyMove(positions.back(), move));
+    }
+
+    std::vector<MoveAnalysis> analyses;
+    for (size_t ply = 0; ply < moves.size(); ++ply)
+        analyses.push_back(analyzeMove(engine, positions, ply, moves[ply], profile.depth));
+
+    if (profile.verifyDepth > profile.depth) {
+        std::vector<size_t> suspicious;
+        for (size_t ply = 0; ply < analyses.size(); ++ply)
+            if (isSuspicious(analyses, ply)) suspicious.push_back(ply);
+        for (auto ply : suspicious) {
+            analyses[ply] = analyzeMove(engine, positions, ply, moves[ply], profile.verifyDepth);
+            ++stats[int(positions[ply].activeColor)].verified;
+        }
+    }
+
+    auto node = &game.root;
+    for (size_t ply = 0; ply < analyses.size(); ++ply, node = &node->children.front()) {
+        auto& position = positions[ply];
+        auto& [best, actual] = analyses[ply];
+        auto& played = node->children.front();
+        if (!allLegalMoves(positions[ply + 1]).empty())
+            played.after.push_back({formatEvaluation(actual.best, position.activeColor)});
+
+        auto loss = std::max(0, centipawns(best.best) - centipawns(actual.best));
+        auto& player = stats[int(position.activeColor)];
+        ++player.moves;
+        player.totalLoss += loss;
+        player.totalAccuracy += moveAccuracy(best.best, actual.best, position.board);
+
+        int nag = loss >= kBlunderLoss ? kBlunderNAG
+            : loss >= kMistakeLoss     ? kMistakeNAG
+            : loss >= kInaccuracyLoss  ? kInaccuracyNAG
+                                       : 0;
+        if (nag) {
+            played.nags.push_back(nag);
+            auto comment = formatEvaluation(best.best, position.activeColor);
+            node->children.push_back(variation(position, best.pv, comment));
+        }
+    }
+    return legal;
+}
+
+/**
+ * Writes a table with the average centipawn loss and accuracy of both players of the game.
+ */
+void writeSummary(std::ostream& out, const pgn::Game& game, const GameStats& stats) {
+    auto name = [&](Color color) {
+        auto name = game.tag(color == Color::WHITE ? "White" : "Black");
+        return name.empty() ? "?" : name;
+    };
+    out << name(Color::WHITE) << " - " << name(Color::BLACK) << " " << game.result << std::endl;
+    out << std::left << std::setw(24) << "Player" << std::right << std::setw(8) << "Moves"
+        << std::setw(8) << "ACPL" << std::setw(10) << "Accuracy" << std::endl;
+    for (auto color : {Color::WHITE, Color::BLACK}) {
+        auto& player = stats[int(color)];
+        out << std::left << std::setw(24) << name(color) << std::right << std::setw(8)
+            << player.moves << std::fixed << std::setprecision(1) << std::setw(8)
+            << player.averageLoss() << std::setw(10) << player.accuracy() << std::endl;
+    }
+    if (auto verified = stats[0].verified + stats[1].verified)
+        out << verified << " suspicious moves verified" << std::endl;
+}
+
+/**
+ * Returns the string as a JSON string literal.
+ */
+std::string quote(const std::string& str) {
+    std::string result = "\"";
+    for (unsigned char c : str) {
+        if (c == '"' || c == '\\') {
+            result += '\\';
+            result += c;
+        } else if (c < ' ') {
+            char escape[8];
+            std::snprintf(escape, sizeof(escape), "\\u%04x", c);
+            result += escape;
+        } else {
+            result += c;
+        }
+    }
+    return result + "\"";
+}
+
+/**
+ * Writes the stats of the game as a JSON object, like {"event": "...", "white": {"name": "...",
+ * "moves": 40, "acpl": 23.5, "accuracy": 91.2, "verified": 3}, "black": {...}, "result": "1-0"}.
+ */
+void writeJSON(std::ostream& out, const pgn::Game& game, const GameStats& stats) {
+    out << "{\"event\": " << quote(game.tag("Event"));
+    for (auto color : {Color::WHITE, Color::BLACK}) {
+        auto white = color == Color::WHITE;
+        auto& player = stats[int(color)];
+        out << (white ? ", \"white\"" : ", \"black\"")
+            << ": {\"name\": " << quote(game.tag(white ? "White" : "Black"))
+            << ", \"moves\": " << player.moves << std::fixed << std::setprecision(1)
+            << ", \"acpl\": " << player.averageLoss() << ", \"accuracy\": " << player.accuracy()
+            << ", \"verified\": " << player.verified << "}";
+    }
+    out << ", \"result\": " << quote(game.result) << "}";
+}
+
+int main(int argc, char** argv) {
+    std::ofstream json;
+    Profile profile;
+    while (argc > 2) {
+        std::string option = argv[1];
+        if (option == "--json") {
+            json.open(argv[2]);
+            if (!json) {
+                std::cerr << "Cannot open " << argv[2] << std::endl;
+                std::exit(1);
+            }
+        } else if (option == "--verify") {
+            profile.verifyDepth = std::atoi(argv[2]);
+        } else {
+            break;
+        }
+        argv += 2;
+        argc -= 2;
+    }
+    if (argc > 2) {
+        std::cerr << "Usage: " << argv[0]
+                  << " [--json stats.json] [--verify depth] [depth] < games.pgn > annotated.pgn"
+                  << std::endl;
+        std::exit(1);
+    }
+    if (argc > 1) profile.depth = std::atoi(argv[1]);
+
+    AlphaBetaEngine engine;
+    int numGames = 0, numFailed = 0, numMalformed = 0;
+    pgn::Game game;
+    if (json.is_open()) json << "[";
+    pgn::ReadResult read;
+    while ((read = pgn::readGame(std::cin, game)) != pgn::ReadResult::END) {
+        if (read == pgn::ReadResult::MALFORMED) {
+            ++numMalformed;
+            continue;
+        }
+        GameStats stats;
+        clearHashTable();  // Each game is annotated independently
+        numFailed += !annotate(game, engine, profile, stats);
+        pgn::writeGame(std::cout, game);
+        writeSummary(std::cerr, game, stats);
+        if (json.is_open()) writeJSON(json << (numGames ? ",\n " : "\n "), game, stats);
+        ++numGames;
+        game = {};
+    }
+    if (json.is_open()) json << "\n]" << std::endl;
+    std::cerr << numGames << " games annotated, " << numFailed << " with illegal moves"
+              << std::endl;
+    if (numMalformed) std::cerr << numMalformed << " malformed games skipped" << std::endl;
+    return numFailed + numMalformed != 0;
+}
@@ -0,0 +1,111 @@
+#include <chrono>
+#include <iomanip>
+#include <iostream>
+#include <string>
+#include <vector>
+
+#include "eval.h"
+#include "fen.h"
+#include "moves.h"
+
+/**
+ * Benchmarks for the hot paths of the engine: move generation, applying moves, attack detection and
+ * board evaluation, so performance regressions are measurable. Each benchmark runs over a standard
+ * set of positions, repeating until it has run for long enough to give a stable time per position.
+ */
+
+using Clock = std::chrono::steady_clock;
+
+// Minimum time to run each benchmark
+static constexpr std::chrono::milliseconds kMinDuration{500};
+
+// Positions from the opening, middle game and endgame, including the perft test positions
+static const std::vector<std::string> kBenchmarkPositions = {
+    fen::initialPosition,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+};
+
+// Size of the table for the storePosition benchmark, which is larger than the caches
+static constexpr int kTableBits = 20;
+
+// Results of the benchmarked functions are added here, so the compiler can't optimize them away
+static uint64_t sink = 0;
+
+/**
+ * Runs the function on all positions, doubling the number of rounds until the minimum duration is
+ * reached, and prints the average time per position.
+ */
+template <typename F>
+void benchmark(const std::string& name, const std::vector<Position>& positions, const F& fun) {
+    for (uint64_t rounds = 1;; rounds *= 2) {
+        auto start = Clock::now();
+        for (uint64_t round = 0; round < rounds; ++round)
+            for (auto& position : positions) fun(position);
+        auto elapsed = std::chrono::duration<double, std::nano>(Clock::now() - start);
+        if (elapsed < kMinDuration) continue;
+
+        std::cout << std::left << std::setw(20) << name << std::right << std::setw(12)
+                  << rounds * positions.size() << " positions " << std::fixed
+                  << std::setprecision(1) << std::setw(10)
+                  << elapsed.count() / (rounds * positions.size()) << " ns/position" << std::endl;
+        return;
+    }
+}
+
+int main(int argc, char** argv) {
+    std::string filter = argc > 1 ? argv[1] : "";
+    std::vector<Position> positions;
+    for (auto& fen : kBenchmarkPositions) positions.push_back(fen::parsePosition(fen));
+
+    auto run = [&](const std::string& name, const auto& fun) {
+        if (name.find(filter) != std::string::npos) benchmark(name, positions, fun);
+    };
+
+    run("allLegalMoves", [](const Position& position) { sink += allLegalMoves(position).size(); });
+    run("addAvailableMoves", [](const Position& position) {
+        MoveVector moves;
+        addAvailableMoves(moves, position.board, position.activeColor);
+        addAvailableCaptures(moves, position.board, position.activeColor);
+        sink += moves.size();
+    });
+    std::vector<ComputedMoveVector> legalMoves;
+    for (auto& position : positions) legalMoves.push_back(allLegalMoves(position));
+    size_t next = 0;  // Index of the moves of the position, which are benchmarked in order
+    run("applyMove", [&](const Position& position) {
+        for (auto& [move, newPosition] : legalMoves[next++ % legalMoves.size()])
+            sink += applyMove(position, move).halfmoveClock;
+    });
+    // Store positions at pseudo-random places in a large table, as a transposition table would
+    std::vector<Position> table(1 << kTableBits);
+    uint64_t stored = 0;
+    run("storePosition", [&](const Position& position) {
+        auto& entry = table[(++stored * 0x9e3779b97f4a7c15ull) >> (64 - kTableBits)];
+        sink += entry.halfmoveClock;
+        entry = position;
+    });
+    run("isAttacked", [](const Position& position) {
+        for (Square square = 0; square != kNumSquares; ++square)
+            sink += isAttacked(position.board, square, !position.activeColor);
+    });
+    run("isInCheck", [](const Position& position) { sink += isInCheck(position); });
+    // Find each piece in turn, comparing the portable and vectorized ways of scanning the board
+    run("findPiecesSWAR", [](const Position& position) {
+        for (uint8_t piece = 0; piece != kNumPieces; ++piece)
+            sink += equalSetSWAR(position.board, Piece(piece), false);
+    });
+#ifdef __SSE2__
+    run("findPiecesSSE2", [](const Position& position) {
+        for (uint8_t piece = 0; piece != kNumPieces; ++piece)
+            sink += equalSetSSE2(position.board, Piece(piece), false);
+    });
+#endif
+    run("evaluateBoard", [](const Position& position) {
+        sink += evaluateBoard(position.board) > 0;
+    });
+
+    return sink == 0;  // Use the sink, but it's never zero
+}
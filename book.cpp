@@ -0,0 +1,107 @@
+#include <sstream>
+#include <string>
+
+#include "book.h"
+#include "fen.h"
+#include "moves.h"
+#include "san.h"
+
+namespace {
+/**
+ * Returns the key of the position in the book, which ignores unusable en passant targets.
+ */
+std::string key(Position position) {
+    normalizeEnPassant(position);
+    return fen::to_epd(position);
+}
+}  // namespace
+
+void Book::add(const Position& position, Move move, float weight) {
+    auto& entries = _entries[key(position)];
+    for (auto& entry : entries) {
+        if (entry.move == move) {
+            entry.weight = weight;
+            return;
+        }
+    }
+    entries.push_back({move, weight});
+}
+
+const std::vector<Book::Entry>& Book::entries(const Position& position) const {
+    static const std::vector<Entry> none;
+    auto it = _entries.find(key(position));
+    return it == _entries.end() ? none : it->second;
+}
+
+Move Book::choose(const Position& position, float random) const {
+    auto& candidates = entries(position);
+    float total = 0;
+    for (auto& entry : candidates) total += entry.effectiveWeight();
+    if (total <= 0) return {};
+
+    float pick = random * total;
+    Move last;
+    for (auto& entry : candidates) {
+        if (entry.effectiveWeight() <= 0) continue;
+        if (pick < entry.effectiveWeight()) return entry.move;
+        pick -= entry.effectiveWeight();
+        last = entry.move;
+    }
+    return last;  // Only reached due to rounding
+}
+
+void Book::learn(Position position,
+                 const std::vector<Move>& moves,
+                 Color engineColor,
+                 float result,
+                 float decay) {
+    float outcome = 2 * result - 1;  // Map loss, draw and win to -1, 0 and 1
+    for (auto move : moves) {
+        auto it = _entries.find(key(position));
+        if (it == _entries.end()) return;  // Left the book
+
+        bool found = false;
+        for (auto& entry : it->second) {
+            if (!(entry.move == move)) continue;
+            found = true;
+            if (position.activeColor != engineColor) break;
+            entry.learned = decay * entry.learned + (1 - decay) * outcome;
+            ++entry.games;
+        }
+        if (!found) return;
+
+        position = applyMove(position, move);
+    }
+}
+
+bool Book::read(std::istream& in) {
+    std::string line;
+    while (std::getline(in, line)) {
+        if (line.empty()) continue;
+        std::stringstream ss(line);
+        std::string placement, color, castling, enPassant, san;
+        Entry entry;
+        if (!(ss >> placement >> color >> castling >> enPassant >> san >> entry.weight >>
+              entry.learned >> entry.games))
+            return false;
+
+        auto epd = placement + " " + color + " " + castling + " " + enPassant;
+        if (!fen::isValid(epd)) return false;
+
+        auto position = fen::parsePosition(epd + " 0 1");
+        entry.move = san::parseMove(position, san);
+        if (!entry.move) return false;
+        _entries[key(position)].push_back(entry);
+    }
+    return true;
+}
+
+void Book::write(std::ostream& out) const {
+    for (auto& [epd, entries] : _entries) {
+        auto position = fen::parsePosition(epd + " 0 1");
+        for (auto& entry : entries) {
+            out << epd << " " << san::to_string(position, entry.move) << " " << entry.weight << " "
+                << entry.learned << " " << entry.games << std::endl;
+        }
+    }
+}
@@ -0,0 +1,73 @@
+#include <iostream>
+#include <map>
+#include <string>
+#include <vector>
+
+#include "common.h"
+
+#pragma once
+
+/**
+ * An opening book mapping positions to candidate moves with weights. The book learns from the
+ * engine's own results: each book move the engine played keeps a decaying average of the results
+ * of the games it was played in, which scales its weight. Lines that score poorly are gradually
+ * avoided, while recent results count more than older ones.
+ */
+class Book {
+public:
+    struct Entry {
+        Move move;
+        float weight = 1;   // Weight as given when adding the move to the book
+        float learned = 0;  // Decaying average result, from -1 (always lost) to 1 (always won)
+        int games = 0;      // Number of games learned from
+
+        float effectiveWeight() const { return weight * (1 + learned); }
+    };
+
+    // Fraction of the learned value retained on each update, the rest comes from the new result
+    static constexpr float kDefaultDecay = 0.75f;
+
+    /**
+     * Adds a legal move to the book, or updates its weight if the move is already present.
+     */
+    void add(const Position& position, Move move, float weight = 1);
+
+    /**
+     * Returns the book entries for the position, or an empty vector if it is not in the book.
+     */
+    const std::vector<Entry>& entries(const Position& position) const;
+
+    /**
+     * Chooses a book move with probability proportional to its effective weight, using the given
+     * random number in [0, 1). Returns an invalid move if the position has no usable entries.
+     */
+    Move choose(const Position& position, float random) const;
+
+    /**
+     * Updates the entries for the book moves the engine played in a game starting at the given
+     * position, until the game left the book. The result is the engine's score for the game:
+     * 1 for a win, 0.5 for a draw and 0 for a loss.
+     */
+    void learn(Position position,
+               const std::vector<Move>& moves,
+               Color engineColor,
+               float result,
+               float decay = kDefaultDecay);
+
+    /**
+     * Reads book entries, one per line, each consisting of an EPD position, a move in SAN
+     * notation, the weight, the learned value and the number of games. Returns false if a line
+     * could not be parsed, in which case the entries read so far are kept.
+     */
+    bool read(std::istream& in);
+
+    /**
+     * Writes all book entries in the format accepted by read.
+     */
+    void write(std::ostream& out) const;
+
+    size_t size() const { return _entries.size(); }
+
+private:
+    std::map<std::string, std::vector<Entry>> _entries;  // Keyed by EPD
+};
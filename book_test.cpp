@@ -0,0 +1,107 @@
+#include <cassert>
+#include <iostream>
+#include <sstream>
+#include <vector>
+
+#include "book.h"
+#include "fen.h"
+#include "moves.h"
+#include "san.h"
+
+Book makeBook() {
+    Book book;
+    auto position = fen::parsePosition(fen::initialPosition);
+    book.add(position, san::parseMove(position, "e4"), 2);
+    book.add(position, san::parseMove(position, "d4"), 2);
+    auto e4 = applyMove(position, san::parseMove(position, "e4"));
+    book.add(e4, san::parseMove(e4, "c5"));
+    book.add(e4, san::parseMove(e4, "e5"));
+    return book;
+}
+
+void testChoose() {
+    auto book = makeBook();
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(book.entries(position).size() == 2);
+    assert(book.choose(position, 0.0f) == san::parseMove(position, "e4"));
+    assert(book.choose(position, 0.49f) == san::parseMove(position, "e4"));
+    assert(book.choose(position, 0.51f) == san::parseMove(position, "d4"));
+    assert(book.choose(position, 0.99f) == san::parseMove(position, "d4"));
+
+    auto d4 = applyMove(position, san::parseMove(position, "d4"));
+    assert(book.entries(d4).empty());
+    assert(!book.choose(d4, 0.5f));
+
+    // Entries without weight are never chosen, not even when rounding runs past the last entry
+    book.add(position, san::parseMove(position, "d4"), 0);
+    assert(book.choose(position, 0.99f) == san::parseMove(position, "e4"));
+    assert(book.choose(position, 1.0f) == san::parseMove(position, "e4"));
+    book.add(position, san::parseMove(position, "e4"), 0);
+    assert(!book.choose(position, 0.5f));
+
+    std::cout << "All choose tests passed!" << std::endl;
+}
+
+void testLearn() {
+    auto book = makeBook();
+    auto position = fen::parsePosition(fen::initialPosition);
+    auto e4 = san::parseMove(position, "e4");
+    auto afterE4 = applyMove(position, e4);
+    auto c5 = san::parseMove(afterE4, "c5");
+
+    // Losing with 1. e4 as white makes it less likely to be chosen, Black's move is unaffected
+    book.learn(position, {e4, c5}, Color::WHITE, 0.0f);
+    auto& entries = book.entries(position);
+    assert(entries[0].learned < 0 && entries[0].games == 1);
+    assert(entries[0].effectiveWeight() < entries[1].effectiveWeight());
+    assert(book.entries(afterE4)[0].games == 0);
+    assert(book.choose(position, 0.49f) == san::parseMove(position, "d4"));
+
+    // Older results decay, so enough wins restore the line
+    for (int i = 0; i < 10; ++i) book.learn(position, {e4, c5}, Color::WHITE, 1.0f);
+    assert(entries[0].learned > 0.9f && entries[0].games == 11);
+    assert(entries[0].effectiveWeight() > entries[1].effectiveWeight());
+
+    // Learning for Black only updates Black's moves
+    book.learn(position, {e4, c5}, Color::BLACK, 0.0f);
+    assert(entries[0].games == 11);
+    assert(book.entries(afterE4)[0].games == 1 && book.entries(afterE4)[0].learned < 0);
+
+    std::cout << "All learn tests passed!" << std::endl;
+}
+
+void testReadWrite() {
+    auto book = makeBook();
+    auto position = fen::parsePosition(fen::initialPosition);
+    book.learn(position, {san::parseMove(position, "d4")}, Color::WHITE, 1.0f);
+
+    std::stringstream ss;
+    book.write(ss);
+    Book copy;
+    assert(copy.read(ss));
+    assert(copy.size() == book.size());
+    std::stringstream again;
+    copy.write(again);
+    assert(again.str() == ss.str());
+
+    std::stringstream bad("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - e5 1 0 0\n");
+    assert(!copy.read(bad));
+
+    // Malformed positions are rejected rather than parsed
+    size_t size = copy.size();
+    std::stringstream malformed("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - e4 1 0 0\n");
+    assert(!copy.read(malformed));
+    std::stringstream garbage("xyz w KQkq - e4 1 0 0\n");
+    assert(!copy.read(garbage));
+    assert(copy.size() == size);
+
+    std::cout << "All read/write tests passed!" << std::endl;
+}
+
+int main() {
+    testChoose();
+    testLearn();
+    testReadWrite();
+    std::cout << "All book tests passed!" << std::endl;
+    return 0;
+}
@@ -0,0 +1,79 @@
+#include <cctype>
+#include <limits>
+#include <sstream>
+#include <string>
+
+#include "config.h"
+
+namespace config {
+namespace {
+std::string trim(const std::string& str) {
+    auto begin = str.find_first_not_of(" \t\r");
+    if (begin == std::string::npos) return "";
+    return str.substr(begin, str.find_last_not_of(" \t\r") + 1 - begin);
+}
+
+/**
+ * Parses the whole string as a number, returning false if it isn't one or is out of range.
+ */
+template <typename T>
+bool parseValue(const std::string& str, T& value, T min, T max) {
+    std::istringstream in(str);
+    T parsed;
+    if (!(in >> parsed) || !(in >> std::ws).eof() || parsed < min || parsed > max) return false;
+    value = parsed;
+    return true;
+}
+
+bool setSearchParam(Params& params, const std::string& key, const std::string& value) {
+    static constexpr int kMaxInt = std::numeric_limits<int16_t>::max();
+    static constexpr float kMaxFloat = kMaxInt;
+    if (key == "moves_to_go") return parseValue(value, params.movesToGo, 1, kMaxInt);
+    if (key == "stable_iterations") return parseValue(value, params.stableIterations, 1, kMaxInt);
+    if (key == "stable_score_margin")
+        return parseValue(value, params.stableScoreMargin, 0.0f, kMaxFloat);
+    if (key == "unstable_time_factor")
+        return parseValue(value, params.unstableTimeFactor, 1.0f, kMaxFloat);
+    if (key == "fail_low_time_factor")
+        return parseValue(value, params.failLowTimeFactor, 1.0f, kMaxFloat);
+    if (key == "fail_low_margin") return parseValue(value, params.failLowMargin, 0.0f, kMaxFloat);
+    if (key == "max_time_factor") return parseValue(value, params.maxTimeFactor, 1, kMaxInt);
+    return false;
+}
+
+bool setPieceValue(Params& params, const std::string& key, const std::string& value) {
+    if (key.size() != 1) return false;
+    auto piece = toPiece(std::toupper(key[0]));
+    if (piece == Piece::NONE || type(piece) == PieceType::KING) return false;
+    return parseValue(value,
+                      params.pieceValues[index(type(piece))],
+                      0,
+                      int(std::numeric_limits<int16_t>::max()));
+}
+}  // namespace
+
+bool loadParams(std::istream& in, Params& params) {
+    auto loaded = params;
+    std::string table;
+    std::string line;
+    while (std::getline(in, line)) {
+        line = trim(line.substr(0, line.find('#')));
+        if (line.empty()) continue;
+        if (line.front() == '[' && line.back() == ']') {
+            table = trim(line.substr(1, line.size() - 2));
+            if (table != "search" && table != "pieces") return false;
+            continue;
+        }
+        auto equals = line.find('=');
+        if (equals == std::string::npos) return false;
+        auto key = trim(line.substr(0, equals));
+        auto value = trim(line.substr(equals + 1));
+        bool set = table == "search" ? setSearchParam(loaded, key, value)
+            : table == "pieces"      ? setPieceValue(loaded, key, value)
+                                     : false;
+        if (!set) return false;
+    }
+    params = loaded;
+    return true;
+}
+}  // namespace config
@@ -0,0 +1,48 @@
+#include <array>
+#include <istream>
+
+#include "common.h"
+
+#pragma once
+
+/**
+ * Parameters of the search and evaluation that can be tuned without recompiling, by loading them
+ * from a configuration file. The defaults are the values the engine uses without one.
+ */
+namespace config {
+
+struct Params {
+    // Number of moves to plan for when the time control doesn't say
+    int movesToGo = 30;
+    // Iterations with the same best move and a similar score, after which the best move is stable
+    int stableIterations = 3;
+    float stableScoreMargin = 0.3;  // In pawns
+    // Factors of the allocated time to search when the best move changed in the last iteration, or
+    // its score dropped by more than the margin, up to the maximum time
+    float unstableTimeFactor = 1.5;
+    float failLowTimeFactor = 2;
+    float failLowMargin = 0.5;  // In pawns
+    // Maximum factor of the allocated time to search, as extra time is taken from later moves
+    int maxTimeFactor = 3;
+    // Values of the pieces in centipawns, by piece type. Kings have no value.
+    std::array<int, kNumPiecesTypes> pieceValues = {100, 300, 300, 500, 900, 0};
+};
+
+/**
+ * Reads parameters from a file in a subset of TOML, replacing those in params that it sets. Keys
+ * are in a [search] table, named like the fields of Params in snake case, or in a [pieces] table,
+ * with the piece letters as keys and values in centipawns:
+ *
+ *     # Tuned for short time controls
+ *     [search]
+ *     stable_iterations = 4
+ *     fail_low_margin = 0.4
+ *
+ *     [pieces]
+ *     N = 320
+ *     B = 330
+ *
+ * Returns false, leaving params unchanged, on unknown tables or keys, or invalid values.
+ */
+bool loadParams(std::istream& in, Params& params);
+}  // namespace config
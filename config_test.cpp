@@ -0,0 +1,67 @@
+#include <cassert>
+#include <iostream>
+#include <sstream>
+#include <string>
+
+#include "config.h"
+
+void testDefaults() {
+    config::Params params;
+    std::istringstream empty("# Nothing to change\n\n");
+    assert(config::loadParams(empty, params));
+    assert(params.stableIterations == 3 && params.maxTimeFactor == 3);
+    assert(params.pieceValues[index(PieceType::QUEEN)] == 900);
+    std::cout << "All defaults tests passed!" << std::endl;
+}
+
+void testLoadParams() {
+    config::Params params;
+    std::istringstream in(
+        "# Tuned for short time controls\n"
+        "[search]\n"
+        "stable_iterations = 4\n"
+        "  fail_low_margin=0.25  # Trailing comment\n"
+        "moves_to_go = 40\n"
+        "\n"
+        "[ pieces ]\n"
+        "N = 320\n"
+        "b = 330\n");
+    assert(config::loadParams(in, params));
+    assert(params.stableIterations == 4);
+    assert(params.failLowMargin == 0.25f);
+    assert(params.movesToGo == 40);
+    assert(params.unstableTimeFactor == 1.5f);  // Not in the file
+    assert(params.pieceValues[index(PieceType::KNIGHT)] == 320);
+    assert(params.pieceValues[index(PieceType::BISHOP)] == 330);
+    assert(params.pieceValues[index(PieceType::ROOK)] == 500);
+    std::cout << "All loadParams tests passed!" << std::endl;
+}
+
+void testInvalid() {
+    // Unknown tables or keys, keys in the wrong table, and invalid values change nothing
+    for (auto invalid : {"[eval]\n",
+                         "[pieces]\nstable_iterations = 4\n",
+                         "[search]\nunknown = 1\n",
+                         "[search]\nstable_iterations\n",
+                         "[search]\nstable_iterations = four\n",
+                         "[search]\nstable_iterations = 0\n",
+                         "[search]\nmax_time_factor = 2.5\n",
+                         "[search]\nfail_low_time_factor = 0.5\n",
+                         "[pieces]\nK = 10000\n",
+                         "[pieces]\nN = -300\n",
+                         "[pieces]\nNB = 300\n"}) {
+        config::Params params;
+        std::istringstream in(std::string("[search]\nmoves_to_go = 20\n") + invalid);
+        assert(!config::loadParams(in, params));
+        assert(params.movesToGo == 30);
+    }
+    std::cout << "All invalid tests passed!" << std::endl;
+}
+
+int main() {
+    testDefaults();
+    testLoadParams();
+    testInvalid();
+    std::cout << "All config tests passed!" << std::endl;
+    return 0;
+}
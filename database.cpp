@@ -0,0 +1,178 @@
+#include <algorithm>
+#include <cstdlib>
+#include <sstream>
+#include <string>
+
+#include "database.h"
+#include "hash.h"
+#include "moves.h"
+#include "san.h"
+
+namespace {
+Database::GameIndexVector find(const std::map<std::string, Database::GameIndexVector>& map,
+                               const std::string& key) {
+    auto it = map.find(key);
+    return it == map.end() ? Database::GameIndexVector() : it->second;
+}
+
+void insert(Database::GameIndexVector& games, Database::GameIndex game) {
+    if (games.empty() || games.back() != game) games.push_back(game);
+}
+
+/**
+ * Returns the hash of the position, ignoring an unusable en passant target, so transpositions
+ * through a double pawn push are found.
+ */
+uint64_t positionKey(Position position) {
+    normalizeEnPassant(position);
+    return Hash(position)();
+}
+
+/**
+ * Returns the score of the game for the given side: 1 for a win, 0.5 for a draw, 0 for a loss,
+ * or a negative number if the game has no result.
+ */
+float score(const std::string& result, Color color) {
+    if (result == "1/2-1/2") return 0.5f;
+    if (result == "1-0") return color == Color::WHITE ? 1.0f : 0.0f;
+    if (result == "0-1") return color == Color::BLACK ? 1.0f : 0.0f;
+    return -1.0f;
+}
+}  // namespace
+
+Database::Database(std::istream& pgn, int maxPlies) {
+    pgn::Game game;
+    auto offset = pgn.tellg();
+    for (pgn::ReadResult read; (read = pgn::readGame(pgn, game)) != pgn::ReadResult::END;
+         offset = pgn.tellg()) {
+        if (read == pgn::ReadResult::MALFORMED) {
+            ++_skipped;  // Keep indexing the games that follow
+            continue;
+        }
+        GameInfo info;
+        info.offset = offset;
+        info.white = game.tag("White");
+        info.black = game.tag("Black");
+        info.eco = game.tag("ECO");
+        info.result = game.result;
+        info.whiteElo = std::atoi(game.tag("WhiteElo").c_str());
+        info.blackElo = std::atoi(game.tag("BlackElo").c_str());
+
+        auto position = game.startPosition();
+        info.positions.push_back(positionKey(position));
+        for (auto& san : game.mainLine()) {
+            if (int(info.positions.size()) > maxPlies) break;
+            auto move = san::parseMove(position, san);
+            if (!move) break;  // Index the positions up to the illegal move
+            info.moves.push_back(san::to_string(position, move));
+            position = applyMove(position, move);
+            info.positions.push_back(positionKey(position));
+        }
+        add(std::move(info));
+    }
+}
+
+void Database::add(GameInfo info) {
+    GameIndex index = _games.size();
+    insert(_players[info.white], index);
+    insert(_players[info.black], index);
+    insert(_ecos[info.eco], index);
+    insert(_results[info.result], index);
+    for (auto position : info.positions) insert(_positions[position], index);
+    _games.push_back(std::move(info));
+}
+
+pgn::Game Database::game(std::istream& pgn, GameIndex game) const {
+    pgn::Game result;
+    pgn.clear();
+    pgn.seekg(_games[game].offset);
+    pgn::readGame(pgn, result);
+    return result;
+}
+
+Database::GameIndexVector Database::gamesWithPlayer(const std::string& name) const {
+    return find(_players, name);
+}
+
+Database::GameIndexVector Database::gamesWithECO(const std::string& eco) const {
+    return find(_ecos, eco);
+}
+
+Database::GameIndexVector Database::gamesWithResult(const std::string& result) const {
+    return find(_results, result);
+}
+
+Database::GameIndexVector Database::gamesWithPosition(const Position& position) const {
+    auto it = _positions.find(positionKey(position));
+    return it == _positions.end() ? GameIndexVector() : it->second;
+}
+
+Database::MoveStatisticsVector Database::moveStatistics(const Position& position) const {
+    auto hash = positionKey(position);
+    MoveStatisticsVector statistics;
+    for (auto index : gamesWithPosition(position)) {
+        auto& info = _games[index];
+        size_t ply = 0;
+        while (info.positions[ply] != hash) ++ply;
+        if (ply >= info.moves.size()) continue;  // Game ended or no longer indexed here
+
+        auto& move = info.moves[ply];
+        auto it = std::find_if(statistics.begin(), statistics.end(),
+                               [&](auto& stats) { return stats.move == move; });
+        if (it == statistics.end()) it = statistics.insert(it, {move});
+        ++it->games;
+
+        auto result = score(info.result, position.activeColor);
+        if (result == 1.0f) ++it->wins;
+        if (result == 0.5f) ++it->draws;
+        if (result == 0.0f) ++it->losses;
+
+        auto opponentElo = position.activeColor == Color::WHITE ? info.blackElo : info.whiteElo;
+        if (opponentElo) ++it->ratedOpponents, it->totalOpponentRating += opponentElo;
+    }
+    std::stable_sort(statistics.begin(), statistics.end(),
+                     [](auto& left, auto& right) { return left.games > right.games; });
+    return statistics;
+}
+
+void Database::save(std::ostream& out) const {
+    for (auto& info : _games) {
+        out << info.offset << '\t' << info.white << '\t' << info.black << '\t' << info.whiteElo
+            << '\t' << info.blackElo << '\t' << info.eco << '\t' << info.result << '\t';
+        const char* separator = "";
+        for (auto& move : info.moves) out << separator << move, separator = " ";
+        out << '\t' << std::hex;
+        for (auto position : info.positions) out << ' ' << position;
+        out << std::dec << '\n';
+    }
+}
+
+bool Database::load(std::istream& in) {
+    *this = {};
+    std::string line;
+    while (std::getline(in, line)) {
+        std::stringstream ss(line);
+        GameInfo info;
+        std::string offset, whiteElo, blackElo, moves, positions;
+        if (!std::getline(ss, offset, '\t') || !std::getline(ss, info.white, '\t') ||
+            !std::getline(ss, info.black, '\t') || !std::getline(ss, whiteElo, '\t') ||
+            !std::getline(ss, blackElo, '\t') || !std::getline(ss, info.eco, '\t') ||
+            !std::getline(ss, info.result, '\t') || !std::getline(ss, moves, '\t') ||
+            !std::getline(ss, positions))
+            return false;
+        info.offset = std::atoll(offset.c_str());
+        info.whiteElo = std::atoi(whiteElo.c_str());
+        info.blackElo = std::atoi(blackElo.c_str());
+
+        std::stringstream sans(moves);
+        for (std::string move; sans >> move;) info.moves.push_back(move);
+
+        std::stringstream hashes(positions);
+        uint64_t position;
+        while (hashes >> std::hex >> position) info.positions.push_back(position);
+        if (!hashes.eof() || info.positions.size() != info.moves.size() + 1) return false;
+
+        add(std::move(info));
+    }
+    return true;
+}
@@ -0,0 +1,102 @@
+#include <cstdint>
+#include <iostream>
+#include <map>
+#include <string>
+#include <unordered_map>
+#include <vector>
+
+#include "common.h"
+#include "pgn.h"
+
+#pragma once
+
+/**
+ * An index over the games in a PGN file, for finding games by player, opening, result or by a
+ * position reached early in the game. Only the indexed fields and the offsets of the games in the
+ * file are kept, so the games themselves are read on demand. The index can be saved and loaded,
+ * so large files only need to be parsed once.
+ */
+class Database {
+public:
+    using GameIndex = uint32_t;
+    using GameIndexVector = std::vector<GameIndex>;
+
+    struct GameInfo {
+        std::streamoff offset = 0;  // Start of the game in the PGN file
+        std::string white, black, eco, result;
+        int whiteElo = 0, blackElo = 0;   // Zero if unrated
+        std::vector<std::string> moves;   // Main line moves in SAN, one less than the positions
+        std::vector<uint64_t> positions;  // Hashes of the positions in the main line
+    };
+
+    /**
+     * Statistics for a move played from some position, with the results as seen by the side
+     * making the move.
+     */
+    struct MoveStatistics {
+        std::string move;  // The move in SAN
+        int games = 0, wins = 0, draws = 0, losses = 0;
+        int ratedOpponents = 0;  // Number of games where the opponent has a rating
+        long totalOpponentRating = 0;
+
+        float winPercentage() const { return games ? 100.0f * wins / games : 0; }
+        float drawPercentage() const { return games ? 100.0f * draws / games : 0; }
+        float lossPercentage() const { return games ? 100.0f * losses / games : 0; }
+        float averageOpponentRating() const {
+            return ratedOpponents ? float(totalOpponentRating) / ratedOpponents : 0;
+        }
+    };
+    using MoveStatisticsVector = std::vector<MoveStatistics>;
+
+    // Number of plies of the main line for which positions are indexed
+    static constexpr int kDefaultIndexedPlies = 40;
+
+    Database() = default;
+
+    /**
+     * Builds the index by reading all games from the PGN input, which must support seeking so
+     * games can be retrieved later. Positions are indexed for the first maxPlies plies. Games with
+     * malformed movetext are skipped.
+     */
+    Database(std::istream& pgn, int maxPlies = kDefaultIndexedPlies);
+
+    size_t size() const { return _games.size(); }
+    size_t skipped() const { return _skipped; }  // Malformed games left out of the index
+    const GameInfo& info(GameIndex game) const { return _games[game]; }
+
+    /**
+     * Reads the game with the given index from the PGN input the index was built from.
+     */
+    pgn::Game game(std::istream& pgn, GameIndex game) const;
+
+    /**
+     * Queries returning the indices of the matching games, in the order of the PGN file.
+     */
+    GameIndexVector gamesWithPlayer(const std::string& name) const;
+    GameIndexVector gamesWithECO(const std::string& eco) const;
+    GameIndexVector gamesWithResult(const std::string& result) const;
+    GameIndexVector gamesWithPosition(const Position& position) const;
+
+    /**
+     * Returns statistics for each move played in the given position in the indexed part of the
+     * games, most popular moves first. Games reaching the position by transposition are included.
+     */
+    MoveStatisticsVector moveStatistics(const Position& position) const;
+
+    /**
+     * Writes the index, and reads it back, replacing any existing index. Returns false if the
+     * input could not be parsed.
+     */
+    void save(std::ostream& out) const;
+    bool load(std::istream& in);
+
+private:
+    void add(GameInfo info);
+
+    std::vector<GameInfo> _games;
+    size_t _skipped = 0;
+    std::map<std::string, GameIndexVector> _players;
+    std::map<std::string, GameIndexVector> _ecos;
+    std::map<std::string, GameIndexVector> _results;
+    std::unordered_map<uint64_t, GameIndexVector> _positions;
+};
@@ -0,0 +1,165 @@
+#include <cassert>
+#include <iostream>
+#include <sstream>
+
+#include "database.h"
+#include "fen.h"
+#include "moves.h"
+#include "san.h"
+
+const char* const kGames = R"([Event "Game 1"]
+[White "Morphy"]
+[Black "Duke"]
+[WhiteElo "2600"]
+[BlackElo "2000"]
+[ECO "C41"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 1-0
+
+[Event "Game 2"]
+[White "Anderssen"]
+[Black "Morphy"]
+[WhiteElo "2500"]
+[BlackElo "2600"]
+[ECO "C50"]
+[Result "0-1"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bc4 {Italian} (3. Bb5 a6) Bc5 0-1
+
+[Event "Game 3"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[ECO "C33"]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. f4 exf4 1/2-1/2
+)";
+
+void testQueries() {
+    std::stringstream pgn(kGames);
+    Database db(pgn);
+    assert(db.size() == 3);
+    assert(db.info(1).white == "Anderssen" && db.info(1).black == "Morphy");
+
+    assert(db.gamesWithPlayer("Morphy") == Database::GameIndexVector({0, 1}));
+    assert(db.gamesWithPlayer("Anderssen") == Database::GameIndexVector({1, 2}));
+    assert(db.gamesWithPlayer("Steinitz").empty());
+    assert(db.gamesWithECO("C50") == Database::GameIndexVector({1}));
+    assert(db.gamesWithResult("1/2-1/2") == Database::GameIndexVector({2}));
+
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(db.gamesWithPosition(position).size() == 3);
+    for (auto move : {"e4", "e5", "Nf3"})
+        position = applyMove(position, san::parseMove(position, move));
+    assert(db.gamesWithPosition(position) == Database::GameIndexVector({0, 1}));
+
+    // Positions in variations are not indexed
+    auto nc6 = applyMove(position, san::parseMove(position, "Nc6"));
+    auto bb5 = applyMove(nc6, san::parseMove(nc6, "Bb5"));
+    assert(db.gamesWithPosition(bb5).empty());
+
+    std::cout << "All query tests passed!" << std::endl;
+}
+
+void testMaxPlies() {
+    std::stringstream pgn(kGames);
+    Database db(pgn, 2);
+    auto position = fen::parsePosition(fen::initialPosition);
+    for (auto move : {"e4", "e5"}) position = applyMove(position, san::parseMove(position, move));
+    assert(db.gamesWithPosition(position).size() == 3);
+    position = applyMove(position, san::parseMove(position, "Nf3"));
+    assert(db.gamesWithPosition(position).empty());
+
+    std::cout << "All max plies tests passed!" << std::endl;
+}
+
+void testGame() {
+    std::stringstream pgn(kGames);
+    Database db(pgn);
+    auto game = db.game(pgn, 2);
+    assert(game.tag("Event") == "Game 3");
+    assert(game.mainLine().size() == 4);
+    game = db.game(pgn, 1);
+    assert(game.tag("Event") == "Game 2" && game.result == "0-1");
+    assert(game.root.children[0].children[0].children[0].children[0].children.size() == 2);
+
+    std::cout << "All game tests passed!" << std::endl;
+}
+
+void testMalformedGame() {
+    std::string games = kGames;
+    games.replace(games.find("Bc5 0-1"), 3, "Bc5 $999");
+    std::stringstream pgn(games);
+    Database db(pgn);
+    assert(db.size() == 2 && db.skipped() == 1);
+    assert(db.gamesWithPlayer("Anderssen") == Database::GameIndexVector({1}));
+    assert(db.game(pgn, 1).tag("Event") == "Game 3");
+
+    std::cout << "All malformed game tests passed!" << std::endl;
+}
+
+void testMoveStatistics() {
+    std::stringstream pgn(kGames);
+    Database db(pgn);
+    auto position = fen::parsePosition(fen::initialPosition);
+    auto stats = db.moveStatistics(position);
+    assert(stats.size() == 1 && stats[0].move == "e4" && stats[0].games == 3);
+    assert(stats[0].wins == 1 && stats[0].draws == 1 && stats[0].losses == 1);
+    assert(stats[0].ratedOpponents == 2 && stats[0].averageOpponentRating() == 2300);
+
+    // Statistics are for the side to move, most popular moves first
+    for (auto move : {"e4", "e5"}) position = applyMove(position, san::parseMove(position, move));
+    stats = db.moveStatistics(position);
+    assert(stats.size() == 2 && stats[0].move == "Nf3" && stats[1].move == "f4");
+    assert(stats[0].games == 2 && stats[0].wins == 1 && stats[0].losses == 1);
+    assert(stats[0].winPercentage() == 50 && stats[0].drawPercentage() == 0);
+    assert(stats[1].drawPercentage() == 100 && stats[1].ratedOpponents == 0);
+    position = applyMove(position, san::parseMove(position, "Nf3"));
+    stats = db.moveStatistics(position);
+    assert(stats.size() == 2 && stats[0].games == 1 && stats[1].games == 1);
+    assert(stats[0].move == "d6" && stats[0].lossPercentage() == 100);
+    assert(stats[1].move == "Nc6" && stats[1].winPercentage() == 100);
+
+    // No moves after the end of the game
+    for (auto move : {"d6", "d4", "Bg4"})
+        position = applyMove(position, san::parseMove(position, move));
+    assert(db.gamesWithPosition(position).size() == 1 && db.moveStatistics(position).empty());
+
+    std::cout << "All move statistics tests passed!" << std::endl;
+}
+
+void testSaveLoad() {
+    std::stringstream pgn(kGames);
+    Database db(pgn);
+    std::stringstream index;
+    db.save(index);
+
+    Database copy;
+    assert(copy.load(index));
+    assert(copy.size() == db.size());
+    assert(copy.gamesWithPlayer("Morphy") == db.gamesWithPlayer("Morphy"));
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(copy.gamesWithPosition(position).size() == 3);
+    assert(copy.game(pgn, 2).tag("Event") == "Game 3");
+    assert(copy.info(1).blackElo == 2600 && copy.info(1).moves == db.info(1).moves);
+    std::stringstream again;
+    copy.save(again);
+    assert(again.str() == index.str());
+
+    std::stringstream bad("12\tMorphy\n");
+    assert(!copy.load(bad));
+
+    std::cout << "All save/load tests passed!" << std::endl;
+}
+
+int main() {
+    testQueries();
+    testMaxPlies();
+    testGame();
+    testMalformedGame();
+    testMoveStatistics();
+    testSaveLoad();
+    std::cout << "All database tests passed!" << std::endl;
+    return 0;
+}
@@ -0,0 +1,148 @@
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cmath>
+#include <cstdio>
+#include <cstdlib>  // For std::exit
+#include <fstream>
+#include <iostream>
+#include <random>
+#include <string>
+#include <thread>
+#include <vector>
+
+#include "adjudication.h"
+#include "engine.h"
+#include "game.h"
+#include "training.h"
+
+/**
+ * Generates training data by self-play, see training.h. Plays the given number of games, each
+ * starting with a few random moves so games differ, and searching every later move to the given
+ * depth. Positions where the side to move is in check or the best move is a capture are skipped,
+ * as their static evaluation is misleading, as are positions with a mate found. Games are
+ * adjudicated once clearly decided, as the result is all that matters. Games are divided over
+ * worker processes, as the search keeps its state in globals, and the output of the workers is
+ * concatenated in the order of the workers. Games are reproducible for the same seed.
+ */
+
+// Random moves at the start of each game
+static constexpr int kRandomPlies = 8;
+
+// Adjudication, to keep games short: won after 4 moves by each side with a score of at least 10
+// pawns, drawn after 10 moves within 0.1 pawns from move 40, and drawn at move 200
+static const adjudication::Rules kRules = {4, 1000, 10, 10, 40, 200, false};
+
+struct Options {
+    std::string output;
+    int games = 100;
+    int depth = 4;
+    int workers = std::max(1u, std::thread::hardware_concurrency());
+    unsigned seed = 0;
+};
+
+/**
+ * Plays a game and writes the records for its positions, returning their number.
+ */
+int playGame(std::ostream& out, int depth, unsigned seed) {
+    std::mt19937 gen(seed);
+    Game game;
+    for (int ply = 0; ply < kRandomPlies && game.result() == "*"; ++ply) {
+        auto moves = allLegalMoves(game.currentPosition());
+        game.pushMove(moves[std::uniform_int_distribution<size_t>(0, moves.size() - 1)(gen)].first);
+    }
+
+    AlphaBetaEngine engine;
+    search::Limits limits;
+    limits.depth = depth;
+    adjudication::Adjudicator adjudicator(kRules);
+    std::vector<Position> history;
+    std::vector<training::Record> records;
+    clearHashTable();
+    while (game.result() == "*") {
+        auto position = game.currentPosition();
+        engine.setPosition(position, history);
+        auto best = engine.go(limits).best;
+
+        auto score = int(std::lround(std::clamp(best.evaluation * 100, -30000.0f, 30000.0f)));
+        bool capture = position.board[best.move.to] != Piece::NONE ||
+            best.move.kind == MoveKind::EN_PASSANT;
+        if (!isInCheck(position) && !capture && !search::mateDistance(best))
+            records.push_back({position,
+                               int16_t(position.activeColor == Color::WHITE ? score : -score),
+                               training::Result::DRAW});
+
+        history.push_back(position);
+        game.pushMove(best.move);
+        if (game.result() != "*") break;
+        auto result = adjudicator.addScore(game.currentPosition(), score);
+        if (result != "*") game.setResult(result);
+    }
+
+    auto result = *training::toResult(game.result());
+    for (auto& record : records) {
+        record.result = result;
+        training::write(out, record);
+    }
+    return records.size();
+}
+
+/**
+ * Plays the games of the worker, every workers-th one, writing them to the file.
+ */
+void work(const Options& options, int worker, const std::string& path) {
+    std::ofstream out(path, std::ios::binary);
+    for (int game = worker; game < options.games; game += options.workers)
+        playGame(out, options.depth, options.seed + game);
+    if (!out) std::exit(1);
+}
+
+int main(int argc, char** argv) {
+    if (argc < 2 || argc > 6) {
+        std::cerr << "Usage: " << argv[0] << " <output> [games] [depth] [workers] [seed]"
+                  << std::endl;
+        std::exit(1);
+    }
+    Options options;
+    options.output = argv[1];
+    if (argc > 2) options.games = std::atoi(argv[2]);
+    if (argc > 3) options.depth = std::atoi(argv[3]);
+    if (argc > 4) options.workers = std::max(1, std::atoi(argv[4]));
+    if (argc > 5) options.seed = std::strtoul(argv[5], nullptr, 10);
+    options.workers = std::max(1, std::min(options.workers, options.games));
+
+    std::vector<pid_t> pids;
+    auto part = [&](int worker) { return options.output + "." + std::to_string(worker); };
+    for (int worker = 0; worker < options.workers; ++worker) {
+        auto pid = fork();
+        if (pid < 0) {
+            std::perror("fork");
+            std::exit(1);
+        }
+        if (pid == 0) {
+            work(options, worker, part(worker));
+            std::exit(0);
+        }
+        pids.push_back(pid);
+    }
+    bool failed = false;
+    for (auto pid : pids) {
+        int status;
+        failed |= waitpid(pid, &status, 0) < 0 || !WIFEXITED(status) || WEXITSTATUS(status);
+    }
+
+    std::ofstream out(options.output, std::ios::binary);
+    for (int worker = 0; worker < options.workers; ++worker) {
+        std::ifstream in(part(worker), std::ios::binary);
+        if (in.peek() != std::ifstream::traits_type::eof()) out << in.rdbuf();
+        std::remove(part(worker).c_str());
+    }
+    if (failed || !out) {
+        std::cerr << "Cannot write " << options.output << std::endl;
+        std::exit(1);
+    }
+    auto records = size_t(out.tellp()) / training::kRecordSize;
+    std::cout << options.games << " games, " << records << " positions" << std::endl;
+    return 0;
+}
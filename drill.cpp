@@ -0,0 +1,65 @@
+#include <cstdlib>  // For std::exit
+#include <fstream>
+#include <iostream>
+#include <random>
+#include <string>
+
+#include "pgn.h"
+#include "repertoire.h"
+
+/**
+ * Drills an opening repertoire, see repertoire.h. Reads the repertoire from the PGN file given as
+ * first argument, with all games and their variations, and quizzes the user playing the given
+ * color on standard input. Spaced repetition statistics are read from, and written back to, the
+ * optional statistics file, so each run is a new session that only asks the positions that are
+ * due. Without a seed, the opponent's moves vary between runs.
+ */
+
+int main(int argc, char** argv) {
+    if (argc < 3 || (std::string(argv[2]) != "white" && std::string(argv[2]) != "black")) {
+        std::cerr << "Usage: " << argv[0]
+                  << " <repertoire.pgn> {white|black} [stats] [lines] [seed]" << std::endl;
+        std::exit(1);
+    }
+    std::ifstream pgn(argv[1]);
+    if (!pgn) {
+        std::cerr << "Cannot open " << argv[1] << std::endl;
+        std::exit(1);
+    }
+    Repertoire repertoire;
+    pgn::Game game;
+    pgn::ReadResult read;
+    while ((read = pgn::readGame(pgn, game)) != pgn::ReadResult::END)
+        if (read == pgn::ReadResult::MALFORMED)
+            std::cerr << "Ignoring malformed game" << std::endl;
+        else if (!repertoire.add(game))
+            std::cerr << "Ignoring illegal moves in game" << std::endl;
+
+    auto user = std::string(argv[2]) == "white" ? Color::WHITE : Color::BLACK;
+    std::string statsFile = argc > 3 ? argv[3] : "";
+    int lines = argc > 4 ? std::atoi(argv[4]) : 10;
+    unsigned seed = argc > 5 ? std::atoi(argv[5]) : std::random_device()();
+
+    Schedule schedule;
+    if (!statsFile.empty()) {
+        std::ifstream in(statsFile);
+        if (in && !schedule.read(in)) {
+            std::cerr << "Cannot read statistics from " << statsFile << std::endl;
+            std::exit(1);
+        }
+    }
+    schedule.nextSession();
+
+    auto result = drill(repertoire, schedule, user, std::cin, std::cout, lines, seed);
+    std::cout << result.correct << " of " << result.asked << " correct" << std::endl;
+
+    if (!statsFile.empty()) {
+        std::ofstream out(statsFile);
+        schedule.write(out);
+        if (!out) {
+            std::cerr << "Cannot write statistics to " << statsFile << std::endl;
+            std::exit(1);
+        }
+    }
+    return 0;
+}
@@ -0,0 +1,76 @@
+#include <algorithm>
+
+#include "engine.h"
+#include "moves.h"
+
+namespace {
+/**
+ * Returns the legal moves in the position that the limits allow searching.
+ */
+ComputedMoveVector rootMoves(const Position& position, const search::Limits& limits) {
+    auto moves = allLegalMoves(position);
+    auto& searchMoves = limits.searchMoves;
+    if (searchMoves.empty()) return moves;
+    auto excluded = [&](const ComputedMove& move) {
+        return std::find(searchMoves.begin(), searchMoves.end(), move.first) == searchMoves.end();
+    };
+    moves.erase(std::remove_if(moves.begin(), moves.end(), excluded), moves.end());
+    return moves;
+}
+}  // namespace
+
+Engine::Result AlphaBetaEngine::go(const search::Limits& limits,
+                                   const search::InfoFunction& info) {
+    _stopped = false;
+    auto stopLimits = limits;
+    stopLimits.stop = [this, stop = limits.stop]() { return _stopped || (stop && stop()); };
+
+    Result result;
+    auto startNodes = nodeCount;
+    auto recordInfo = [&](const search::RootMove& best, int depth, search::Duration elapsed) {
+        result.pv = best.pv;
+        result.depth = depth;
+        if (info) info(best, depth, elapsed);
+    };
+    result.best = search::iterativeDeepening(_position, stopLimits, recordInfo, _history);
+    result.nodes = nodeCount - startNodes;
+    if (!result.best.move)
+        result.pv.clear();
+    else if (result.pv.empty() || result.pv.front() != result.best.move)
+        result.pv = {result.best.move};
+    return result;
+}
+
+Engine::Result RandomEngine::go(const search::Limits& limits, const search::InfoFunction&) {
+    Result result;
+    auto moves = rootMoves(_position, limits);
+    if (moves.empty()) return result;
+    auto move = moves[std::uniform_int_distribution<size_t>(0, moves.size() - 1)(_generator)].first;
+    result.best = {move, false, false, drawEval, 1};
+    result.pv = {move};
+    result.nodes = moves.size();
+    result.depth = 1;
+    return result;
+}
+
+Engine::Result GreedyEngine::go(const search::Limits& limits, const search::InfoFunction&) {
+    Result result;
+    for (auto& [move, position] : rootMoves(_position, limits)) {
+        auto evaluation = evaluateBoard(position.board);
+        if (_position.activeColor == Color::BLACK) evaluation = -evaluation;
+        if (!result.best.move || evaluation > result.best.evaluation)
+            result.best = {move, false, false, evaluation, 1};
+        ++result.nodes;
+    }
+    if (!result.best.move) return result;
+    result.pv = {result.best.move};
+    result.depth = 1;
+    return result;
+}
+
+std::unique_ptr<Engine> makeEngine(const std::string& name) {
+    if (name == "alphabeta") return std::make_unique<AlphaBetaEngine>();
+    if (name == "greedy") return std::make_unique<GreedyEngine>();
+    if (name == "random") return std::make_unique<RandomEngine>();
+    return nullptr;
+}
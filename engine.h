@@ -0,0 +1,95 @@
+#include <atomic>
+#include <memory>
+#include <random>
+#include <string>
+#include <vector>
+
+#include "common.h"
+#include "eval.h"
+#include "fen.h"
+#include "search.h"
+
+#pragma once
+
+/**
+ * A common interface for the algorithms choosing moves, so frontends like the UCI engine don't
+ * depend on how moves are chosen. Engines search the position last set, until the limits say to
+ * stop or stop() is called from another thread.
+ */
+class Engine {
+public:
+    /**
+     * The result of a search. The best move is invalid if there are no legal moves.
+     */
+    struct Result {
+        EvaluatedMove best;
+        MoveVector pv;       // Principal variation, starting with the best move if known
+        uint64_t nodes = 0;  // Positions searched, if the engine counts them
+        int depth = 0;       // Depth of the last completed iteration
+    };
+
+    virtual ~Engine() = default;
+
+    /**
+     * Sets the position to search, and the positions of the game before it, oldest first.
+     */
+    virtual void setPosition(const Position& position, const std::vector<Position>& history) {
+        _position = position;
+        _history = history;
+    }
+
+    /**
+     * Searches the position within the limits. Engines call the info function after each
+     * completed iteration, if they have iterations.
+     */
+    virtual Result go(const search::Limits& limits, const search::InfoFunction& info = {}) = 0;
+
+    /**
+     * Stops a search in progress in another thread as soon as possible.
+     */
+    virtual void stop() {}
+
+protected:
+    Position _position = fen::parsePosition(fen::initialPosition);
+    std::vector<Position> _history;
+};
+
+/**
+ * Iterative deepening alpha-beta search, see search::iterativeDeepening.
+ */
+class AlphaBetaEngine : public Engine {
+public:
+    Result go(const search::Limits& limits, const search::InfoFunction& info = {}) override;
+    void stop() override { _stopped = true; }
+
+private:
+    std::atomic<bool> _stopped = false;
+};
+
+/**
+ * A baseline that plays a random legal move, for testing frontends and measuring the strength of
+ * other engines. Moves are reproducible for the same seed.
+ */
+class RandomEngine : public Engine {
+public:
+    explicit RandomEngine(unsigned seed = 0) : _generator(seed) {}
+    Result go(const search::Limits& limits, const search::InfoFunction& info = {}) override;
+
+private:
+    std::mt19937 _generator;
+};
+
+/**
+ * A baseline that plays the move with the best static evaluation, not looking at any replies.
+ * Of moves with the same evaluation, it plays the first one generated.
+ */
+class GreedyEngine : public Engine {
+public:
+    Result go(const search::Limits& limits, const search::InfoFunction& info = {}) override;
+};
+
+/**
+ * Returns a new engine with the given name, "alphabeta", "greedy" or "random", or nullptr for an
+ * unknown name.
+ */
+std::unique_ptr<Engine> makeEngine(const std::string& name);
@@ -0,0 +1,92 @@
+#include <cassert>
+#include <chrono>
+#include <iostream>
+#include <set>
+#include <string>
+#include <thread>
+
+#include "engine.h"
+#include "fen.h"
+#include "moves.h"
+
+using namespace std::chrono_literals;
+
+void testAlphaBeta() {
+    AlphaBetaEngine engine;
+    engine.setPosition(fen::parsePosition("6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1"), {});
+    int iterations = 0;
+    auto info = [&](const search::RootMove&, int, search::Duration) { ++iterations; };
+    auto result = engine.go({}, info);
+    assert(std::string(result.best.move) == "e7g7" && result.best.evaluation == bestEval);
+    assert(result.pv.size() == 1 && result.pv.front() == result.best.move);
+    assert(result.nodes > 0 && iterations == 1);
+
+    // Stopping from another thread ends an unlimited search, after the first iteration
+    engine.setPosition(fen::parsePosition(fen::initialPosition), {});
+    search::Limits limits;
+    limits.time = search::Duration::max();
+    std::thread stopper([&engine]() {
+        std::this_thread::sleep_for(100ms);
+        engine.stop();
+    });
+    assert(engine.go(limits).best.move);
+    stopper.join();
+    std::cout << "All alpha-beta engine tests passed!" << std::endl;
+}
+
+void testRandom() {
+    auto position = fen::parsePosition(fen::initialPosition);
+    std::set<std::string> played;
+    RandomEngine engine(42);
+    engine.setPosition(position, {});
+    for (int i = 0; i < 20; ++i) {
+        auto result = engine.go({});
+        assert(checkMove(position, result.best.move) == IllegalMoveReason::NONE);
+        played.insert(std::string(result.best.move));
+    }
+    assert(played.size() > 1);
+
+    // The same seed gives the same moves, and only moves to search are played
+    RandomEngine first(7), second(7);
+    first.setPosition(position, {});
+    second.setPosition(position, {});
+    search::Limits limits;
+    limits.searchMoves = {Move(Square(1, 0), Square(2, 0), MoveKind::QUIET_MOVE)};
+    for (int i = 0; i < 5; ++i) assert(first.go({}).best.move == second.go({}).best.move);
+    assert(std::string(first.go(limits).best.move) == "a2a3");
+    std::cout << "All random engine tests passed!" << std::endl;
+}
+
+void testGreedy() {
+    GreedyEngine engine;
+    engine.setPosition(fen::parsePosition("4k3/1p6/8/3q4/8/8/2P5/3RK3 w - - 0 1"), {});
+    auto result = engine.go({});
+    assert(std::string(result.best.move) == "d1d5" && result.best.evaluation == 5);
+
+    // Evaluations are from the point of view of the side to move
+    engine.setPosition(fen::parsePosition("3rk3/8/8/3Q4/8/8/8/4K3 b - - 0 1"), {});
+    result = engine.go({});
+    assert(std::string(result.best.move) == "d8d5" && result.best.evaluation == 5);
+
+    // No moves when the game is over
+    engine.setPosition(fen::parsePosition("k7/8/1Q6/8/8/8/8/7K b - - 0 1"), {});
+    assert(!engine.go({}).best.move);
+    std::cout << "All greedy engine tests passed!" << std::endl;
+}
+
+void testMakeEngine() {
+    assert(dynamic_cast<AlphaBetaEngine*>(makeEngine("alphabeta").get()));
+    assert(dynamic_cast<GreedyEngine*>(makeEngine("greedy").get()));
+    assert(dynamic_cast<RandomEngine*>(makeEngine("random").get()));
+    assert(!makeEngine("mcts"));
+    std::cout << "All makeEngine tests passed!" << std::endl;
+}
+
+int main() {
+    testAlphaBeta();
+    testRandom();
+    testGreedy();
+    testMakeEngine();
+    std::cout << "All engine tests passed!" << std::endl;
+    return 0;
+}
@@ -1,10 +1,16 @@
 #include <algorithm>
+#include <cctype>
+#include <cmath>
 #include <iostream>
-#include <random>
+#include <limits>
+#include <optional>
+#include <sstream>
 #include <string>
 
 #include "eval.h"
+#include "hash.h"
 #include "moves.h"
+#include "tablebase.h"
 
 constexpr bool debug = 0;
 #define D \
@@ -42,146 +48,138 @@ EvaluatedMove::operator std::string() const {
     return ss.str();
 }
 
-// Implement a hashing method for chess positions using Zobrist hashing
-// https://en.wikipedia.org/wiki/Zobrist_hashing This relies just on the number of locations
-// ("squares") and number of pieces, where we assume piece 0 to be "no piece". The hash allows for
-// efficient incremental updating of the hash value when a move is made.
-
-// 1 for black to move, 1 for each castling right, 8 for en passant file
-static constexpr int kNumExtraVectors = 24;
-static constexpr int kNumBoardVectors = kNumPieces * kNumSquares;
-static constexpr int kNumHashVectors = kNumBoardVectors + kNumExtraVectors;
-
-// A random 64-bit integer for each piece on each square, as well as the extra vectors. The first
-// piece is None, but it is not omitted here, as it allows removing a hard-to-predict branch in the
-// hash function.
-static std::array<uint64_t, kNumHashVectors> hashVectors = []() {
-    std::array<uint64_t, kNumHashVectors> vectors;
-    std::ranlux48 gen(0xbad5eed5'bad5eed5);
-    for (auto& v : vectors) v = gen();
-    return vectors;
-}();
-
-// A Hash is a 64-bit integer that represents a position. It is the XOR of the hash vectors for
-// each piece on each square, as well as the applicable extra vectors.
-class Hash {
-    uint64_t hash = 0;
-
-public:
-    enum ExtraVectors {
-        BLACK_TO_MOVE = kNumBoardVectors + 0,
-        CASTLING_1 = kNumBoardVectors + 1,
-        CASTLING_15 = kNumBoardVectors + 15,
-        EN_PASSANT_A = kNumBoardVectors + 16,
-        EN_PASSANT_H = kNumBoardVectors + 23,
-    };
+TranspositionTable<EvaluatedMove> hashTable(18);
+
+void clearHashTable() {
+    hashTable.clear();
+}
 
-    Hash() = default;
-    Hash(Position position) {
-        int location = 0;
-        for (auto square : SquareSet::occupancy(position.board))
-            toggle(position.board[square], square.index());
-        if (position.activeColor == Color::BLACK) toggle(BLACK_TO_MOVE);
-        if (position.castlingAvailability != CastlingMask::NONE)
-            toggle(ExtraVectors(CASTLING_1 - 1 + uint8_t(position.castlingAvailability)));
-        if (position.enPassantTarget.index())
-            toggle(ExtraVectors(position.enPassantTarget.file() + EN_PASSANT_A));
+MoveVector principalVariation(Position position, int depth, int maxdepth) {
+    MoveVector pv;
+    for (; depth <= maxdepth; ++depth) {
+        auto entry = hashTable.probe(Hash(position));
+        if (!entry || entry->bound != Bound::EXACT || entry->draft != maxdepth - depth) break;
+        if (!entry->value.move) break;
+        pv.push_back(entry->value.move);
+        position = applyMove(position, entry->value.move);
     }
+    return pv;
+}
 
-    uint64_t operator()() { return hash; }
+/**
+ * Triangular table of principal variations: the row for each depth has the best line found from
+ * the position at that depth, made of the best move followed by the row of the next depth.
+ */
+struct PVTable {
+    static constexpr int kMaxDepth = 128;
 
-    void move(Piece piece, int from, int to) {
-        toggle(piece, from);
-        toggle(piece, to);
+    std::array<std::array<Move, kMaxDepth>, kMaxDepth> moves;
+    std::array<int, kMaxDepth> length = {};
+
+    void clear(int depth) {
+        if (depth < kMaxDepth) length[depth] = 0;
     }
-    void capture(Piece piece, Piece target, int from, int to) {
-        toggle(piece, from);
-        toggle(target, to);
-        toggle(piece, to);
+
+    void update(int depth, Move move) {
+        if (depth >= kMaxDepth) return;
+        moves[depth][0] = move;
+        int next = depth + 1 < kMaxDepth ? std::min(length[depth + 1], kMaxDepth - 1) : 0;
+        std::copy_n(moves[depth + 1].begin(), next, moves[depth].begin() + 1);
+        length[depth] = 1 + next;
     }
 
-    // Does not cancel out castling rights or en passant targets.
-    // Assumes that passed in board is the same as the board used to construct this hash.
-    void applyMove(const Board& board, Move mv) {
-        auto piece = board[mv.from];
-        auto target = board[mv.to];
-        move(piece, mv.from.index(), mv.to.index());
-        switch (mv.kind) {
-        case MoveKind::QUIET_MOVE: break;
-        case MoveKind::DOUBLE_PAWN_PUSH: toggle(ExtraVectors(mv.to.file() + EN_PASSANT_A)); break;
-        case MoveKind::KING_CASTLE:  // Assume the move has the king move, so adjust the rook here.
-            move(addColor(PieceType::ROOK, color(piece)),
-                 (color(piece) == Color::WHITE ? Position::whiteKingSideRook
-                                               : Position::blackKingSideRook)
-                     .index(),
-                 (color(piece) == Color::WHITE ? Position::whiteRookCastledKingSide
-                                               : Position::blackRookCastledKingSide)
-                     .index());
-            break;
-        case MoveKind::QUEEN_CASTLE:  // Assume the move has the king move, so adjust the rook here.
-            move(addColor(PieceType::ROOK, color(piece)),
-                 (color(piece) == Color::WHITE ? Position::whiteQueenSideRook
-                                               : Position::blackQueenSideRook)
-                     .index(),
-                 (color(piece) == Color::WHITE ? Position::whiteRookCastledQueenSide
-                                               : Position::blackRookCastledQueenSide)
-                     .index());
-            break;
-
-        case MoveKind::CAPTURE: toggle(target, mv.to.index()); break;
-        case MoveKind::EN_PASSANT:
-            // Depending of the color of our piece, the captured pawn is either above or below the
-            // destination square.
-            toggle(target, mv.to.index() + (color(piece) == Color::WHITE ? -kNumFiles : kNumFiles));
-            break;
-        case MoveKind::KNIGHT_PROMOTION:
-        case MoveKind::BISHOP_PROMOTION:
-        case MoveKind::ROOK_PROMOTION:
-        case MoveKind::QUEEN_PROMOTION:
-            toggle(piece, mv.to.index());  // Remove the pawn, add the promoted piece
-            toggle(addColor(promotionType(mv.kind), color(piece)), mv.to.index());
-            break;
-        case MoveKind::KNIGHT_PROMOTION_CAPTURE:
-        case MoveKind::BISHOP_PROMOTION_CAPTURE:
-        case MoveKind::ROOK_PROMOTION_CAPTURE:
-        case MoveKind::QUEEN_PROMOTION_CAPTURE:
-            toggle(target, mv.to.index());  // Remove the captured piece
-            toggle(piece, mv.to.index());   // Remove the pawn, add the promoted piece
-            toggle(addColor(promotionType(mv.kind), color(piece)), mv.to.index());
-            break;
-        }
+    void update(int depth, const MoveVector& pv) {
+        if (depth >= kMaxDepth) return;
+        length[depth] = std::min(int(pv.size()), kMaxDepth);
+        std::copy_n(pv.begin(), length[depth], moves[depth].begin());
     }
+} pvTable;
 
-    // Use toggle to add/remove a piece or non piece/location vector.
-    void toggle(Piece piece, int location) { toggle(index(piece) * kNumSquares + location); }
-    void toggle(int vector) { hash ^= hashVectors[vector]; }
-    void toggle(ExtraVectors extra) { toggle(kNumBoardVectors + int(extra)); }
-};
+MoveVector principalVariation(int depth) {
+    if (depth >= PVTable::kMaxDepth) return {};
+    return {pvTable.moves[depth].begin(), pvTable.moves[depth].begin() + pvTable.length[depth]};
+}
 
-struct HashTable {
-    static constexpr int kNumEntries = 1 << 20;
-    static constexpr int kNumBits = 20;
-    static constexpr int kNumMask = kNumEntries - 1;
+// Hashes of the positions of the game, followed by those on the path of the search
+static std::vector<uint64_t> repetitionStack;
 
-    struct Entry {
-        Hash hash;
-        EvaluatedMove move;
-    };
+void setGameHistory(const std::vector<Position>& history) {
+    repetitionStack.clear();
+    for (auto& position : history) repetitionStack.push_back(Hash(position)());
+}
 
-    std::array<Entry, kNumEntries> entries;
+/**
+ * Returns true if the position with the given hash, to be pushed on the repetition stack, repeats
+ * an earlier one. Only every other position has the same side to move, and none before the last
+ * irreversible move, as given by the halfmove clock, can be the same.
+ */
+static bool isRepetition(Hash hash, int halfmoveClock) {
+    auto size = int(repetitionStack.size());
+    for (int distance = 2; distance <= std::min(halfmoveClock, size); distance += 2)
+        if (repetitionStack[size - distance] == hash()) return true;
+    return false;
+}
 
-    EvaluatedMove* find(Hash hash) {
-        auto& entry = entries[hash() & kNumMask];
-        if (entry.hash() == hash()) return &entry.move;
-        return nullptr;
-    }
+static StopFunction stopFunction;
+static bool stopped = false;
+static uint64_t pollCount = 0;
 
-    void insert(Hash hash, EvaluatedMove move) {
-        auto& entry = entries[hash() & kNumMask];
-        entry.hash = hash;
-        entry.move = move;
-    }
-} hashTable;
+void setStopFunction(StopFunction stop) {
+    stopFunction = std::move(stop);
+    stopped = false;
+    pollCount = 0;
+}
+
+bool searchStopped() {
+    return stopped;
+}
+
+/**
+ * Counts the node and calls the stop function every kPollInterval nodes, so checking the clock
+ * doesn't slow the search.
+ */
+static bool pollStop() {
+    ++nodeCount;
+    if (!stopped && stopFunction && ++pollCount % kPollInterval == 0) stopped = stopFunction();
+    return stopped;
+}
+
+static std::ostream* searchLog = nullptr;
+static int searchLogMaxDepth = 0;
+static uint64_t searchLogRecords = 0;  // Records left to write
+
+void setSearchLog(std::ostream* log, int maxDepth, uint64_t maxRecords) {
+    searchLog = log;
+    searchLogMaxDepth = maxDepth;
+    searchLogRecords = maxRecords;
+}
+
+/**
+ * Writes a record of the search of the last position in moves to the search log, if enabled. The
+ * allMoves are in the order searched, but not all may have been searched if a mate was found.
+ */
+static void logNode(const ComputedMoveVector& moves,
+                    const ComputedMoveVector& allMoves,
+                    const EvaluatedMove& best,
+                    bool cached) {
+    int depth = moves.size();
+    if (!searchLog || depth > searchLogMaxDepth || !searchLogRecords) return;
+    --searchLogRecords;
+
+    auto& [move, position] = moves.back();
+    *searchLog << depth << " " << (move ? std::string(move) : "-") << " " << std::hex
+               << Hash(position)() << std::dec << " " << (best.move ? std::string(best.move) : "-")
+               << " " << best.evaluation;
+    if (cached)
+        *searchLog << " cached";
+    else
+        for (auto& [move, newPosition] : allMoves) *searchLog << " " << std::string(move);
+    *searchLog << "\n";
+}
+
+// Material of both sides in pawns at the start, and at most in the endgame, for the game phase
+static constexpr float kStartMaterial = 78;
+static constexpr float kEndgameMaterial = 17;
 
 // Values of pieces, in centipawns
 static std::array<int16_t, kNumPieces> pieceValues = {
@@ -219,16 +217,278 @@ static std::array<int16_t, kNumMoveKinds> moveValues = {
     900,  // 15 Queen promotion capture
 };
 
+/**
+ * Sets the value of the type of piece for both colors, and of promotions to it, in the tables.
+ */
+static void setPieceValue(decltype(pieceValues)& pieces,
+                          decltype(moveValues)& moves,
+                          PieceType type,
+                          int16_t value) {
+    pieces[index(addColor(type, Color::WHITE))] = value;
+    pieces[index(addColor(type, Color::BLACK))] = -value;
+    for (uint8_t kind = index(MoveKind::PROMOTION_MASK); kind < kNumMoveKinds; ++kind)
+        if (promotionType(MoveKind(kind)) == type) moves[kind] = value;
+}
+
+bool loadPieceValues(std::istream& in) {
+    auto pieces = pieceValues;
+    auto moves = moveValues;
+    std::string line;
+    while (std::getline(in, line)) {
+        std::istringstream fields(line.substr(0, line.find('#')));
+        char name;
+        int value;
+        if (!(fields >> name)) continue;  // Empty line or only a comment
+        auto piece = toPiece(std::toupper(name));
+        if (piece == Piece::NONE || type(piece) == PieceType::KING) return false;
+        if (!(fields >> value) || !(fields >> std::ws).eof()) return false;
+        if (value < 0 || value > std::numeric_limits<int16_t>::max()) return false;
+
+        setPieceValue(pieces, moves, type(piece), value);
+    }
+    pieceValues = pieces;
+    moveValues = moves;
+    return true;
+}
+
+void setPieceValues(const config::Params& params) {
+    for (uint8_t type = 0; type < index(PieceType::KING); ++type)
+        setPieceValue(pieceValues, moveValues, PieceType(type), params.pieceValues[type]);
+}
+
 uint64_t evalCount = 0;
+uint64_t nodeCount = 0;
 uint64_t cacheCount = 0;
+int selDepth = 0;
+/**
+ * Returns the number of moves a pawn needs to promote, if it is passed, nothing is in its way, and
+ * the opponent's king can't catch it, even when the opponent is to move, or std::nullopt otherwise.
+ * Only applies to pawn endings, where no other pieces can stop the pawn.
+ */
+static std::optional<int> unstoppablePawnMoves(const Board& board, Square pawn) {
+    auto color = ::color(board[pawn]);
+    auto enemyPawns = SquareSet::find(board, addColor(PieceType::PAWN, !color));
+    auto enemyKing = SquareSet::find(board, addColor(PieceType::KING, !color));
+    if (enemyKing.empty() || !(passedPawnMask(color, pawn) & enemyPawns).empty()) return {};
+    if (!(pawnFrontSpan(color, pawn) & SquareSet::occupancy(board)).empty()) return {};
+
+    auto promotion = Square(color == Color::WHITE ? kNumRanks - 1 : 0, pawn.file());
+    auto moves = std::abs(promotion.rank() - pawn.rank());
+    if (moves == kNumRanks - 2) --moves;  // The first move may advance two squares
+
+    // The rule of the square, with one extra move for the king in case the opponent is to move
+    if (kingDistance(*enemyKing.begin(), promotion) <= moves + 1) return {};
+    return moves;
+}
+
+/**
+ * Returns the bonus, in centipawns from white's point of view, for winning a pawn race in a pawn
+ * ending: the side whose unstoppable pawn promotes at least two moves before any of the opponent's
+ * pawns is likely to win, regardless of who is to move.
+ */
+static int pawnRace(const Board& board) {
+    std::array<int, 2> fastest = {kNumRanks, kNumRanks};  // Moves to promote, by color
+    auto pawns =
+        SquareSet::find(board, Piece::WHITE_PAWN) | SquareSet::find(board, Piece::BLACK_PAWN);
+    for (auto pawn : pawns) {
+        auto& moves = fastest[int(color(board[pawn]))];
+        if (auto unstoppable = unstoppablePawnMoves(board, pawn))
+            moves = std::min(moves, *unstoppable);
+    }
+
+    // Worth a queen, less the pawn it promotes from
+    int bonus = moveValues[index(MoveKind::QUEEN_PROMOTION)] - pieceValues[index(Piece::WHITE_PAWN)];
+    if (fastest[0] < fastest[1] - 1) return bonus;
+    if (fastest[1] < fastest[0] - 1) return -bonus;
+    return 0;
+}
+
+/**
+ * Returns how far the game is into the endgame, from 0 with all pieces on the board to 1 in the
+ * endgame, as used for blending middle game and endgame scores.
+ */
+static float endgameWeight(const Board& board) {
+    return (1 - gamePhase(board)) / (1 - kEndgameMaterial / kStartMaterial);
+}
+
+// Bonuses for a passed pawn by its rank, counting from its own side, in centipawns. Passed pawns
+// are worth more in the endgame, when fewer pieces can stop them.
+static constexpr std::array<int, kNumRanks> kPassedPawnMiddlegame = {0, 5, 5, 10, 20, 35, 60, 0};
+static constexpr std::array<int, kNumRanks> kPassedPawnEndgame = {0, 10, 15, 25, 40, 65, 100, 0};
+
+/**
+ * Returns the score of the passed pawns, in centipawns from white's point of view. Besides the bonus
+ * by rank, which is halved when an opponent's piece blocks the pawn, advanced pawns get more for a
+ * free path to promotion and a rook of their own behind them. In the endgame, the opponent's king
+ * should be far from the square in front of the pawn, and to a lesser extent, the own king close.
+ */
+static int passedPawns(const Board& board) {
+    auto occupied = SquareSet::occupancy(board);
+    int middlegame = 0;
+    int endgame = 0;
+    for (auto color : {Color::WHITE, Color::BLACK}) {
+        auto enemyPawns = SquareSet::find(board, addColor(PieceType::PAWN, !color));
+        auto rooks = SquareSet::find(board, addColor(PieceType::ROOK, color));
+        auto ownKing = SquareSet::find(board, addColor(PieceType::KING, color));
+        auto enemyKing = SquareSet::find(board, addColor(PieceType::KING, !color));
+        int sign = color == Color::WHITE ? 1 : -1;
+        for (auto pawn : SquareSet::find(board, addColor(PieceType::PAWN, color))) {
+            if (!(passedPawnMask(color, pawn) & enemyPawns).empty()) continue;
+
+            int rank = color == Color::WHITE ? pawn.rank() : kNumRanks - 1 - pawn.rank();
+            int weight = rank - 1;  // From 0 on the starting rank to 5 on the seventh
+            Square stop(pawn.rank() + sign, pawn.file());
+            int bonus = 0;  // Same in the middle game and endgame
+            bool blockaded = isColor(board[stop], !color);
+            if (!blockaded && (pawnFrontSpan(color, pawn) & occupied).empty()) bonus += 5 * weight;
+            for (auto rook : rooks & pawnFrontSpan(!color, pawn))
+                if ((SquareSet::path(rook, pawn) & occupied).empty()) bonus += 4 * weight;
+
+            int kings = 0;  // Only in the endgame
+            if (!ownKing.empty() && !enemyKing.empty())
+                kings = (3 * kingDistance(*enemyKing.begin(), stop) -
+                         kingDistance(*ownKing.begin(), stop)) *
+                    weight;
+
+            int divisor = blockaded ? 2 : 1;
+            middlegame += sign * (kPassedPawnMiddlegame[rank] / divisor + bonus);
+            endgame += sign * (kPassedPawnEndgame[rank] / divisor + bonus + kings);
+        }
+    }
+    if (!middlegame && !endgame) return 0;
+
+    // Blend the scores by the game phase, from the middle game at the start to the endgame
+    return std::lround(middlegame + (endgame - middlegame) * endgameWeight(board));
+}
+
+// Bonuses for each king move closer to the opponent's king, by piece type, in centipawns
+static constexpr std::array<int, kNumPiecesTypes> kTropismWeights = {0, 3, 2, 2, 5, 0};
+
+/**
+ * Returns the score for pieces close to the opponent's king, in centipawns from white's point of
+ * view. Nearby pieces may help an attack on the king, which matters less as pieces come off the
+ * board, so the score is phased out in the endgame.
+ */
+static int kingTropism(const Board& board) {
+    auto whiteKing = SquareSet::find(board, Piece::WHITE_KING);
+    auto blackKing = SquareSet::find(board, Piece::BLACK_KING);
+    if (whiteKing.empty() || blackKing.empty()) return 0;
+
+    int tropism = 0;
+    for (auto square : SquareSet::occupancy(board)) {
+        auto piece = board[square];
+        auto weight = kTropismWeights[index(type(piece))];
+        if (!weight) continue;
+        bool white = color(piece) == Color::WHITE;
+        auto distance = kingDistance(square, *(white ? blackKing : whiteKing).begin());
+        tropism += (white ? 1 : -1) * weight * (kNumRanks - 1 - distance);
+    }
+    return tropism ? std::lround(tropism * (1 - endgameWeight(board))) : 0;
+}
+
 float evaluateBoard(const Board& board) {
     int32_t value = 0;
+    bool pawnEnding = true;  // Only kings and pawns
 
-    for (auto piece : board.squares()) value += pieceValues[index(piece)];
+    for (auto square : SquareSet::occupancy(board)) {
+        auto piece = board[square];
+        value += pieceValues[index(piece)];
+        pawnEnding &= type(piece) == PieceType::PAWN || type(piece) == PieceType::KING;
+    }
+    if (pawnEnding) value += pawnRace(board);
+    value += passedPawns(board);
+    value += kingTropism(board);
 
     return value / 100.0f;
 }
 
+float gamePhase(const Board& board) {
+    // Material of both sides in pawns, with a lower bound for the endgame
+    int32_t material = 0;
+    for (auto square : SquareSet::occupancy(board))
+        material += std::abs(pieceValues[index(board[square])]);
+    return std::clamp(material / 100.0f, kEndgameMaterial, kStartMaterial) / kStartMaterial;
+}
+
+int staticExchange(const Board& board, Move move) {
+    // Kings capture last, as any recapture would win the king
+    static constexpr int kKingValue = 100 * 100;
+    auto value = [](Piece piece) {
+        return type(piece) == PieceType::KING ? kKingValue : std::abs(pieceValues[index(piece)]);
+    };
+
+    auto after = board;
+    auto piece = board[move.from];
+    auto side = color(piece);
+    std::array<int, kNumSquares> gains;  // Material gained so far, by the side making each capture
+    gains[0] = value(board[move.to]);
+    if (move.kind == MoveKind::EN_PASSANT) {
+        gains[0] = value(addColor(PieceType::PAWN, !side));
+        after[Square(move.from.rank(), move.to.file())] = Piece::NONE;
+    }
+    if (move.isPromotion()) {
+        piece = addColor(promotionType(move.kind), side);
+        gains[0] += value(piece) - value(board[move.from]);
+    }
+    after[move.from] = Piece::NONE;
+    after[move.to] = piece;
+
+    // Capture with the least valuable attacker each turn, including those behind earlier ones
+    int depth = 0;
+    for (side = !side;; side = !side) {
+        auto occupancy = SquareSet::occupancy(after);
+        std::optional<Square> attacker;
+        for (auto from : occupancy) {
+            auto candidate = after[from];
+            if (color(candidate) != side || !possibleCaptures(candidate, from).contains(move.to) ||
+                !(SquareSet::path(from, move.to) & occupancy).empty())
+                continue;
+            if (!attacker || value(candidate) < value(after[*attacker])) attacker = from;
+        }
+        if (!attacker) break;
+        ++depth;
+        gains[depth] = value(after[move.to]) - gains[depth - 1];
+        after[move.to] = after[*attacker];
+        after[*attacker] = Piece::NONE;
+    }
+
+    // Each side may stop capturing when continuing would lose material
+    for (; depth > 0; --depth) gains[depth - 1] = -std::max(-gains[depth - 1], gains[depth]);
+    return gains[0];
+}
+
+EvaluationTrace traceEvaluation(const Board& board) {
+    EvaluationTrace trace;
+    bool pawnEnding = true;
+    for (auto square : SquareSet::occupancy(board)) {
+        auto value = pieceValues[index(board[square])];
+        trace.squares[square.index()] = value;
+        (value > 0 ? trace.whiteMaterial : trace.blackMaterial) += value;
+        auto type = ::type(board[square]);
+        pawnEnding &= type == PieceType::PAWN || type == PieceType::KING;
+    }
+    if (pawnEnding) trace.pawnRace = pawnRace(board);
+    trace.passedPawns = passedPawns(board);
+    trace.kingTropism = kingTropism(board);
+    trace.phase = gamePhase(board);
+    trace.evaluation = evaluateBoard(board);
+    return trace;
+}
+
+WinDrawLoss winDrawLoss(float evaluation, const Board& board) {
+    if (evaluation == bestEval) return {1000, 0, 0};
+    if (evaluation == worstEval) return {0, 0, 1000};
+
+    auto phase = gamePhase(board);
+
+    // The evaluation at which the win chance is 50%, and how fast it changes around it
+    auto even = 0.5f + 0.5f * phase;
+    auto spread = even / 4;
+    auto win = int(1000 / (1 + std::exp((even - evaluation) / spread)));
+    auto loss = int(1000 / (1 + std::exp((even + evaluation) / spread)));
+    return {win, 1000 - win - loss, loss};
+}
+
 bool improveMove(EvaluatedMove& best, const EvaluatedMove& ourMove) {
     auto indent = debug ? std::string(ourMove.depth * 4 - 4, ' ') : "";
     bool improved = best < ourMove;
@@ -241,65 +501,201 @@ bool improveMove(EvaluatedMove& best, const EvaluatedMove& ourMove) {
     return ourMove.mate && ourMove.check;
 }
 
-EvaluatedMove computeBestMove(ComputedMoveVector& moves, int maxdepth) {
+/**
+ * Orders the moves for the search, so the best ones are likely searched first and cause cutoffs:
+ * captures of the most valuable victim by the least valuable attacker (MVV/LVA) first, then
+ * promotions, then quiet moves in the order generated.
+ */
+static void orderMoves(const Board& board, ComputedMoveVector& moves) {
+    auto score = [&](Move move) {
+        int victim = std::abs(pieceValues[index(board[move.to])]);
+        if (move.kind == MoveKind::EN_PASSANT) victim = pieceValues[index(Piece::WHITE_PAWN)];
+        int attacker = std::abs(pieceValues[index(board[move.from])]);
+        if (!victim && !move.isPromotion()) return 0;
+        return 16 * (victim + moveValues[index(move.kind)]) - attacker / 100 + 1;
+    };
+    std::stable_sort(moves.begin(), moves.end(), [&](auto& lhs, auto& rhs) {
+        return score(lhs.first) > score(rhs.first);
+    });
+}
+
+/**
+ * Returns true if the move changes the material on the board, so quiescence search looks at it.
+ */
+static bool isTactical(const Board& board, Move move) {
+    return board[move.to] != Piece::NONE || move.kind == MoveKind::EN_PASSANT || move.isPromotion();
+}
+
+/**
+ * Returns the value of the best move so far for alpha-beta bounds, or alpha if there is none yet.
+ */
+static float bound(const EvaluatedMove& best, float alpha) {
+    return best.move ? std::max(alpha, best.evaluation) : alpha;
+}
+
+/**
+ * Returns true if the cached entry decides the search with the given window: exact results always
+ * do, while bounds only do if they are strictly outside the window, like moves that are cut off.
+ */
+static bool usable(const TranspositionTable<EvaluatedMove>::Entry& entry, float alpha, float beta) {
+    switch (entry.bound) {
+    case Bound::EXACT: return true;
+    case Bound::LOWER: return entry.value.evaluation > beta;
+    case Bound::UPPER: return entry.value.move && entry.value.evaluation < alpha;
+    default: return false;
+    }
+}
+
+// Bonus for positions the tablebases show as won, in pawns. It exceeds any material advantage, so
+// the search heads for won endings and avoids lost ones, but stays well below a mate.
+static constexpr float kTablebaseWin = 200;
+
+/**
+ * Returns the static evaluation of the position from the perspective of the side to move. Endings
+ * in the tablebases are scored by their outcome: drawn ones as a draw, and won or lost ones by
+ * adding the bonus for a win to the evaluation of the board, so the search still makes progress
+ * towards the mate.
+ */
+static float evaluatePosition(const Position& position) {
+    auto evaluation = evaluateBoard(position.board);
+    if (position.activeColor == Color::BLACK) evaluation = -evaluation;
+    auto wdl = tablebase::probe(position);
+    if (!wdl) return evaluation;
+    switch (*wdl) {
+    case tablebase::WDL::WIN: return evaluation + kTablebaseWin;
+    case tablebase::WDL::LOSS: return evaluation - kTablebaseWin;
+    default: return drawEval;
+    }
+}
+
+/**
+ * Searches the position beyond the maximum depth, where the side to move may stand pat: quiet
+ * moves all evaluate to the static evaluation of the position, and only captures and promotions
+ * are searched further, so evaluations aren't taken in the middle of an exchange. Without quiet
+ * moves, the side to move has to make one of the others.
+ */
+static EvaluatedMove quiesce(ComputedMoveVector& moves,
+                             ComputedMoveVector& allMoves,
+                             int maxdepth,
+                             float alpha,
+                             float beta) {
+    auto position = moves.back().second;
+    int depth = moves.size();
+    EvaluatedMove best;
+
+    for (auto& [move, newPosition] : allMoves) {
+        if (isTactical(position.board, move)) continue;
+        ++evalCount;
+        auto standPat = evaluatePosition(position);
+        improveMove(best, {move, false, false, standPat, depth});
+        if (standPat > beta) return best;
+        break;
+    }
+
+    orderMoves(position.board, allMoves);
+    for (auto& computedMove : allMoves) {
+        if (!isTactical(position.board, computedMove.first)) break;
+        auto ourMove = evaluateMove(moves, computedMove, maxdepth, bound(best, alpha), beta);
+        bool mate = improveMove(best, ourMove);
+        if (mate || stopped || best.evaluation > beta) break;
+    }
+    return best;
+}
+
+EvaluatedMove computeBestMove(ComputedMoveVector& moves, int maxdepth, float alpha, float beta) {
     auto position = moves.back().second;
     auto allMoves = allLegalMoves(position);
     EvaluatedMove best;  // Default to the worst possible move
     int depth = moves.size();
     auto indent = debug ? std::string(depth * 4 - 4, ' ') : "";
 
-    // Base case: if depth is zero, return the static evaluation of the position
+    selDepth = std::max(selDepth, depth);
+    pvTable.clear(depth);
+    if (pollStop()) return best;
+
+    // Base case: beyond the maximum depth, only search captures and promotions
     if (depth > maxdepth) {
-        auto currentEval = evaluateBoard(position.board);
-        for (auto& [move, newPosition] : allMoves) {
-            ++evalCount;
-            auto newEval = currentEval;
-            if (move.kind >= MoveKind::CAPTURE)
-                newEval -= pieceValues[index(newPosition.board[move.to])];
-            if (position.activeColor == Color::BLACK) newEval = -newEval;
-            newEval += moveValues[index(move.kind)];
-            EvaluatedMove ourMove{move, false, false, newEval, depth};
-            improveMove(best, ourMove);
-        }
+        best = quiesce(moves, allMoves, maxdepth, alpha, beta);
+        logNode(moves, allMoves, best, false);
         return best;
     }
 
     Hash hash(position);
-    auto cachedMove = hashTable.find(hash);
-    if (cachedMove) {
+    int draft = maxdepth - depth;
+    auto entry = hashTable.probe(hash);
+    if (entry && entry->draft == draft && usable(*entry, alpha, beta)) {
         ++cacheCount;
-        D << indent << "cached " << *cachedMove << std::endl;
-        return *cachedMove;
+        D << indent << "cached " << entry->value << std::endl;
+        if (entry->bound == Bound::EXACT)
+            pvTable.update(depth, principalVariation(position, depth, maxdepth));
+        logNode(moves, allMoves, entry->value, true);
+        return entry->value;
     }
 
-    // TODO: Sort moves by Most Valuable Victim (MVV) / Least Valuable Attacker (LVA)
-
-    // Recursive case: compute all legal moves and evaluate them
-    auto opponentKing =
-        SquareSet::find(position.board, addColor(PieceType::KING, !position.activeColor));
+    // Recursive case: evaluate all legal moves, until one is better than the opponent allows. Only
+    // moves strictly outside the window are cut off, so evaluations within it, including the depth
+    // used to prefer faster mates, are the same as without pruning. The best move found for this
+    // position before, at any depth, is tried first.
+    orderMoves(position.board, allMoves);
+    if (entry && entry->value.move) {
+        auto it = std::find_if(allMoves.begin(), allMoves.end(), [&](auto& computedMove) {
+            return computedMove.first == entry->value.move;
+        });
+        if (it != allMoves.end()) std::rotate(allMoves.begin(), it, it + 1);
+    }
+    bool cutoff = false;
     for (auto& computedMove : allMoves) {
-        // Recursively compute the best moves for the opponent, worst for us.
-        auto move = computedMove.first;
-        auto& newPosition = computedMove.second;
-        moves.push_back(computedMove);
-        auto opponentMove = -computeBestMove(moves, maxdepth);
-        moves.pop_back();
-
-        bool mate = !opponentMove.move;  // Either checkmate or stalemate
-        bool check = isAttacked(newPosition.board, opponentKing);
-
-        char kind[2][2] = {{' ', '='}, {'+', '#'}};  // {{check, mate}, {check, mate}}
-
-        float evaluation = mate ? (check ? bestEval : drawEval) : opponentMove.evaluation;
-        EvaluatedMove ourMove(
-            move, check, mate, evaluation, mate ? moves.size() : opponentMove.depth);
-        if (improveMove(best, ourMove)) break;
+        auto ourMove = evaluateMove(moves, computedMove, maxdepth, bound(best, alpha), beta);
+        bool mate = improveMove(best, ourMove);
+        if (best.move == computedMove.first) pvTable.update(depth, best.move);
+        cutoff = best.evaluation > beta;
+        if (mate || stopped || cutoff) break;
     }
-    // Cache the best move for this position
-    hashTable.insert(hash, best);
+
+    // Cache the best move for this position, unless the search was stopped before completing. The
+    // evaluation is only a lower bound if some moves were cut off, or an upper bound if no move
+    // reached alpha.
+    if (stopped) return best;
+    logNode(moves, allMoves, best, false);
+    auto kind = cutoff ? Bound::LOWER : Bound::EXACT;
+    if (best.move && best.evaluation < alpha) kind = Bound::UPPER;
+    hashTable.store(hash, draft, kind, best);
     return best;
 }
 
+EvaluatedMove evaluateMove(ComputedMoveVector& moves,
+                           const ComputedMove& computedMove,
+                           int maxdepth,
+                           float alpha,
+                           float beta) {
+    // Recursively compute the best moves for the opponent, worst for us.
+    auto move = computedMove.first;
+    auto& newPosition = computedMove.second;
+    Hash hash(newPosition);
+    bool check = isInCheck(newPosition);
+    if (isRepetition(hash, newPosition.halfmoveClock)) {
+        pvTable.clear(moves.size() + 1);
+        return {move, check, false, drawEval, int(moves.size())};
+    }
+    // Drawn endings need no search. Won and lost ones still do, to find the mate and make progress,
+    // with the outcome from the tablebases added to the static evaluation, see evaluatePosition.
+    if (tablebase::probe(newPosition) == tablebase::WDL::DRAW) {
+        pvTable.clear(moves.size() + 1);
+        return {move, check, false, drawEval, int(moves.size())};
+    }
+
+    repetitionStack.push_back(hash());
+    moves.push_back(computedMove);
+    auto opponentMove = -computeBestMove(moves, maxdepth, -beta, -alpha);
+    moves.pop_back();
+    repetitionStack.pop_back();
+
+    bool mate = !opponentMove.move;  // Either checkmate or stalemate
+
+    float evaluation = mate ? (check ? bestEval : drawEval) : opponentMove.evaluation;
+    return {move, check, mate, evaluation, int(mate ? moves.size() : opponentMove.depth)};
+}
+
 uint64_t perft(Position position, int depth) {
     if (depth <= 0) return 1;
     uint64_t nodes = 0;
@@ -309,3 +705,15 @@ uint64_t perft(Position position, int depth) {
     }
     return nodes;
 }
+
+uint64_t perft(Position position, int depth, TranspositionTable<uint64_t>& table) {
+    if (depth <= 1) return depth <= 0 ? 1 : allLegalMoves(position).size();
+    Hash hash(position);
+    auto entry = table.probe(hash);
+    if (entry && entry->draft == depth) return entry->value;
+    uint64_t nodes = 0;
+    for (auto& [move, newPosition] : allLegalMoves(position))
+        nodes += perft(newPosition, depth - 1, table);
+    table.store(hash, depth, Bound::EXACT, nodes);
+    return nodes;
+}
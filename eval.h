@@ -1,11 +1,18 @@
+#include <functional>
+#include <limits>
 #include <map>
 #include <sstream>
 
 #include "common.h"
+#include "config.h"
+#include "hash.h"
+#include "moves.h"
 
-static float worstEval = -999;
-static float drawEval = 0;
-static float bestEval = 999;
+#pragma once
+
+constexpr float worstEval = -999;
+constexpr float drawEval = 0;
+constexpr float bestEval = 999;
 
 /**
  * The evaluation is always from the perspective of the active color. Higher evaluations are better,
@@ -42,29 +49,172 @@ struct EvaluatedMove {
     }
 };
 
+/**
+ * The expected outcome of a game, in per mille, from the perspective of the side to move.
+ */
+struct WinDrawLoss {
+    int win;
+    int draw;
+    int loss;
+
+    float expectedScore() const { return (win + draw / 2.0f) / 1000; }
+};
+
+/**
+ * Estimates the chances to win, draw or lose with the given evaluation, using a logistic model.
+ * With less material on the board, the same advantage is more likely to win, so the model scales
+ * with the material: with all pieces on the board, being a pawn ahead wins half the games.
+ */
+WinDrawLoss winDrawLoss(float evaluation, const Board& board);
+
 extern uint64_t evalCount;
+extern uint64_t nodeCount;  // Number of positions searched by computeBestMove
 extern uint64_t cacheCount;
+extern int selDepth;  // Maximum depth reached by computeBestMove, including quiescence search
 
 /**
  * This function iterates over each square in the board, uses the pieceValues map to find
  * the value of the piece on that square, and adjusts the total value accordingly. White
  * pieces have positive values, and black pieces have negative values, so the returned value
  * represents the advantage to the white player: positive for white's advantage, negative
- * for black's advantage.
+ * for black's advantage. In pawn endings, a pawn that the opponent's king can't catch, and that
+ * promotes at least two moves before any of the opponent's, adds the value of a queen. Passed
+ * pawns get a bonus, which is larger in the endgame, for how advanced and unhindered they are.
+ * Before the endgame, pieces close to the opponent's king get a bonus too.
  */
 float evaluateBoard(const Board& board);
 
+/**
+ * Returns the material the side to move gains with the move, in centipawns, if both sides then
+ * keep capturing on its target square with their least valuable piece for as long as that gains
+ * material. This static exchange evaluation (SEE) is negative for moves losing material, and zero
+ * for quiet moves to safe squares. Pins and checks are ignored.
+ */
+int staticExchange(const Board& board, Move move);
+
+/**
+ * The game phase, from 1 with all pieces on the board down to 17/78 in the endgame, where at most
+ * a rook and a pawn or so are left besides the kings. It is based on the material of both sides.
+ */
+float gamePhase(const Board& board);
+
+/**
+ * A breakdown of the static evaluation of a board, for debugging. The contributions of the pieces
+ * on each square, the material, the pawn race bonus of pawn endings, the score of passed pawns and
+ * the king tropism are in centipawns, from white's point of view.
+ */
+struct EvaluationTrace {
+    std::array<int, kNumSquares> squares = {};  // Contribution of the piece on each square
+    int whiteMaterial = 0;
+    int blackMaterial = 0;  // Negative, like the contributions of black pieces
+    int pawnRace = 0;       // Bonus for a pawn that promotes well before the opponent's
+    int passedPawns = 0;    // Score of the passed pawns
+    int kingTropism = 0;    // Score for pieces close to the opponent's king
+    float phase = 1;
+    float evaluation = 0;  // As returned by evaluateBoard
+};
+EvaluationTrace traceEvaluation(const Board& board);
+
+/**
+ * Reads piece values in centipawns, replacing the built-in ones, from lines like "N 320", with
+ * the piece letter followed by its value, and anything after a '#' ignored. Pieces not listed keep
+ * their value, promotions are worth the value of the piece promoted to, and kings have no value.
+ * Returns false, changing no values, if a line can't be parsed. Clear the hash table after changing
+ * values, as cached evaluations use the old ones.
+ */
+bool loadPieceValues(std::istream& in);
+
+/**
+ * Sets the values of all pieces, and of promotions to them, to those of the parameters. As with
+ * loadPieceValues, clear the hash table after changing values.
+ */
+void setPieceValues(const config::Params& params);
+
+// Bounds of the alpha-beta window when searching without one
+static constexpr float kNoAlpha = -std::numeric_limits<float>::infinity();
+static constexpr float kNoBeta = std::numeric_limits<float>::infinity();
+
 /**
  * Evaluates the best moves from a given chess position up to a certain depth.
- * Each move is evaluated based on the static evaluation of the board or by recursive calls
- * to this function, decreasing the depth until it reaches zero. It also accounts for checkmate
- * and stalemate situations.
+ * Each move is evaluated by recursive calls to this function, decreasing the depth until it reaches
+ * zero, followed by a quiescence search of captures and promotions, so the static evaluation isn't
+ * taken in the middle of an exchange. It also accounts for checkmate and stalemate situations.
+ * Endings in the loaded tablebases are scored by their outcome: drawn ones aren't searched, and won
+ * or lost ones get a large bonus or penalty, still below a mate, on top of the static evaluation.
+ * Captures are searched first, most valuable victim first. With an alpha-beta window, moves
+ * evaluating strictly below alpha or above beta are cut off, as the caller won't choose them, and
+ * the result is only a bound. Evaluations within the window are the same as without pruning.
  *
  * @param position The current chess position to evaluate.
  * @param depth The depth to which the evaluation should be performed.
+ * @param alpha The evaluation the side to move already has elsewhere.
+ * @param beta The evaluation the opponent already has elsewhere, negated.
  * @return A map of moves to their evaluation score.
  */
-EvaluatedMove computeBestMove(ComputedMoveVector& moves, int depth);
+EvaluatedMove computeBestMove(ComputedMoveVector& moves,
+                              int depth,
+                              float alpha = kNoAlpha,
+                              float beta = kNoBeta);
+
+/**
+ * Evaluates a single legal move from the last position in the moves vector, by searching the best
+ * reply of the opponent up to the given maximum depth. This is the evaluation computeBestMove
+ * uses to rank the moves, exposed so that callers can compare the alternatives. The window is from
+ * the perspective of the side making the move, as for computeBestMove.
+ */
+EvaluatedMove evaluateMove(ComputedMoveVector& moves,
+                           const ComputedMove& move,
+                           int maxdepth,
+                           float alpha = kNoAlpha,
+                           float beta = kNoBeta);
+
+/**
+ * Returns the principal variation from a position at the given depth of a search to maxdepth, by
+ * following the best moves cached in the hash table. The variation ends early where entries have
+ * been replaced.
+ */
+MoveVector principalVariation(Position position, int depth, int maxdepth);
+
+/**
+ * Returns the principal variation from the given depth of the last search, as kept in a triangular
+ * table during the search. Where the search used cached results, the variation continues with the
+ * moves from the hash table, as above.
+ */
+MoveVector principalVariation(int depth);
+
+/**
+ * Sets the positions of the game, oldest first and ending with the position to search. Moves that
+ * repeat any of these positions, or a position earlier in the search, are evaluated as a draw, as
+ * the opponent can keep repeating. Only positions since the last capture or pawn move can repeat.
+ */
+void setGameHistory(const std::vector<Position>& history);
+
+/**
+ * Clears the cached search results, such as when starting a new game. This makes searches
+ * independent of any earlier ones.
+ */
+void clearHashTable();
+
+/**
+ * A function that computeBestMove calls periodically to check whether to stop searching. Once it
+ * returns true, searchStopped() is true and the search unwinds as quickly as possible, returning
+ * an incomplete result that must be discarded. Setting a new function resets the stopped state.
+ */
+using StopFunction = std::function<bool()>;
+static constexpr uint64_t kPollInterval = 1024;  // Nodes between calls of the stop function
+void setStopFunction(StopFunction stop);
+bool searchStopped();
+
+/**
+ * For debugging, writes a record of each node computeBestMove searches to the log, up to the given
+ * depth and number of records. Passing a null log stops logging. Records are written once all
+ * children of the node are done, one per line:
+ *
+ *     <depth> <move to the node> <hash> <best move> <evaluation> [cached | <moves in order>]
+ *
+ * As children come right before their parent, with one more depth, the tree can be reconstructed.
+ */
+void setSearchLog(std::ostream* log, int maxDepth = 4, uint64_t maxRecords = 1'000'000);
 
 /**
  *  a debugging function to walk the move generation tree of strictly legal moves to count all the
@@ -72,3 +222,9 @@ EvaluatedMove computeBestMove(ComputedMoveVector& moves, int depth);
  *  bugs. (See https://www.chessprogramming.org/Perft)
  */
 uint64_t perft(Position position, int depth);
+
+/**
+ * Like the above, but caches the counts of subtrees in the table, so transpositions are only
+ * counted once. Much faster for larger depths, but no longer a measure of move generation speed.
+ */
+uint64_t perft(Position position, int depth, TranspositionTable<uint64_t>& table);
@@ -1,11 +1,13 @@
 #include <chrono>
 #include <cstdlib>  // For std::exit
 #include <iostream>
+#include <sstream>
 #include <string>
 
 #include "eval.h"
 #include "fen.h"
 #include "moves.h"
+#include "tablebase.h"
 
 std::ostream& operator<<(std::ostream& os, const MoveVector& moves) {
     os << "[";
@@ -86,7 +88,6 @@ void testFromStdIn(int depth) {
         // Parse the FEN string into a Position
         std::cerr << fen << std::endl;
         Position position = fen::parsePosition(fen);
-        auto startTime = std::chrono::high_resolution_clock::now();
 
         // Print the board in grid notation
         printBoard(std::cerr, position.board);
@@ -121,6 +122,231 @@ void testEvaluatedMove() {
     std::cout << "EvaluatedMove tests passed" << std::endl;
 }
 
+void testWinDrawLoss() {
+    auto start = fen::parsePosition(fen::initialPosition).board;
+    auto endgame = fen::parsePosition("8/4k3/8/3p4/3P4/4K3/4P3/8 w - - 0 1").board;
+    for (auto board : {start, endgame}) {
+        auto even = winDrawLoss(0, board);
+        assert(even.win == even.loss && even.expectedScore() == 0.5f);
+        auto ahead = winDrawLoss(1, board), behind = winDrawLoss(-1, board);
+        assert(ahead.win == behind.loss && ahead.loss == behind.win && ahead.win > even.win);
+        assert(ahead.win + ahead.draw + ahead.loss == 1000);
+    }
+    assert(winDrawLoss(1, start).win == 500);
+    assert(winDrawLoss(1, endgame).win > winDrawLoss(1, start).win);
+    assert(winDrawLoss(bestEval, start).win == 1000 && winDrawLoss(worstEval, start).loss == 1000);
+    std::cout << "WinDrawLoss tests passed" << std::endl;
+}
+
+void testLoadPieceValues() {
+    auto board = fen::parsePosition("4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1").board;
+    assert(evaluateBoard(board) == 6);
+
+    std::istringstream values("# Tuned values\nN 320\n\nb 330  # Bishops are lowercase too\n");
+    assert(loadPieceValues(values));
+    assert(evaluateBoard(board) == 6.5f);
+
+    // Unknown pieces, kings, missing or negative values and trailing text change nothing
+    for (auto invalid : {"X 100", "K 100", "N", "N -300", "N 300 extra", "N 40000"}) {
+        std::istringstream in(std::string("P 200\n") + invalid);
+        assert(!loadPieceValues(in));
+        assert(evaluateBoard(board) == 6.5f);
+    }
+
+    std::istringstream defaults("N 300\nB 300");
+    assert(loadPieceValues(defaults));
+    assert(evaluateBoard(board) == 6);
+
+    config::Params params;
+    params.pieceValues[index(PieceType::BISHOP)] = 350;
+    setPieceValues(params);
+    assert(evaluateBoard(board) == 6.5f);
+    setPieceValues({});
+    assert(evaluateBoard(board) == 6);
+    std::cout << "Piece value tests passed" << std::endl;
+}
+
+void testTraceEvaluation() {
+    auto trace = traceEvaluation(fen::parsePosition(fen::initialPosition).board);
+    assert(trace.squares[Square(0, 3).index()] == 900);
+    assert(trace.squares[Square(7, 0).index()] == -500);
+    assert(trace.squares[Square(3, 3).index()] == 0);
+    assert(trace.whiteMaterial == 3900 && trace.blackMaterial == -3900);
+    assert(trace.phase == 1 && trace.evaluation == 0);
+
+    auto board = fen::parsePosition("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").board;
+    trace = traceEvaluation(board);
+    assert(trace.whiteMaterial == 500 && trace.blackMaterial == 0);
+    assert(trace.phase == gamePhase(board) && trace.phase == 17 / 78.0f);
+    assert(trace.evaluation == evaluateBoard(board));
+    std::cout << "Trace evaluation tests passed" << std::endl;
+}
+
+void testPawnRace() {
+    // White's pawn runs, as the black king is outside its square even when black is to move
+    auto board = fen::parsePosition("8/8/8/8/5P2/8/8/k3K3 w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == 800);
+    // Just outside the square, the king catches the pawn if it's to move, so there is no bonus
+    board = fen::parsePosition("8/8/8/8/5P2/k7/8/4K3 w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == 0);
+
+    // A pawn on its starting rank can advance two squares
+    board = fen::parsePosition("7K/1p6/8/8/8/8/8/4k3 w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == -800);
+
+    // The pawn may also be stopped by a blocker or an opposing pawn
+    board = fen::parsePosition("8/8/8/5K2/5P2/8/8/k7 w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == 0);
+    board = fen::parsePosition("8/6p1/8/8/5P2/8/8/k3K3 w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == 0);
+
+    // Of two runners, the faster one wins if it's two moves ahead, and it's a toss-up otherwise
+    board = fen::parsePosition("8/7K/8/6p1/1P6/8/k7/8 w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == 0);
+    board = fen::parsePosition("8/7K/8/8/1P6/6p1/k7/8 w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == -800);
+
+    // Other pieces can stop the pawn
+    board = fen::parsePosition("8/8/8/8/5P2/8/8/k3K2n w - - 0 1").board;
+    assert(traceEvaluation(board).pawnRace == 0);
+    std::cout << "Pawn race tests passed" << std::endl;
+}
+
+void testPassedPawns() {
+    auto passed = [](const std::string& fen) {
+        return traceEvaluation(fen::parsePosition(fen).board).passedPawns;
+    };
+    assert(passed(fen::initialPosition) == 0);
+    assert(passed("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1") == 10);  // Endgame bonus
+    assert(passed("4k3/4p3/8/8/8/8/8/4K3 w - - 0 1") == -10);
+    assert(passed("4k3/3p4/8/8/8/8/4P3/4K3 w - - 0 1") == 0);  // Neither is passed
+
+    // Advanced pawns are worth more, unless blockaded, and even more with a rook behind them
+    auto advanced = passed("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1");
+    assert(advanced > passed("4k3/8/8/8/8/3P4/8/4K3 w - - 0 1"));
+    assert(advanced > passed("4k3/8/3n4/3P4/8/8/8/4K3 w - - 0 1"));
+    assert(advanced < passed("4k3/8/8/3P4/8/8/8/3RK3 w - - 0 1"));
+
+    // In the endgame, the opponent's king should be far away, and the own king close
+    assert(passed("7k/8/8/3P4/8/8/8/4K3 w - - 0 1") > advanced);
+    assert(passed("4k3/8/4K3/3P4/8/8/8/8 w - - 0 1") > advanced);
+
+    // Passed pawns count less in the middle game
+    auto middlegame = "rnbqkbnr/pppppppp/8/8/8/8/PP3PPP/RNBQKBNR w - - 0 1";
+    assert(passed(middlegame) < 0 && passed(middlegame) > -10);
+    std::cout << "Passed pawn tests passed" << std::endl;
+}
+
+void testKingTropism() {
+    auto tropism = [](const std::string& fen) {
+        return traceEvaluation(fen::parsePosition(fen).board).kingTropism;
+    };
+    assert(tropism(fen::initialPosition) == 0);
+
+    // Developing a knight toward the opponent's king brings it two king moves closer
+    auto developed = "rnbqkbnr/pppppppp/8/8/8/2N5/PPPPPPPP/R1BQKBNR b KQkq - 1 1";
+    assert(tropism(developed) == 6);
+    assert(tropism("rnbqkb1r/pppppppp/5n2/8/8/2N5/PPPPPPPP/R1BQKBNR w KQkq - 2 2") == 0);
+
+    // Queens count more than knights, and nothing counts in the endgame
+    assert(tropism("rnbqkbnr/pppppppp/8/8/8/2Q5/PPPPPPPP/RNB1KBNR b KQkq - 1 1") > 6);
+    assert(tropism("4k3/8/8/3Q4/8/8/8/4K3 w - - 0 1") == 0);
+    std::cout << "King tropism tests passed" << std::endl;
+}
+
+void testStaticExchange() {
+    auto see = [](const std::string& fen,
+                  Square from,
+                  Square to,
+                  std::optional<PieceType> promotion = std::nullopt) {
+        auto board = fen::parsePosition(fen).board;
+        return staticExchange(board, inferMove(board, from, to, promotion));
+    };
+    assert(see("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", "e4"_sq, "d5"_sq) == 300);
+    assert(see("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1", "e4"_sq, "d5"_sq) == 200);
+    assert(see("4k3/2p5/3p4/8/8/8/8/3RK3 w - - 0 1", "d1"_sq, "d6"_sq) == -400);
+    assert(see("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", "d1"_sq, "d6"_sq) == 0);
+
+    // Pieces behind the first attacker join in, and either side stops when it would lose
+    assert(see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2"_sq, "d5"_sq) == 100);
+    assert(see("3rk3/3r4/8/3p4/8/8/3R4/4K3 w - - 0 1", "d2"_sq, "d5"_sq) == -400);
+    assert(see("3qk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2"_sq, "d5"_sq) == 100);
+
+    // Kings can only capture undefended pieces, and promotions gain the new piece
+    assert(see("4k3/8/8/3p4/4K3/8/8/8 w - - 0 1", "e4"_sq, "d5"_sq) == 100);
+    assert(see("4k3/8/2p5/3p4/4K3/8/8/8 w - - 0 1", "e4"_sq, "d5"_sq) < -100);
+    assert(see("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7"_sq, "b8"_sq, PieceType::QUEEN) == 1100);
+    assert(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5"_sq, "d6"_sq) == 100);
+    std::cout << "Static exchange tests passed" << std::endl;
+}
+
+void testSearch() {
+    auto search = [](const std::string& fen, int depth, float alpha, float beta) {
+        ComputedMoveVector moves;
+        moves.push_back({Move(), fen::parsePosition(fen)});
+        clearHashTable();
+        return computeBestMove(moves, depth, alpha, beta);
+    };
+
+    // Quiescence search sees the queen is lost to the recapture, even at the first ply
+    auto best = search("4k3/8/2p5/3n4/8/8/8/3QK3 w - - 0 1", 1, kNoAlpha, kNoBeta);
+    assert(std::string(best.move) != "d1d5" && best.evaluation > 4);
+
+    // Within the window, the result is the same as without one, and outside of it, only a bound
+    auto fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    auto full = search(fen, 3, kNoAlpha, kNoBeta);
+    auto windowed = search(fen, 3, full.evaluation - 1, full.evaluation + 1);
+    assert(windowed.move == full.move && windowed.evaluation == full.evaluation);
+    assert(search(fen, 3, kNoAlpha, full.evaluation - 1).evaluation > full.evaluation - 1);
+    assert(search(fen, 3, full.evaluation + 1, kNoBeta).evaluation < full.evaluation + 1);
+
+    // Bounds cached by windowed searches don't change the result of a full one
+    ComputedMoveVector moves = {{Move(), fen::parsePosition(fen)}};
+    computeBestMove(moves, 3, full.evaluation + 1, kNoBeta);
+    auto again = computeBestMove(moves, 3, kNoAlpha, kNoBeta);
+    assert(again.move == full.move && again.evaluation == full.evaluation);
+
+    std::cout << "All search tests passed!" << std::endl;
+}
+
+void testTablebase() {
+    auto search = [](const std::string& fen, int depth) {
+        ComputedMoveVector moves;
+        moves.push_back({Move(), fen::parsePosition(fen)});
+        clearHashTable();
+        return computeBestMove(moves, depth);
+    };
+
+    // Won and lost endings score well beyond their material, but below a mate
+    auto kqk = "4k3/8/8/8/8/8/8/3QK3 w - - 0 1";
+    auto material = search(kqk, 1).evaluation;
+    tablebase::add(*tablebase::generate("KQvK"));
+    auto won = search(kqk, 1);
+    assert(won.evaluation > material + 100 && won.evaluation < bestEval);
+    auto lost = search("4k3/8/8/8/8/8/8/3QK3 b - - 0 1", 1);
+    assert(lost.evaluation < -material - 100 && lost.evaluation > worstEval);
+
+    // Capturing the rook leaves a lost KQvK, so it's better to keep it on the board for now
+    auto fen = "3Rk3/8/8/8/8/8/8/Q3K3 b - - 0 1";
+    assert(std::string(search(fen, 1).move) != "e8d8");
+    tablebase::clear();
+    assert(std::string(search(fen, 1).move) == "e8d8");
+
+    std::cout << "All tablebase tests passed!" << std::endl;
+}
+
+void testHashedPerft() {
+    // The same counts, also when the table is too small for all positions
+    auto position = fen::parsePosition(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    TranspositionTable<uint64_t> table(4);
+    assert(perft(position, 3, table) == 97862);
+    assert(perft(position, 3, table) == 97862);
+    assert(perft(position, 1, table) == 48);
+
+    std::cout << "All hashed perft tests passed!" << std::endl;
+}
+
 int main(int argc, char* argv[]) {
     if (argc == 2) {
         int depth = std::stoi(argv[1]);
@@ -133,6 +359,16 @@ int main(int argc, char* argv[]) {
     }
 
     testEvaluatedMove();
+    testWinDrawLoss();
+    testLoadPieceValues();
+    testTraceEvaluation();
+    testPawnRace();
+    testPassedPawns();
+    testKingTropism();
+    testStaticExchange();
+    testSearch();
+    testTablebase();
+    testHashedPerft();
 
     std::string fen(argv[1]);
     int depth = std::stoi(argv[2]);
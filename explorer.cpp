@@ -0,0 +1,51 @@
+#include <cstdlib>  // For std::exit
+#include <fstream>
+#include <iomanip>
+#include <iostream>
+#include <string>
+
+#include "database.h"
+#include "fen.h"
+#include "moves.h"
+#include "san.h"
+
+/**
+ * Prints the moves played in the position, with the results as seen by the side to move.
+ */
+void printStatistics(const Database& db, const Position& position) {
+    std::cout << fen::to_epd(position) << std::endl;
+    std::cout << "Move      Games   Win%  Draw%  Loss%  Opp. Elo" << std::endl;
+    for (auto& stats : db.moveStatistics(position)) {
+        std::cout << std::left << std::setw(8) << stats.move << std::right << std::setw(7)
+                  << stats.games << std::fixed << std::setprecision(1) << std::setw(7)
+                  << stats.winPercentage() << std::setw(7) << stats.drawPercentage()
+                  << std::setw(7) << stats.lossPercentage() << std::setw(10)
+                  << std::setprecision(0) << stats.averageOpponentRating() << std::endl;
+    }
+}
+
+int main(int argc, char** argv) {
+    if (argc < 2) {
+        std::cerr << "Usage: " << argv[0] << " games.pgn [move...]" << std::endl;
+        std::exit(1);
+    }
+    std::ifstream pgn(argv[1]);
+    if (!pgn) {
+        std::cerr << "Cannot open " << argv[1] << std::endl;
+        std::exit(1);
+    }
+    Database db(pgn);
+
+    auto position = fen::parsePosition(fen::initialPosition);
+    for (int i = 2; i < argc; ++i) {
+        auto move = san::parseMove(position, argv[i]);
+        if (!move) {
+            std::cerr << "Illegal move \"" << argv[i] << "\" in " << fen::to_string(position)
+                      << std::endl;
+            std::exit(1);
+        }
+        position = applyMove(position, move);
+    }
+    printStatistics(db, position);
+    return 0;
+}
@@ -1,11 +1,136 @@
+#include <algorithm>
+#include <cstdlib>
+#include <functional>
 #include <iostream>
+#include <iterator>
+#include <optional>
+#include <random>
 #include <sstream>
 #include <string>
 #include <vector>
 
 #include "common.h"
+#include "fen.h"
 
 namespace fen {
+namespace {
+// Unicode chess symbols, in the order of the pieces
+const std::string unicodePieces[] = {
+    "♙", "♘", "♗", "♖", "♕", "♔", "♟", "♞", "♝", "♜", "♛", "♚"};
+
+/**
+ * Returns the length of the UTF-8 encoded character starting with the given byte.
+ */
+size_t utf8Length(unsigned char ch) {
+    if ((ch & 0xe0) == 0xc0) return 2;
+    if ((ch & 0xf0) == 0xe0) return 3;
+    if ((ch & 0xf8) == 0xf0) return 4;
+    return 1;
+}
+
+/**
+ * Parses castling rights, given as "KQkq" or by rook files as in Shredder-FEN and X-FEN, where a
+ * file names the right on its side of the king, and "KQkq" the outermost rook on that side. The
+ * start file of each rook is stored with its right. Without a king on the back rank, only the
+ * standard rook files name rights, see toCastlingMask.
+ */
+void parseCastling(Position& position, const std::string& str) {
+    auto& board = position.board;
+    position.castlingAvailability = CastlingMask::NONE;
+    for (char ch : str) {
+        bool white = ch >= 'A' && ch < 'A' + kNumFiles;
+        bool black = ch >= 'a' && ch < 'a' + kNumFiles;
+        auto right = toCastlingMask(std::string(1, ch));
+        int file = ch - (white ? 'A' : 'a');
+        if (white || black) {
+            auto kingSide = white ? CastlingMask::WHITE_KINGSIDE : CastlingMask::BLACK_KINGSIDE;
+            auto queenSide = white ? CastlingMask::WHITE_QUEENSIDE : CastlingMask::BLACK_QUEENSIDE;
+            auto squares = castlingSquares(board, kingSide);
+            if (!squares) squares = castlingSquares(board, queenSide);
+            if (squares) right = file > squares->king.file() ? kingSide : queenSide;
+        } else if (right != CastlingMask::NONE) {
+            auto squares = castlingSquares(board, right);
+            file = squares ? squares->rook.file()
+                           : Position::kStandardRookFiles[castlingIndex(right)];
+        }
+        if (right == CastlingMask::NONE) continue;
+        position.castlingAvailability |= right;
+        position.castlingRookFiles[castlingIndex(right)] = file;
+    }
+}
+
+/**
+ * Returns true if castling with any of the rights needs chess960 rules, as the king or rook isn't
+ * on its standard square.
+ */
+bool isChess960(const Position& position) {
+    for (auto right : castlingRights(position.castlingAvailability)) {
+        auto index = castlingIndex(right);
+        if (position.castlingRookFiles[index] != Position::kStandardRookFiles[index]) return true;
+        auto squares = castlingSquares(position.board, right);
+        if (squares && squares->king.file() != Position::kKingFile) return true;
+    }
+    return false;
+}
+
+/**
+ * Returns the castling rights of the position in FEN notation. In chess960, rook files name the
+ * rights by the start files of their rooks, and otherwise, as in X-FEN, only rights of rooks that
+ * aren't the outermost on their side of the king need their file.
+ */
+std::string castlingString(const Position& position, bool rookFiles) {
+    if (position.variant != Variant::CHESS960)
+        return to_string(position.castlingAvailability, rookFiles);
+    std::string str;
+    for (auto right : castlingRights(position.castlingAvailability)) {
+        int file = position.castlingRookFiles[castlingIndex(right)];
+        auto outermost = castlingSquares(position.board, right);
+        if (!rookFiles && (!outermost || outermost->rook.file() == file))
+            str += to_string(right);
+        else
+            str += char((contains(CastlingMask::WHITE, right) ? 'A' : 'a') + file);
+    }
+    return str.empty() ? "-" : str;
+}
+
+/**
+ * Parses a line of a diagram as a rank of squares, returning false if it isn't one.
+ */
+bool parseDiagramRank(const std::string& line, std::vector<Piece>& rank) {
+    for (size_t pos = 0; pos < line.size();) {
+        auto symbol = line.substr(pos, utf8Length(line[pos]));
+        pos += symbol.size();
+        if (symbol == " " || symbol == "\t" || symbol == "\r" || symbol == "|") continue;
+
+        // Rank numbers may label either side of the diagram
+        bool label = rank.empty() || rank.size() == kNumFiles;
+        if (label && std::isdigit(static_cast<unsigned char>(symbol[0]))) continue;
+
+        if (symbol == "." || symbol == "-" || symbol == "·") {
+            rank.push_back(Piece::NONE);
+        } else if (symbol.size() == 1 && toPiece(symbol[0]) != Piece::NONE) {
+            rank.push_back(toPiece(symbol[0]));
+        } else {
+            auto unicode = std::find(std::begin(unicodePieces), std::end(unicodePieces), symbol);
+            if (unicode == std::end(unicodePieces)) return false;
+            rank.push_back(Piece(unicode - std::begin(unicodePieces) + 1));
+        }
+    }
+    return rank.size() == kNumFiles;
+}
+
+/**
+ * Parses a move counter, clamped to the range of its field. Missing or invalid counters, as in EPD
+ * or damaged FEN strings, give the default.
+ */
+int parseCounter(const std::string& str, int min, int max, int defaultValue) {
+    char* end = nullptr;
+    auto value = std::strtol(str.c_str(), &end, 10);
+    if (str.empty() || *end) return defaultValue;
+    return std::clamp(value, long(min), long(max));
+}
+}  // namespace
+
 Board parsePiecePlacement(const std::string& piecePlacement) {
     Board board;
 
@@ -46,15 +171,8 @@ Position parsePosition(const std::string& fen) {
         fullmoveNumberStr;
 
     position.activeColor = activeColorStr == "b" ? Color::BLACK : Color::WHITE;
-    position.castlingAvailability = CastlingMask::NONE;
-    for (char ch : castlingAvailabilityStr) {
-        switch (ch) {
-        case 'K': position.castlingAvailability |= CastlingMask::WHITE_KINGSIDE; break;
-        case 'Q': position.castlingAvailability |= CastlingMask::WHITE_QUEENSIDE; break;
-        case 'k': position.castlingAvailability |= CastlingMask::BLACK_KINGSIDE; break;
-        case 'q': position.castlingAvailability |= CastlingMask::BLACK_QUEENSIDE; break;
-        }
-    }
+    parseCastling(position, castlingAvailabilityStr);
+    if (isChess960(position)) position.variant = Variant::CHESS960;
 
     if (enPassantTargetStr != "-") {
         int file = enPassantTargetStr[0] - 'a';
@@ -62,12 +180,163 @@ Position parsePosition(const std::string& fen) {
         position.enPassantTarget = Square{rank, file};
     }
 
-    position.halfmoveClock = std::stoi(halfmoveClockStr);
-    position.fullmoveNumber = std::stoi(fullmoveNumberStr);
+    position.halfmoveClock = parseCounter(halfmoveClockStr, 0, UINT8_MAX, 0);
+    position.fullmoveNumber = parseCounter(fullmoveNumberStr, 1, UINT16_MAX, 1);
 
     return position;
 }
 
+bool isValid(const std::string& fen) {
+    std::stringstream ss(fen);
+    std::string piecePlacement, activeColor, castlingAvailability, enPassantTarget;
+    if (!(ss >> piecePlacement >> activeColor >> castlingAvailability >> enPassantTarget))
+        return false;
+
+    int rank = 0, file = 0;
+    for (char ch : piecePlacement) {
+        if (ch == '/') {
+            if (file != kNumFiles) return false;
+            ++rank;
+            file = 0;
+        } else if (ch >= '1' && ch <= '8') {
+            file += ch - '0';
+        } else if (toPiece(ch) != Piece::NONE) {
+            ++file;
+        } else {
+            return false;
+        }
+        if (rank >= kNumRanks || file > kNumFiles) return false;
+    }
+    if (rank != kNumRanks - 1 || file != kNumFiles) return false;
+
+    if (activeColor != "w" && activeColor != "b") return false;
+    if (castlingAvailability != "-" &&
+        castlingAvailability.find_first_not_of(castlingChars + "ABCDEFGHabcdefgh") !=
+            std::string::npos)
+        return false;
+    return enPassantTarget == "-" || parseSquare(enPassantTarget);
+}
+
+std::optional<Square> parseSquare(const std::string& name) {
+    if (name.size() != 2 || name[0] < 'a' || name[0] >= 'a' + kNumFiles || name[1] < '1' ||
+        name[1] >= '1' + kNumRanks)
+        return std::nullopt;
+    return Square(name[1] - '1', name[0] - 'a');
+}
+
+std::optional<std::vector<Square>> parseSquares(const std::string& names) {
+    std::vector<Square> squares;
+    std::stringstream ss(names);
+    std::string name;
+    while (ss >> name) {
+        auto square = parseSquare(name);
+        if (!square) return std::nullopt;
+        squares.push_back(*square);
+    }
+    return squares;
+}
+
+std::string to_string(const std::vector<Square>& squares) {
+    std::string str;
+    for (auto square : squares) {
+        if (!str.empty()) str += ' ';
+        str += std::string(square);
+    }
+    return str;
+}
+
+std::vector<Square> squares(SquareColor color) {
+    std::vector<Square> squares;
+    for (Square square = 0; square.index() < kNumSquares; ++square)
+        if (square.colorComplex() == color) squares.push_back(square);
+    return squares;
+}
+
+Square randomSquare(std::mt19937& gen) {
+    return Square(std::uniform_int_distribution<int>(0, kNumSquares - 1)(gen));
+}
+
+Square randomSquare(std::mt19937& gen, SquareColor color) {
+    auto candidates = squares(color);
+    return candidates[std::uniform_int_distribution<size_t>(0, candidates.size() - 1)(gen)];
+}
+
+Piece randomPiece(std::mt19937& gen) {
+    return Piece(std::uniform_int_distribution<int>(index(Piece::WHITE_PAWN), kNumPieces - 1)(gen));
+}
+
+std::vector<ParseError> parseMany(std::istream& in, const std::function<void(Position)>& fun) {
+    std::vector<ParseError> errors;
+    std::string line;
+    for (size_t number = 1; std::getline(in, line); ++number) {
+        auto text = line.substr(0, line.find('#'));
+        if (text.find_first_not_of(" \t\r") == std::string::npos) continue;
+        if (isValid(text))
+            fun(parsePosition(text));
+        else
+            errors.push_back({number, line});
+    }
+    return errors;
+}
+
+bool parseDiagram(const std::string& diagram, Board& board) {
+    std::vector<std::vector<Piece>> ranks;
+    std::stringstream ss(diagram);
+    std::string line;
+    while (std::getline(ss, line)) {
+        std::vector<Piece> rank;
+        if (parseDiagramRank(line, rank)) ranks.push_back(rank);
+    }
+    if (ranks.size() != kNumRanks) return false;
+
+    for (int rank = 0; rank < kNumRanks; ++rank)
+        for (int file = 0; file < kNumFiles; ++file)
+            board[Square(kNumRanks - 1 - rank, file)] = ranks[rank][file];
+    return true;
+}
+
+Position chess960Position(int white, int black) {
+    auto position = parsePosition("8/pppppppp/8/8/8/8/PPPPPPPP/8 w - - 0 1");
+    for (auto [number, color] : {std::pair{white, Color::WHITE}, std::pair{black, Color::BLACK}}) {
+        int rank = color == Color::WHITE ? 0 : kNumRanks - 1;
+        auto place = [&](int file, PieceType type) {
+            position.board[Square(rank, file)] = addColor(type, color);
+        };
+        // Places a piece on the nth empty square of the back rank
+        auto placeEmpty = [&](int n, PieceType type) {
+            for (int file = 0; file < kNumFiles; ++file)
+                if (position.board[Square(rank, file)] == Piece::NONE && n-- == 0)
+                    return place(file, type);
+        };
+
+        place(number % 4 * 2 + 1, PieceType::BISHOP);  // On the b, d, f or h file
+        number /= 4;
+        place(number % 4 * 2, PieceType::BISHOP);  // On the a, c, e or g file
+        number /= 4;
+        placeEmpty(number % 6, PieceType::QUEEN);
+        number /= 6;
+        static const std::pair<int, int> knights[] = {
+            {0, 1}, {0, 2}, {0, 3}, {0, 4}, {1, 2}, {1, 3}, {1, 4}, {2, 3}, {2, 4}, {3, 4}};
+        placeEmpty(knights[number].second, PieceType::KNIGHT);
+        placeEmpty(knights[number].first, PieceType::KNIGHT);
+        placeEmpty(0, PieceType::ROOK);
+        placeEmpty(0, PieceType::KING);
+        placeEmpty(0, PieceType::ROOK);
+    }
+
+    // Both sides start with a rook on either side of the king, so can castle both ways
+    position.variant = Variant::CHESS960;
+    position.castlingAvailability = CastlingMask::ALL;
+    for (auto right : kCastlingRights)
+        position.castlingRookFiles[castlingIndex(right)] =
+            castlingSquares(position.board, right)->rook.file();
+    return position;
+}
+
+Position chess960Position(int number) {
+    return chess960Position(number, number);
+}
+
 std::string to_string(const Board& board) {
     std::stringstream fen;
     for (int rank = 7; rank >= 0; --rank) {  // Start from the 8th rank and go downwards
@@ -93,12 +362,18 @@ std::string to_string(const Board& board) {
     return fen.str();
 }
 
-std::string to_string(const Position& position) {
+std::string to_epd(const Position& position, bool rookFiles) {
     std::stringstream fen;
     fen << to_string(position.board) << " ";
     fen << to_string(position.activeColor) << " ";
-    fen << to_string(position.castlingAvailability) << " ";
-    fen << (position.enPassantTarget.index() ? std::string(position.enPassantTarget) : "-") << " ";
+    fen << castlingString(position, rookFiles) << " ";
+    fen << (position.enPassantTarget ? std::string(*position.enPassantTarget) : "-");
+    return fen.str();
+}
+
+std::string to_string(const Position& position, bool rookFiles) {
+    std::stringstream fen;
+    fen << to_epd(position, rookFiles) << " ";
     fen << (int)position.halfmoveClock << " ";
     fen << position.fullmoveNumber;
     return fen.str();
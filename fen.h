@@ -1,5 +1,13 @@
+#include <functional>
+#include <iosfwd>
+#include <optional>
+#include <random>
+#include <string>
+#include <vector>
+
 #include "common.h"
 
+#pragma once
 
 namespace fen {
 static constexpr auto emptyPiecePlacement = "8/8/8/8/8/8/8/8";
@@ -18,18 +26,110 @@ std::string to_string(const Board& board);
  * @brief Converts a Position object to a FEN string.
  *
  * @param position The Position object to convert.
+ * @param rookFiles Whether to name castling rights by rook files, like "HAha", instead of "KQkq".
+ * In chess960, these are the start files of the castling rooks, as in Shredder-FEN. Otherwise,
+ * chess960 rights use "KQkq" for the outermost rooks and rook files for others, as in X-FEN.
  * @return std::string The FEN string representing the position.
  */
-std::string to_string(const Position& position);
+std::string to_string(const Position& position, bool rookFiles = false);
+
+/**
+ * @brief Converts a Position object to the first four fields of a FEN string, as used by EPD,
+ * omitting the halfmove clock and fullmove number.
+ *
+ * @param position The Position object to convert.
+ * @param rookFiles Whether to name castling rights by rook files, like "HAha", instead of "KQkq".
+ * @return std::string The EPD string representing the position.
+ */
+std::string to_epd(const Position& position, bool rookFiles = false);
 
 /**
- * Parses a FEN string and returns the corresponding Position object.
+ * Parses a FEN string and returns the corresponding Position object. Castling rights may also be
+ * given by the files of the rooks, like "HAha" in Shredder-FEN and X-FEN, each naming the right on
+ * its side of the king, while "KQkq" name the outermost rook on that side, as in X-FEN. The start
+ * file of the rook is kept with each right, see Position::castlingRookFiles. When the king or
+ * castling rook of any right isn't on its standard square, the position is for chess960.
  *
  * @param fen The FEN string to parse.
  * @return The Position object corresponding to the given FEN string.
  */
 Position parsePosition(const std::string& fen);
 
+/**
+ * Returns true if the FEN string is well-formed: a piece placement with eight ranks of eight
+ * squares, followed by the active color, castling availability and en passant target. The halfmove
+ * clock and fullmove number are optional, as in EPD. Doesn't check whether the position is legal.
+ * Strings that aren't well-formed parse to unspecified positions.
+ */
+bool isValid(const std::string& fen);
+
+/**
+ * A line of a corpus that isn't a valid FEN or EPD, with its line number, starting at 1.
+ */
+struct ParseError {
+    size_t line;
+    std::string text;
+};
+
+/**
+ * Reads a corpus of positions, like a test suite or training data, with one FEN or EPD per line,
+ * and calls fun for each position in order, without keeping them in memory. Anything after a '#' is
+ * ignored, as are blank lines. Returns the lines that aren't valid, see isValid, which are skipped.
+ */
+std::vector<ParseError> parseMany(std::istream& in, const std::function<void(Position)>& fun);
+
+/**
+ * Parses a square name, like "e4", or returns nothing if the name isn't a square.
+ */
+std::optional<Square> parseSquare(const std::string& name);
+
+/**
+ * Parses square names separated by whitespace, like "e1 f1 g1", in order. Returns nothing if any
+ * of the names isn't a square.
+ */
+std::optional<std::vector<Square>> parseSquares(const std::string& names);
+
+/**
+ * Returns the names of the squares separated by spaces, like "e1 f1 g1", the reverse of
+ * parseSquares.
+ */
+std::string to_string(const std::vector<Square>& squares);
+
+/**
+ * Returns the squares of the given color, in order of their index, starting at a1 or b1.
+ */
+std::vector<Square> squares(SquareColor color);
+
+/**
+ * Random squares, optionally of a given color, and pieces other than NONE, for coordinate and
+ * piece recognition trainers. Using the given generator, the choices are reproducible from a seed.
+ */
+Square randomSquare(std::mt19937& gen);
+Square randomSquare(std::mt19937& gen, SquareColor color);
+Piece randomPiece(std::mt19937& gen);
+
+// Number of Chess960 starting positions, and the number of the standard starting position
+static constexpr int kNumChess960Positions = 960;
+static constexpr int kStandardChess960Position = 518;
+
+/**
+ * @brief Returns the Chess960 starting position with the given number, using the standard
+ * numbering from 0 to 959, where 518 is the standard starting position.
+ *
+ * The position is for chess960, with castling availability for both sides and rooks, even for the
+ * standard starting position.
+ *
+ * @param number The number of the starting position.
+ * @return Position The starting position, with white to move.
+ */
+Position chess960Position(int number);
+
+/**
+ * @brief Like the above, but for Double Fischer Random Chess, where white and black have
+ * independently chosen back ranks.
+ */
+Position chess960Position(int white, int black);
+
 /**
  * @brief Parses the piece placement string of a FEN notation and returns a Board object.
  *
@@ -37,4 +137,16 @@ Position parsePosition(const std::string& fen);
  * @return Board The Board object representing the parsed FEN notation.
  */
 Board parsePiecePlacement(const std::string& piecePlacement);
+
+/**
+ * @brief Parses a board diagram, with one line per rank starting at the 8th, as printed for
+ * debugging. Squares are letters as in FEN or Unicode chess symbols, with ".", "-" or "·" for
+ * empty squares, optionally separated by spaces or "|". Other lines, like file labels or borders,
+ * and rank numbers on either side are ignored.
+ *
+ * @param diagram The diagram to parse.
+ * @param board The Board to set on success.
+ * @return bool True if the diagram has exactly eight ranks of eight squares.
+ */
+bool parseDiagram(const std::string& diagram, Board& board);
 }  // namespace fen
@@ -1,8 +1,11 @@
 #include <cassert>
 #include <iostream>
+#include <set>
+#include <sstream>
 #include <vector>
 
 #include "fen.h"
+#include "parallel.h"
 
 // Test
 int testparse() {
@@ -11,9 +14,9 @@ int testparse() {
 
     std::cout << "Piece Placement: " << fen::to_string(position.board) << "\n";
     std::cout << "Active Color: " << to_string(position.activeColor) << "\n";
-    std::cout << "Castling Availability: " << (int)position.castlingAvailability << "\n";
+    std::cout << "Castling Availability: " << to_string(position.castlingAvailability) << "\n";
     std::cout << "En Passant Target: "
-              << (position.enPassantTarget.index() ? std::string(position.enPassantTarget) : "-")
+              << (position.enPassantTarget ? std::string(*position.enPassantTarget) : "-")
               << "\n";
     std::cout << "Halfmove Clock: " << (int)position.halfmoveClock << "\n";
     std::cout << "Fullmove Number: " << position.fullmoveNumber << "\n";
@@ -34,7 +37,7 @@ void testInitialPosition() {
     assert(position.board == board);
     assert(position.activeColor == Color::WHITE);
     assert(position.castlingAvailability == CastlingMask::ALL);
-    assert(position.enPassantTarget == Square(0));
+    assert(!position.enPassantTarget);
     assert(position.halfmoveClock == 0);
     assert(position.fullmoveNumber == 1);
 }
@@ -71,10 +74,240 @@ void testFENPosition() {
     }
 }
 
+void testEPD() {
+    auto position = fen::parsePosition("4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3 0 42");
+    assert(fen::to_epd(position) == "4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3");
+}
+
+void testRookFileCastling() {
+    // Castling rights named by rook files round-trip, and are the same as the standard ones
+    auto fen = "r3k2r/8/8/8/8/8/8/R3K2R w HAh - 0 1";
+    auto position = fen::parsePosition(fen);
+    assert(fen::isValid(fen));
+    assert(position.castlingAvailability == toCastlingMask("KQk"));
+    assert(position.castlingRookFiles == Position::kStandardRookFiles);
+    assert(fen::to_string(position, true) == fen);
+    assert(fen::to_epd(position) == "r3k2r/8/8/8/8/8/8/R3K2R w KQk -");
+    assert(fen::to_string(fen::parsePosition(fen::initialPosition), true) ==
+           "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+
+    assert(position.variant == Variant::STANDARD);
+
+    // Without a board, only the standard rook files name castling rights
+    assert(toCastlingMask("Bb") == CastlingMask::NONE);
+    assert(!fen::isValid("r3k2r/8/8/8/8/8/8/R3K2R w Hx - 0 1"));
+
+    // Other files name the right on their side of the king, as in chess960
+    auto shredder = "1r3kr1/8/8/8/8/8/8/1R3KR1 w GBgb - 0 1";
+    position = fen::parsePosition(shredder);
+    assert(fen::isValid(shredder));
+    assert(position.variant == Variant::CHESS960);
+    assert(position.castlingAvailability == CastlingMask::ALL);
+    assert(fen::to_string(position, true) == shredder);
+    assert(fen::to_string(position) == "1r3kr1/8/8/8/8/8/8/1R3KR1 w KQkq - 0 1");
+
+    // X-FEN names the rights of the outermost rooks as in standard chess
+    position = fen::parsePosition("1r3kr1/8/8/8/8/8/8/1R3KR1 w Kq - 0 1");
+    assert(position.variant == Variant::CHESS960);
+    assert(fen::to_string(position, true) == "1r3kr1/8/8/8/8/8/8/1R3KR1 w Gb - 0 1");
+
+    // Rights of inner rooks keep their files, also where the outer rook has no right
+    auto inner = "rr2k2r/8/8/8/8/8/8/RR2K2R w HBb - 0 1";
+    position = fen::parsePosition(inner);
+    assert(position.variant == Variant::CHESS960);
+    assert(position.castlingAvailability == toCastlingMask("KQq"));
+    assert(position.castlingRookFiles[castlingIndex(CastlingMask::WHITE_QUEENSIDE)] == 1);
+    assert(position.castlingRookFiles[castlingIndex(CastlingMask::BLACK_QUEENSIDE)] == 1);
+    assert(fen::to_string(position, true) == inner);
+    auto xfen = "rr2k2r/8/8/8/8/8/8/RR2K2R w KBb - 0 1";
+    assert(fen::to_string(position) == xfen);
+    assert(fen::parsePosition(xfen).castlingRookFiles == position.castlingRookFiles);
+}
+
+void testCounters() {
+    // Out of range counters are clamped, and missing or invalid ones get the defaults
+    auto position = fen::parsePosition("4k3/8/8/8/8/8/8/4K3 w - - 300 70000");
+    assert(position.halfmoveClock == 255 && position.fullmoveNumber == 65535);
+    position = fen::parsePosition("4k3/8/8/8/8/8/8/4K3 w - - -5 0");
+    assert(position.halfmoveClock == 0 && position.fullmoveNumber == 1);
+    position = fen::parsePosition("4k3/8/8/8/8/8/8/4K3 w - -");
+    assert(position.halfmoveClock == 0 && position.fullmoveNumber == 1);
+    position = fen::parsePosition("4k3/8/8/8/8/8/8/4K3 w - - x1 12");
+    assert(position.halfmoveClock == 0 && position.fullmoveNumber == 12);
+
+    std::cout << "All counter tests passed!" << std::endl;
+}
+
+void testIsValid() {
+    assert(fen::isValid(fen::initialPosition));
+    assert(fen::isValid("4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3"));  // No counters, as in EPD
+    for (auto invalid : {"",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8 w KQkq - 0 1",
+                         "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                         "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                         "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e 0 1",
+                         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq i3 0 1"})
+        assert(!fen::isValid(invalid));
+}
+
+void testParseMany() {
+    std::stringstream corpus;
+    corpus << "# Positions for testing\n"
+           << fen::initialPosition << "\n"
+           << "\n"
+           << "4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3 bm Qc1+; # EPD with an operation\n"
+           << "4k3/8/8/8/8/8/8/4K3 x - - 0 1\n"
+           << "4k3/8/8/3Q4/8/8/8/4K3 w - - 0 1\n";
+    std::vector<std::string> positions;
+    auto errors = fen::parseMany(corpus, [&](Position position) {
+        positions.push_back(fen::to_epd(position));
+    });
+    assert(positions.size() == 3);
+    assert(positions[0] == "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -");
+    assert(positions[1] == "4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3");
+    assert(positions[2] == "4k3/8/8/3Q4/8/8/8/4K3 w - -");
+    assert(errors.size() == 1 && errors[0].line == 5);
+    assert(errors[0].text == "4k3/8/8/8/8/8/8/4K3 x - - 0 1");
+
+    // Results are in the order of the positions, whatever the number of threads
+    std::vector<Position> chess960;
+    for (int number = 0; number < fen::kNumChess960Positions; ++number)
+        chess960.push_back(fen::chess960Position(number));
+    auto toString = [](const Position& position) { return fen::to_string(position.board); };
+    for (unsigned threads : {1, 3, 16}) {
+        auto boards = parallelMap(chess960, toString, threads);
+        assert(boards.size() == chess960.size());
+        for (size_t i = 0; i < chess960.size(); ++i) assert(boards[i] == toString(chess960[i]));
+    }
+    assert(parallelMap(std::vector<Position>(), toString).empty());
+
+    std::cout << "All corpus tests passed!" << std::endl;
+}
+
+void testDiagram() {
+    Board board;
+    assert(fen::parseDiagram("8   . . . . . . k .\n"
+                             "7   . . . . Q . . .\n"
+                             "6   . . . . . K . .\n"
+                             "5   . . . . . . . .\n"
+                             "4   . . . . . . . .\n"
+                             "3   . . . . . . . .\n"
+                             "2   . . . . . . . .\n"
+                             "1   . . . . . . . .\n"
+                             "    a b c d e f g h\n",
+                             board));
+    assert(fen::to_string(board) == "6k1/4Q3/5K2/8/8/8/8/8");
+
+    // Unicode symbols, borders and labels on the right
+    assert(fen::parseDiagram("+-----------------+\n"
+                             "| ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜ | 8\n"
+                             "| ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟ | 7\n"
+                             "| · · · · · · · · | 6\n"
+                             "| · · · · · · · · | 5\n"
+                             "| · · · · ♙ · · · | 4\n"
+                             "| · · · · · · · · | 3\n"
+                             "| ♙ ♙ ♙ ♙ · ♙ ♙ ♙ | 2\n"
+                             "| ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖ | 1\n"
+                             "+-----------------+\n",
+                             board));
+    assert(fen::to_string(board) == "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR");
+
+    // Compact grids without spaces, but not with missing ranks or squares
+    assert(fen::parseDiagram("....k...\n........\n........\n........\n"
+                             "........\n........\n........\n....K...",
+                             board));
+    assert(fen::to_string(board) == "4k3/8/8/8/8/8/8/4K3");
+    assert(!fen::parseDiagram("....k...\n........\n........\n........\n"
+                              "........\n........\n....K...",
+                              board));
+    assert(!fen::parseDiagram("....k...\n........\n........\n........\n"
+                              "........\n........\n........\n...K...",
+                              board));
+
+    std::cout << "All diagram tests passed!" << std::endl;
+}
+
+void testChess960() {
+    assert(fen::to_string(fen::chess960Position(fen::kStandardChess960Position)) ==
+           fen::initialPosition);
+    assert(fen::to_string(fen::chess960Position(0)) ==
+           "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert(fen::to_string(fen::chess960Position(959), true) ==
+           "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1");
+    assert(fen::to_string(fen::chess960Position(518, 0), true) ==
+           "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAhf - 0 1");
+    assert(fen::chess960Position(0).variant == Variant::CHESS960);
+
+    // All positions differ, with bishops on opposite colors and the king between the rooks
+    std::set<std::string> placements;
+    for (int number = 0; number < fen::kNumChess960Positions; ++number) {
+        auto board = fen::chess960Position(number).board;
+        std::string rank;
+        for (int file = 0; file < kNumFiles; ++file) rank += to_char(board[Square(0, file)]);
+        placements.insert(rank);
+        assert(rank.find('B') % 2 != rank.rfind('B') % 2);
+        assert(rank.find('R') < rank.find('K') && rank.find('K') < rank.rfind('R'));
+        std::string blackRank;
+        for (char piece : rank) blackRank += std::tolower(piece);
+        assert(fen::to_string(board).substr(0, kNumFiles) == blackRank);
+    }
+    assert(placements.size() == fen::kNumChess960Positions);
+
+    std::cout << "All Chess960 tests passed!" << std::endl;
+}
+
+void testSquares() {
+    assert(fen::parseSquare("e4") == "e4"_sq);
+    assert(fen::parseSquare("h8") == "h8"_sq);
+    for (auto bad : {"", "e", "e9", "i1", "E4", "e44"}) assert(!fen::parseSquare(bad));
+
+    auto squares = fen::parseSquares(" e1 f1\tg1 ");
+    assert(squares && squares->size() == 3 && squares->back() == "g1"_sq);
+    assert(fen::to_string(*squares) == "e1 f1 g1");
+    assert(fen::parseSquares("")->empty() && fen::to_string(std::vector<Square>()) == "");
+    assert(!fen::parseSquares("e1 f0"));
+
+    auto dark = fen::squares(SquareColor::DARK);
+    auto light = fen::squares(SquareColor::LIGHT);
+    assert(dark.size() == 32 && light.size() == 32);
+    assert(dark.front() == "a1"_sq && light.front() == "b1"_sq && light.back() == "g8"_sq);
+
+    // The same seed gives the same choices
+    std::mt19937 gen(42), again(42);
+    std::set<int> seen;
+    for (int i = 0; i < 1000; ++i) {
+        auto square = fen::randomSquare(gen);
+        assert(square == fen::randomSquare(again));
+        seen.insert(square.index());
+        assert(fen::randomSquare(gen, SquareColor::LIGHT).colorComplex() == SquareColor::LIGHT);
+        fen::randomSquare(again, SquareColor::LIGHT);
+        auto piece = fen::randomPiece(gen);
+        assert(piece != Piece::NONE && piece == fen::randomPiece(again));
+    }
+    assert(seen.size() == kNumSquares);
+
+    std::cout << "All square tests passed!" << std::endl;
+}
+
 int main() {
     testparse();
     testInitialPosition();
     testFENPiecePlacement();
+    testFENPosition();
+    testEPD();
+    testRookFileCastling();
+    testCounters();
+    testIsValid();
+    testParseMany();
+    testDiagram();
+    testChess960();
+    testSquares();
     std::cout << "All FEN tests passed!" << std::endl;
     return 0;
 }
@@ -0,0 +1,209 @@
+#include <algorithm>
+
+#include "game.h"
+#include "fen.h"
+#include "moves.h"
+#include "san.h"
+
+namespace {
+std::optional<Square> enPassantTarget(Position position) {
+    normalizeEnPassant(position);
+    return position.enPassantTarget;
+}
+
+bool isRepetition(const Position& left, const Position& right) {
+    return left.board == right.board && left.activeColor == right.activeColor &&
+        left.castlingAvailability == right.castlingAvailability &&
+        enPassantTarget(left) == enPassantTarget(right);
+}
+
+bool isCheckmate(const Position& position) {
+    return allLegalMoves(position).empty() && isInCheck(position);
+}
+
+/**
+ * Returns true if neither side can checkmate by any sequence of legal moves, which is the case
+ * with only kings and a single minor piece, or only kings and bishops on squares of one color.
+ */
+bool isDeadPosition(const Board& board) {
+    int knights = 0;
+    int bishops[2] = {0, 0};  // Indexed by SquareColor
+    for (auto square : SquareSet::occupancy(board)) {
+        switch (type(board[square])) {
+        case PieceType::KING: break;
+        case PieceType::KNIGHT: ++knights; break;
+        case PieceType::BISHOP: ++bishops[int(square.colorComplex())]; break;
+        default: return false;
+        }
+    }
+    return knights + bishops[0] + bishops[1] <= 1 || (!knights && (!bishops[0] || !bishops[1]));
+}
+
+bool hasOnlyKing(const Board& board, Color side) {
+    for (auto square : SquareSet::occupancy(board))
+        if (isColor(board[square], side) && type(board[square]) != PieceType::KING) return false;
+    return true;
+}
+
+void updateTag(Game::Tags& tags, const std::string& name, const std::string& value) {
+    for (auto& [tagName, oldValue] : tags)
+        if (tagName == name) return void(oldValue = value);
+    tags.emplace_back(name, value);
+}
+
+std::string winner(Color color) {
+    return color == Color::WHITE ? "1-0" : "0-1";
+}
+}  // namespace
+
+std::string to_string(TerminationReason reason) {
+    switch (reason) {
+    case TerminationReason::NONE: return "ongoing";
+    case TerminationReason::CHECKMATE: return "checkmate";
+    case TerminationReason::STALEMATE: return "stalemate";
+    case TerminationReason::RESIGNATION: return "resignation";
+    case TerminationReason::TIMEOUT: return "timeout";
+    case TerminationReason::REPETITION: return "repetition";
+    case TerminationReason::FIFTY_MOVES: return "fifty-move rule";
+    case TerminationReason::INSUFFICIENT_MATERIAL: return "insufficient material";
+    case TerminationReason::ADJUDICATION: return "adjudication";
+    }
+    return "unknown";
+}
+
+Game::Game() : Game(fen::parsePosition(fen::initialPosition)) {}
+
+Game::Game(const Position& start) : _positions{start} {
+    checkTermination();
+}
+
+bool Game::pushMove(Move move, std::chrono::milliseconds timestamp) {
+    if (isOver()) return false;
+
+    auto& position = currentPosition();
+    for (auto& [legal, newPosition] : allLegalMoves(position)) {
+        if (legal.from != move.from || legal.to != move.to) continue;
+        if (legal.isPromotion() != move.isPromotion()) continue;
+        if (move.isPromotion() && promotionType(legal.kind) != promotionType(move.kind)) continue;
+
+        auto san = san::to_string(position, legal);
+        _moves.resize(_ply);
+        _positions.resize(_ply + 1);
+        _moves.push_back({legal, san, timestamp});
+        _positions.push_back(newPosition);  // Invalidates the position reference
+        ++_ply;
+        checkTermination();
+        return true;
+    }
+    return false;
+}
+
+bool Game::pushMove(const std::string& san, std::chrono::milliseconds timestamp) {
+    auto move = san::parseMove(currentPosition(), san);
+    return move && pushMove(move, timestamp);
+}
+
+bool Game::gotoPly(size_t ply) {
+    if (ply > _moves.size()) return false;
+    _ply = ply;
+    return true;
+}
+
+int Game::repetitions() const {
+    // Pawn moves and captures are irreversible, so earlier positions can't repeat
+    auto& current = currentPosition();
+    size_t reversible = std::min(size_t(current.halfmoveClock), _ply);
+    int count = 1;
+    for (size_t ply = _ply - reversible; ply < _ply; ++ply)
+        if (isRepetition(_positions[ply], current)) ++count;
+    return count;
+}
+
+std::vector<DrawClaim> Game::claimableDraws() const {
+    std::vector<DrawClaim> claims;
+    if (repetitions() >= 3) claims.push_back(DrawClaim::THREEFOLD_REPETITION);
+    if (currentPosition().halfmoveClock >= 100 && !isCheckmate(currentPosition()))
+        claims.push_back(DrawClaim::FIFTY_MOVES);
+    return claims;
+}
+
+void Game::checkTermination() {
+    auto& position = currentPosition();
+    _result = "1/2-1/2";
+    if (allLegalMoves(position).empty()) {
+        if (isInCheck(position))
+            _result = winner(!position.activeColor), _reason = TerminationReason::CHECKMATE;
+        else
+            _reason = TerminationReason::STALEMATE;
+    } else if (isDeadPosition(position.board)) {
+        _reason = TerminationReason::INSUFFICIENT_MATERIAL;
+    } else if (repetitions() >= kAutomaticDrawRepetitions) {
+        _reason = TerminationReason::REPETITION;
+    } else if (position.halfmoveClock >= kAutomaticDrawHalfmoves) {
+        _reason = TerminationReason::FIFTY_MOVES;
+    } else {
+        _result = "*", _reason = TerminationReason::NONE;
+    }
+}
+
+void Game::setResult(const std::string& result, TerminationReason reason) {
+    _result = result;
+    _reason = result == "*" ? TerminationReason::NONE : reason;
+}
+
+bool Game::resign(Color color) {
+    if (isOver()) return false;
+    setResult(winner(!color), TerminationReason::RESIGNATION);
+    return true;
+}
+
+bool Game::timeout(Color color) {
+    if (isOver()) return false;
+    auto& board = currentPosition().board;
+    bool draw = hasOnlyKing(board, !color) || isDeadPosition(board);
+    setResult(draw ? "1/2-1/2" : winner(!color), TerminationReason::TIMEOUT);
+    return true;
+}
+
+bool Game::claimDraw(DrawClaim claim) {
+    auto claims = claimableDraws();
+    if (isOver() || std::find(claims.begin(), claims.end(), claim) == claims.end()) return false;
+    auto reason = claim == DrawClaim::THREEFOLD_REPETITION ? TerminationReason::REPETITION
+                                                           : TerminationReason::FIFTY_MOVES;
+    setResult("1/2-1/2", reason);
+    return true;
+}
+
+pgn::Game Game::toPGN() const {
+    pgn::Game game;
+    game.tags = _tags;
+    auto start = fen::to_string(startPosition());
+    if (start != fen::initialPosition)
+        updateTag(game.tags, "SetUp", "1"), updateTag(game.tags, "FEN", start);
+    updateTag(game.tags, "Result", _result);
+
+    // Values from the PGN standard, which doesn't distinguish the reasons within the rules
+    auto termination = "normal";
+    if (_reason == TerminationReason::NONE) termination = "unterminated";
+    if (_reason == TerminationReason::TIMEOUT) termination = "time forfeit";
+    if (_reason == TerminationReason::ADJUDICATION) termination = "adjudication";
+    updateTag(game.tags, "Termination", termination);
+
+    auto* node = &game.root;
+    for (auto& move : _moves) {
+        node = &node->children.emplace_back();
+        node->move = move.san;
+    }
+    game.result = _result;
+    return game;
+}
+
+std::string Game::tag(const std::string& name) const {
+    for (auto& [tagName, value] : _tags)
+        if (tagName == name) return value;
+    return "";
+}
+
+void Game::setTag(const std::string& name, const std::string& value) {
+    updateTag(_tags, name, value);
+}
@@ -0,0 +1,154 @@
+#include <chrono>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "common.h"
+#include "pgn.h"
+
+#pragma once
+
+/**
+ * A move made in a game, with its SAN representation and the time it was made, measured from
+ * the start of the game.
+ */
+struct GameMove {
+    Move move;
+    std::string san;
+    std::chrono::milliseconds timestamp{0};
+};
+
+/**
+ * Draws that a player may claim, per the FIDE Laws of Chess.
+ */
+enum class DrawClaim {
+    THREEFOLD_REPETITION,  // The current position occurred at least three times
+    FIFTY_MOVES,           // Fifty moves by each side without pawn moves or captures
+};
+
+/**
+ * The reason a game ended, or NONE for an ongoing game.
+ */
+enum class TerminationReason {
+    NONE,
+    CHECKMATE,
+    STALEMATE,
+    RESIGNATION,
+    TIMEOUT,
+    REPETITION,
+    FIFTY_MOVES,
+    INSUFFICIENT_MATERIAL,
+    ADJUDICATION,
+};
+
+/**
+ * Returns a description of the termination reason, like "checkmate" or "fifty-move rule".
+ */
+std::string to_string(TerminationReason reason);
+
+/**
+ * An in-progress or finished game: the start position, the moves made so far with the resulting
+ * positions, the PGN tag pairs and the result. The game has a current ply, which is the end of the
+ * game unless the user navigated back to an earlier position. Making a move at an earlier ply
+ * replaces the rest of the game.
+ */
+class Game {
+public:
+    using Tags = std::vector<std::pair<std::string, std::string>>;
+
+    Game();
+    explicit Game(const Position& start);
+
+    // Repetitions and moves after which the game is drawn, without any claim
+    static constexpr int kAutomaticDrawRepetitions = 5;
+    static constexpr int kAutomaticDrawHalfmoves = 150;
+
+    /**
+     * Makes the move in the current position, if it is legal. Only the squares and promotion
+     * piece of the move are used, so moves in coordinate notation are accepted. Moves made at an
+     * earlier ply reset the result. Returns false and leaves the game unchanged if the move is
+     * illegal, or if the game is over. The game ends after checkmate or stalemate, when neither
+     * side can checkmate, or in a draw after a fivefold repetition or 75 moves without pawn moves
+     * or captures, unless the last move checkmates.
+     */
+    bool pushMove(Move move, std::chrono::milliseconds timestamp = {});
+
+    /**
+     * Like the above, but with the move in Standard Algebraic Notation.
+     */
+    bool pushMove(const std::string& san, std::chrono::milliseconds timestamp = {});
+
+    /**
+     * Navigates to the position after the given number of plies, returning false if the game
+     * doesn't have that many moves.
+     */
+    bool gotoPly(size_t ply);
+
+    size_t ply() const { return _ply; }
+    const Position& startPosition() const { return _positions.front(); }
+    const Position& currentPosition() const { return _positions[_ply]; }
+
+    /**
+     * Returns all moves of the game, including any following the current ply.
+     */
+    const std::vector<GameMove>& moves() const { return _moves; }
+
+    /**
+     * Returns the number of times the current position occurred in the game so far. Positions
+     * are the same if the same player is to move, with the same pieces on the same squares and
+     * the same possible moves, including castling and en passant captures.
+     */
+    int repetitions() const;
+
+    /**
+     * Returns the draws that the player to move can claim in the current position.
+     */
+    std::vector<DrawClaim> claimableDraws() const;
+
+    const Tags& tags() const { return _tags; }
+    std::string tag(const std::string& name) const;
+    void setTag(const std::string& name, const std::string& value);
+
+    /**
+     * The result of the game as in PGN: "1-0", "0-1", "1/2-1/2" or "*" for an ongoing game.
+     */
+    const std::string& result() const { return _result; }
+    TerminationReason terminationReason() const { return _reason; }
+
+    /**
+     * Ends the game with the given result and reason, such as after adjudication by a match
+     * runner or when reading a finished game.
+     */
+    void setResult(const std::string& result,
+                   TerminationReason reason = TerminationReason::ADJUDICATION);
+
+    /**
+     * Ends the game by the given player resigning, or running out of time. Running out of time
+     * is a draw if the opponent has just a king left, or neither side can checkmate. Returns
+     * false if the game was already over.
+     */
+    bool resign(Color color);
+    bool timeout(Color color);
+
+    /**
+     * Ends the game in a draw claimed by the player to move, returning false if the claim is not
+     * valid in the current position or the game was already over.
+     */
+    bool claimDraw(DrawClaim claim);
+
+    /**
+     * Returns the game in PGN form, with the Result and Termination tags set.
+     */
+    pgn::Game toPGN() const;
+
+private:
+    bool isOver() const { return _ply == _moves.size() && _reason != TerminationReason::NONE; }
+    void checkTermination();
+
+    Tags _tags;
+    std::string _result = "*";
+    TerminationReason _reason = TerminationReason::NONE;
+    std::vector<GameMove> _moves;
+    std::vector<Position> _positions;  // The start position and the position after each move
+    size_t _ply = 0;
+};
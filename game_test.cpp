@@ -0,0 +1,199 @@
+#include <cassert>
+#include <iostream>
+#include <sstream>
+#include <vector>
+
+#include "fen.h"
+#include "game.h"
+
+using namespace std::chrono_literals;
+
+void testPushMove() {
+    Game game;
+    assert(game.ply() == 0 && game.moves().empty());
+    assert(game.pushMove("e4", 1500ms));
+    assert(game.pushMove(Move("e7"_sq, "e5"_sq, Move::QUIET), 3000ms));
+    assert(game.pushMove("Nf3"));
+    assert(!game.pushMove("Nf3"));                                 // Illegal for black
+    assert(!game.pushMove(Move("e5"_sq, "e4"_sq, Move::QUIET)));  // Blocked
+    assert(game.ply() == 3 && game.moves().size() == 3);
+    assert(game.moves()[1].san == "e5" && game.moves()[1].timestamp == 3000ms);
+    assert(fen::to_epd(game.currentPosition()) ==
+           "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq -");
+
+    // Promotions need the right piece, castling is recognized from the king move
+    Game promotion(fen::parsePosition("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1"));
+    assert(promotion.pushMove(Move("b7"_sq, "b8"_sq, MoveKind::KNIGHT_PROMOTION)));
+    assert(promotion.moves().back().san == "b8=N");
+    assert(promotion.pushMove("Kf7"));
+    assert(promotion.pushMove(Move("e1"_sq, "g1"_sq, Move::QUIET)));
+    assert(promotion.moves().back().san == "O-O+");
+
+    std::cout << "All pushMove tests passed!" << std::endl;
+}
+
+void testGotoPly() {
+    Game game;
+    for (auto move : {"d4", "d5", "c4", "e6"}) assert(game.pushMove(move));
+    assert(!game.gotoPly(5));
+    assert(game.gotoPly(0));
+    assert(fen::to_string(game.currentPosition()) == fen::initialPosition);
+    assert(game.gotoPly(2) && game.currentPosition().activeColor == Color::WHITE);
+    assert(game.moves().size() == 4);
+
+    // Making a move replaces the rest of the game
+    assert(game.pushMove("Nc3"));
+    assert(game.ply() == 3 && game.moves().size() == 3 && game.moves().back().san == "Nc3");
+    assert(!game.gotoPly(4));
+
+    std::cout << "All gotoPly tests passed!" << std::endl;
+}
+
+void testRepetition() {
+    Game game;
+    for (int i = 0; i < 2; ++i)
+        for (auto move : {"Nf3", "Nf6", "Ng1", "Ng8"}) assert(game.pushMove(move));
+    assert(game.repetitions() == 3);
+    assert(game.claimableDraws() == std::vector<DrawClaim>{DrawClaim::THREEFOLD_REPETITION});
+    assert(game.gotoPly(7) && game.repetitions() == 2 && game.claimableDraws().empty());
+    assert(game.gotoPly(8));
+
+    // The fifth occurrence ends the game
+    for (int i = 0; i < 2; ++i)
+        for (auto move : {"Nc3", "Nc6", "Nb1", "Nb8"}) assert(game.pushMove(move));
+    assert(game.repetitions() == 5 && game.result() == "1/2-1/2");
+    assert(!game.pushMove("e4"));
+
+    // Going back to an earlier ply continues the game
+    assert(game.gotoPly(15) && game.pushMove("Nb8") && game.result() == "1/2-1/2");
+    assert(game.gotoPly(15) && game.pushMove("e5") && game.result() == "*");
+
+    std::cout << "All repetition tests passed!" << std::endl;
+}
+
+void testEnPassantRepetition() {
+    // An en passant target that can't be used doesn't make the position different
+    Game game(fen::parsePosition("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+    for (auto move : {"e4", "Kd7", "Kd2", "Ke8", "Ke1"}) assert(game.pushMove(move));
+    assert(game.repetitions() == 2);
+
+    // But one that can be used does
+    Game capture(fen::parsePosition("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1"));
+    for (auto move : {"e4", "Kd7", "Kd2", "Ke8", "Ke1"}) assert(capture.pushMove(move));
+    assert(capture.repetitions() == 1);
+
+    std::cout << "All en passant repetition tests passed!" << std::endl;
+}
+
+void testFiftyMoves() {
+    Game game(fen::parsePosition("4k3/8/8/8/8/8/8/4K2R w - - 99 80"));
+    assert(game.claimableDraws().empty());
+    assert(game.pushMove("Kf1"));
+    assert(game.claimableDraws() == std::vector<DrawClaim>{DrawClaim::FIFTY_MOVES});
+    assert(game.result() == "*");
+
+    // After 75 moves the game is drawn, unless the last move mates
+    Game late(fen::parsePosition("k7/8/1K6/8/8/8/8/7R w - - 149 100"));
+    assert(late.pushMove("Rh7") && late.result() == "1/2-1/2");
+    assert(late.gotoPly(0) && late.pushMove("Rh8") && late.result() == "1-0");
+    assert(late.moves().back().san == "Rh8#");
+
+    std::cout << "All fifty moves tests passed!" << std::endl;
+}
+
+void testResult() {
+    Game foolsMate;
+    for (auto move : {"f3", "e5", "g4"}) assert(foolsMate.pushMove(move));
+    assert(foolsMate.result() == "*" && foolsMate.terminationReason() == TerminationReason::NONE);
+    assert(foolsMate.pushMove("Qh4"));
+    assert(foolsMate.result() == "0-1");
+    assert(foolsMate.terminationReason() == TerminationReason::CHECKMATE);
+    assert(!foolsMate.pushMove("Kf2") && !foolsMate.resign(Color::WHITE));
+
+    Game stalemate(fen::parsePosition("k7/8/1Q6/8/8/8/8/7K w - - 0 1"));
+    assert(stalemate.pushMove("Kg2") && stalemate.result() == "1/2-1/2");
+    assert(stalemate.terminationReason() == TerminationReason::STALEMATE);
+
+    Game material(fen::parsePosition("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1"));
+    assert(material.pushMove("Kxd2") && material.result() == "1/2-1/2");
+    assert(material.terminationReason() == TerminationReason::INSUFFICIENT_MATERIAL);
+
+    // Bishops on squares of one color can't checkmate, opposite colored bishops can
+    assert(Game(fen::parsePosition("4k3/8/3b4/8/8/8/3B4/4K3 w - - 0 1")).result() == "1/2-1/2");
+    assert(Game(fen::parsePosition("4k3/8/2b5/8/8/8/3B4/4K3 w - - 0 1")).result() == "*");
+
+    std::cout << "All result tests passed!" << std::endl;
+}
+
+void testEndGame() {
+    Game resign;
+    assert(resign.resign(Color::BLACK) && resign.result() == "1-0");
+    assert(resign.terminationReason() == TerminationReason::RESIGNATION);
+    assert(!resign.timeout(Color::WHITE));
+
+    Game timeout(fen::parsePosition("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1"));
+    assert(timeout.timeout(Color::BLACK) && timeout.result() == "1-0");
+    Game lone(fen::parsePosition("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1"));
+    assert(lone.timeout(Color::WHITE) && lone.result() == "1/2-1/2");
+    assert(lone.terminationReason() == TerminationReason::TIMEOUT);
+
+    Game claim;
+    assert(!claim.claimDraw(DrawClaim::THREEFOLD_REPETITION));
+    for (int i = 0; i < 2; ++i)
+        for (auto move : {"Nf3", "Nf6", "Ng1", "Ng8"}) assert(claim.pushMove(move));
+    assert(!claim.claimDraw(DrawClaim::FIFTY_MOVES));
+    assert(claim.claimDraw(DrawClaim::THREEFOLD_REPETITION) && claim.result() == "1/2-1/2");
+    assert(claim.terminationReason() == TerminationReason::REPETITION);
+    assert(to_string(claim.terminationReason()) == "repetition");
+
+    std::cout << "All end game tests passed!" << std::endl;
+}
+
+void testToPGN() {
+    Game game(fen::parsePosition("4k3/8/8/8/8/8/4P3/R3K3 w Q - 0 1"));
+    game.setTag("White", "Alice");
+    for (auto move : {"O-O-O", "Kf7", "e4"}) assert(game.pushMove(move));
+    assert(game.timeout(Color::BLACK));
+
+    std::stringstream out;
+    pgn::writeGame(out, game.toPGN());
+    assert(out.str() ==
+           "[White \"Alice\"]\n"
+           "[SetUp \"1\"]\n"
+           "[FEN \"4k3/8/8/8/8/8/4P3/R3K3 w Q - 0 1\"]\n"
+           "[Result \"1-0\"]\n"
+           "[Termination \"time forfeit\"]\n"
+           "\n"
+           "1. O-O-O Kf7 2. e4 1-0\n"
+           "\n");
+
+    std::cout << "All toPGN tests passed!" << std::endl;
+}
+
+void testTags() {
+    Game game;
+    assert(game.tag("White").empty() && game.result() == "*");
+    game.setTag("White", "Deep Blue");
+    game.setTag("Black", "Kasparov");
+    game.setTag("White", "Kasparov");
+    assert(game.tags().size() == 2 && game.tag("White") == "Kasparov");
+    game.setResult("1/2-1/2");
+    assert(game.result() == "1/2-1/2");
+    assert(game.terminationReason() == TerminationReason::ADJUDICATION);
+
+    std::cout << "All tag tests passed!" << std::endl;
+}
+
+int main() {
+    testPushMove();
+    testGotoPly();
+    testRepetition();
+    testEnPassantRepetition();
+    testFiftyMoves();
+    testResult();
+    testEndGame();
+    testToPGN();
+    testTags();
+    std::cout << "All game tests passed!" << std::endl;
+    return 0;
+}
@@ -0,0 +1,217 @@
+#include <algorithm>
+#include <array>
+#include <cstdint>
+#include <random>
+#include <unordered_set>
+#include <vector>
+
+#include "common.h"
+#include "moves.h"
+
+#pragma once
+
+// Implement a hashing method for chess positions using Zobrist hashing
+// https://en.wikipedia.org/wiki/Zobrist_hashing This relies just on the number of locations
+// ("squares") and number of pieces, where we assume piece 0 to be "no piece". The hash allows for
+// efficient incremental updating of the hash value when a move is made.
+
+// 1 for black to move, 1 for each castling right, 8 for en passant file, and 8 rook files for each
+// castling right whose rook isn't on the standard file, as in chess960
+static constexpr int kNumExtraVectors = 56;
+static constexpr int kNumBoardVectors = kNumPieces * kNumSquares;
+static constexpr int kNumHashVectors = kNumBoardVectors + kNumExtraVectors;
+
+// A random 64-bit integer for each piece on each square, as well as the extra vectors. The first
+// piece is None, but it is not omitted here, as it allows removing a hard-to-predict branch in the
+// hash function.
+inline const std::array<uint64_t, kNumHashVectors> hashVectors = []() {
+    std::array<uint64_t, kNumHashVectors> vectors;
+    std::ranlux48 gen(0xbad5eed5'bad5eed5);
+    for (auto& v : vectors) v = gen();
+    return vectors;
+}();
+
+// A Hash is a 64-bit integer that represents a position. It is the XOR of the hash vectors for
+// each piece on each square, as well as the applicable extra vectors.
+class Hash {
+    uint64_t hash = 0;
+
+public:
+    enum ExtraVectors {
+        BLACK_TO_MOVE = 0,
+        CASTLING_1 = 1,
+        CASTLING_15 = 15,
+        EN_PASSANT_A = 16,
+        EN_PASSANT_H = 23,
+        CASTLING_ROOK_FILE = 24,  // 8 files for each castling right, in order of kCastlingRights
+    };
+
+    Hash() = default;
+    Hash(Position position) {
+        for (auto square : SquareSet::occupancy(position.board))
+            toggle(position.board[square], square.index());
+        if (position.activeColor == Color::BLACK) toggle(BLACK_TO_MOVE);
+        if (position.castlingAvailability != CastlingMask::NONE)
+            toggle(ExtraVectors(CASTLING_1 - 1 + uint8_t(position.castlingAvailability)));
+        for (size_t index = 0; index != kCastlingRights.size(); ++index) {
+            auto file = position.castlingRookFiles[index];
+            if (contains(position.castlingAvailability, kCastlingRights[index]) &&
+                file != Position::kStandardRookFiles[index])
+                toggle(ExtraVectors(CASTLING_ROOK_FILE + index * kNumFiles + file));
+        }
+        if (position.enPassantTarget)
+            toggle(ExtraVectors(position.enPassantTarget->file() + EN_PASSANT_A));
+    }
+
+    uint64_t operator()() const { return hash; }
+
+    void move(Piece piece, int from, int to) {
+        toggle(piece, from);
+        toggle(piece, to);
+    }
+    void capture(Piece piece, Piece target, int from, int to) {
+        toggle(piece, from);
+        toggle(target, to);
+        toggle(piece, to);
+    }
+
+    // Does not cancel out castling rights or en passant targets.
+    // Assumes that passed in board is the same as the board used to construct this hash.
+    void applyMove(const Board& board, Move mv) {
+        auto piece = board[mv.from];
+        auto target = board[mv.to];
+        move(piece, mv.from.index(), mv.to.index());
+        switch (mv.kind) {
+        case MoveKind::QUIET_MOVE: break;
+        case MoveKind::DOUBLE_PAWN_PUSH: toggle(ExtraVectors(mv.to.file() + EN_PASSANT_A)); break;
+        case MoveKind::KING_CASTLE:  // Assume the move has the king move, so adjust the rook here.
+            move(addColor(PieceType::ROOK, color(piece)),
+                 (color(piece) == Color::WHITE ? Position::whiteKingSideRook
+                                               : Position::blackKingSideRook)
+                     .index(),
+                 (color(piece) == Color::WHITE ? Position::whiteRookCastledKingSide
+                                               : Position::blackRookCastledKingSide)
+                     .index());
+            break;
+        case MoveKind::QUEEN_CASTLE:  // Assume the move has the king move, so adjust the rook here.
+            move(addColor(PieceType::ROOK, color(piece)),
+                 (color(piece) == Color::WHITE ? Position::whiteQueenSideRook
+                                               : Position::blackQueenSideRook)
+                     .index(),
+                 (color(piece) == Color::WHITE ? Position::whiteRookCastledQueenSide
+                                               : Position::blackRookCastledQueenSide)
+                     .index());
+            break;
+
+        case MoveKind::CAPTURE: toggle(target, mv.to.index()); break;
+        case MoveKind::EN_PASSANT:
+            // Depending of the color of our piece, the captured pawn is either above or below the
+            // destination square.
+            toggle(target, mv.to.index() + (color(piece) == Color::WHITE ? -kNumFiles : kNumFiles));
+            break;
+        case MoveKind::KNIGHT_PROMOTION:
+        case MoveKind::BISHOP_PROMOTION:
+        case MoveKind::ROOK_PROMOTION:
+        case MoveKind::QUEEN_PROMOTION:
+            toggle(piece, mv.to.index());  // Remove the pawn, add the promoted piece
+            toggle(addColor(promotionType(mv.kind), color(piece)), mv.to.index());
+            break;
+        case MoveKind::KNIGHT_PROMOTION_CAPTURE:
+        case MoveKind::BISHOP_PROMOTION_CAPTURE:
+        case MoveKind::ROOK_PROMOTION_CAPTURE:
+        case MoveKind::QUEEN_PROMOTION_CAPTURE:
+            toggle(target, mv.to.index());  // Remove the captured piece
+            toggle(piece, mv.to.index());   // Remove the pawn, add the promoted piece
+            toggle(addColor(promotionType(mv.kind), color(piece)), mv.to.index());
+            break;
+        }
+    }
+
+    // Use toggle to add/remove a piece or non piece/location vector.
+    void toggle(Piece piece, int location) { toggle(index(piece) * kNumSquares + location); }
+    void toggle(int vector) { hash ^= hashVectors[vector]; }
+    void toggle(ExtraVectors extra) { toggle(kNumBoardVectors + int(extra)); }
+};
+
+/**
+ * How a stored score relates to the true score of a position: equal to it, or only a lower or an
+ * upper bound, as when a search fails high or low. Empty entries have no bound.
+ */
+enum class Bound : uint8_t { NONE, EXACT, LOWER, UPPER };
+
+/**
+ * Fixed-size cache of search results by position hash. Each hash maps to a bucket of a few entries,
+ * so positions that share the low bits of their hash can all be kept. When a bucket is full, the
+ * entry with the least remaining depth (draft) is replaced, as it is the cheapest to recompute.
+ * The value holds the score and best move for searches, or the node count for perft.
+ */
+template <typename Value, size_t kBucketSize = 4>
+class TranspositionTable {
+public:
+    struct Entry {
+        Hash hash;
+        int16_t draft = 0;  // Remaining search depth of the entry
+        Bound bound = Bound::NONE;
+        Value value{};
+    };
+
+    // A table of 2^bits buckets
+    explicit TranspositionTable(int bits)
+        : buckets(size_t(1) << bits), mask((size_t(1) << bits) - 1) {}
+
+    /**
+     * Returns the entry for the hash, regardless of its draft, or nullptr if there is none.
+     */
+    const Entry* probe(Hash hash) const {
+        for (auto& entry : buckets[hash() & mask])
+            if (entry.bound != Bound::NONE && entry.hash() == hash()) return &entry;
+        return nullptr;
+    }
+
+    /**
+     * Stores the value, replacing any entry for the same hash, otherwise an empty entry or the one
+     * with the least draft in the bucket.
+     */
+    void store(Hash hash, int draft, Bound bound, const Value& value) {
+        auto& bucket = buckets[hash() & mask];
+        auto* victim = &bucket.front();
+        for (auto& entry : bucket) {
+            if (entry.bound != Bound::NONE && entry.hash() == hash()) {
+                victim = &entry;
+                break;
+            }
+            if (victim->bound != Bound::NONE &&
+                (entry.bound == Bound::NONE || entry.draft < victim->draft))
+                victim = &entry;
+        }
+        *victim = {hash, int16_t(draft), bound, value};
+    }
+
+    void clear() { std::fill(buckets.begin(), buckets.end(), Bucket()); }
+
+    size_t capacity() const { return buckets.size() * kBucketSize; }
+
+private:
+    using Bucket = std::array<Entry, kBucketSize>;
+    std::vector<Bucket> buckets;
+    size_t mask;
+};
+
+/**
+ * Returns the canonical forms of the positions, see canonical, without duplicates, in the order of
+ * their first occurrence, as needed for clean training and tuning data from large corpora. Only
+ * hashes are kept to find duplicates, so positions with the same 64-bit hash count as the same.
+ */
+inline std::vector<Position> deduplicate(const std::vector<Position>& positions,
+                                         CanonicalOptions options = {}) {
+    std::vector<Position> unique;
+    std::unordered_set<uint64_t> seen;
+    for (auto& position : positions) {
+        auto form = canonical(position, options);
+        // The hash ignores the clocks, so mix them in for when they're kept
+        auto clocks = uint64_t(form.halfmoveClock) << 16 | form.fullmoveNumber;
+        if (seen.insert(Hash(form)() ^ clocks * 0x9e3779b97f4a7c15ull).second)
+            unique.push_back(form);
+    }
+    return unique;
+}
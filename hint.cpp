@@ -0,0 +1,120 @@
+#include <algorithm>
+#include <cstdlib>
+#include <vector>
+
+#include "engine.h"
+#include "hint.h"
+#include "search.h"
+
+namespace {
+std::string name(PieceType type) {
+    static const char* names[] = {"pawn", "knight", "bishop", "rook", "queen", "king"};
+    return names[index(type)];
+}
+
+/**
+ * Returns the total value of the pieces of the side to move, less those of the opponent.
+ */
+int material(const Position& position) {
+    auto trace = traceEvaluation(position.board);
+    auto material = trace.whiteMaterial + trace.blackMaterial;
+    return position.activeColor == Color::WHITE ? material : -material;
+}
+
+/**
+ * Returns true if the side that just moved could mate with its next move, were it to move again.
+ */
+bool threatensMate(const Position& position) {
+    auto again = position;
+    again.activeColor = !position.activeColor;
+    again.enPassantTarget.reset();
+    for (auto& [move, next] : allLegalMoves(again))
+        if (isInCheck(next) && allLegalMoves(next).empty()) return true;
+    return false;
+}
+
+/**
+ * Joins the reasons like "a, b and c".
+ */
+std::string join(const std::vector<std::string>& reasons) {
+    std::string joined;
+    for (size_t i = 0; i < reasons.size(); ++i) {
+        if (i) joined += i + 1 == reasons.size() ? " and " : ", ";
+        joined += reasons[i];
+    }
+    return joined;
+}
+}  // namespace
+
+std::string explainMove(const Position& position,
+                        const EvaluatedMove& best,
+                        const MoveVector& pv) {
+    auto move = best.move;
+    auto& board = position.board;
+    auto piece = board[move.from];
+    auto side = color(piece);
+    auto after = applyMove(position, move);
+    std::vector<std::string> reasons;
+
+    auto mate = search::mateDistance(best);
+    if (mate == 1)
+        reasons.push_back("mates");
+    else if (mate > 0)
+        reasons.push_back("mates in " + std::to_string(mate));
+    else if (mate < 0)
+        reasons.push_back("holds out longest against mate in " + std::to_string(-mate));
+
+    // Material won right away, unless the opponent can recapture at a profit
+    bool capture = (move.kind | MoveKind::CAPTURE_MASK) == move.kind;
+    if (capture && mate <= 0) {
+        auto exchange = staticExchange(board, move);
+        auto target = move.kind == MoveKind::EN_PASSANT ? Square(move.from.rank(), move.to.file())
+                                                        : move.to;
+        auto captured = board[target];
+        auto value = std::abs(traceEvaluation(board).squares[target.index()]);
+        if (exchange >= value && value > 0)
+            reasons.push_back("wins a " + name(type(captured)));
+        else if (exchange > 0)
+            reasons.push_back("wins material");
+        else if (exchange == 0)
+            reasons.push_back("trades a " + name(type(piece)) + " for a " + name(type(captured)));
+    }
+    if (move.isPromotion()) reasons.push_back("promotes to a " + name(promotionType(move.kind)));
+
+    // Material won later on, as when a capture follows a threat
+    if (reasons.empty() && !pv.empty() && pv.front() == move) {
+        auto end = position;
+        for (auto next : pv) end = applyMove(end, next);
+        auto gain = (pv.size() % 2 ? -material(end) : material(end)) - material(position);
+        if (gain > 0) reasons.push_back("wins material later on");
+    }
+
+    if (type(piece) == PieceType::PAWN && !capture && !move.isPromotion()) {
+        auto opponentPawns = SquareSet::find(board, addColor(PieceType::PAWN, !side));
+        if ((passedPawnMask(side, move.from) & opponentPawns).empty())
+            reasons.push_back("pushes a passed pawn");
+    }
+    if (mate <= 0 && isInCheck(after))
+        reasons.push_back("gives check");
+    else if (mate <= 0 && threatensMate(after))
+        reasons.push_back("threatens mate");
+    if (move.kind == MoveKind::KING_CASTLE || move.kind == MoveKind::QUEEN_CASTLE)
+        reasons.push_back("castles");
+
+    if (reasons.empty()) reasons.push_back("improves the position");
+    return join(reasons);
+}
+
+Hint hint(const Position& position, int effort) {
+    AlphaBetaEngine engine;
+    engine.setPosition(position, {});
+    search::Limits limits;
+    limits.depth = std::clamp(effort, 1, search::kMaxDepth);
+    auto result = engine.go(limits);
+
+    Hint hint;
+    hint.best = result.best;
+    hint.pv = result.pv;
+    if (hint.best.move) hint.explanation = explainMove(position, hint.best, hint.pv);
+    return hint;
+}
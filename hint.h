@@ -0,0 +1,33 @@
+#include <string>
+
+#include "common.h"
+#include "eval.h"
+#include "moves.h"
+
+#pragma once
+
+/**
+ * A suggested move for the side to move, with a short explanation for human players, such as
+ * "wins a knight and gives check" or "threatens mate".
+ */
+struct Hint {
+    EvaluatedMove best;       // The move is invalid if there are no legal moves
+    MoveVector pv;            // Principal variation, starting with the best move
+    std::string explanation;  // Empty if there is no move
+};
+
+/**
+ * Searches the position to the depth given by the effort, from 1 up, and explains the best move
+ * found, see explainMove. Higher efforts give better hints, but take exponentially longer.
+ */
+Hint hint(const Position& position, int effort);
+
+/**
+ * Returns why the move, which must be legal, is good, by looking at the move itself, its
+ * evaluation and the principal variation starting with it: whether it mates, wins material as
+ * found by static exchange evaluation or later in the variation, promotes, pushes a passed pawn,
+ * gives check, threatens mate or castles. Without any of these, it improves the position.
+ */
+std::string explainMove(const Position& position,
+                        const EvaluatedMove& best,
+                        const MoveVector& pv);
@@ -0,0 +1,71 @@
+#include <cassert>
+#include <iostream>
+#include <string>
+
+#include "fen.h"
+#include "hint.h"
+
+namespace {
+/**
+ * Returns the explanation of the move from the coordinates, as if the search found it at depth 1.
+ */
+std::string explain(const std::string& fen,
+                    Square from,
+                    Square to,
+                    std::optional<PieceType> promotion = std::nullopt) {
+    auto position = fen::parsePosition(fen);
+    auto move = inferMove(position.board, from, to, promotion);
+    return explainMove(position, {move, false, false, 0, 1}, {move});
+}
+}  // namespace
+
+void testExplainMove() {
+    // Captures win the piece only if it can't be recaptured profitably
+    assert(explain("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", "e4"_sq, "d5"_sq) == "wins a knight");
+    assert(explain("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1", "e4"_sq, "d5"_sq) == "wins material");
+    assert(explain("4k3/8/2p5/3n4/8/8/8/3NK3 w - - 0 1", "d1"_sq, "e3"_sq) ==
+           "improves the position");
+    assert(explain("4k3/8/2p5/3r4/8/8/8/3RK3 w - - 0 1", "d1"_sq, "d5"_sq) ==
+           "trades a rook for a rook");
+
+    // Promotions, passed pawns, checks, mate threats and castling
+    assert(explain("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7"_sq, "b8"_sq, PieceType::QUEEN) ==
+           "wins a knight, promotes to a queen and gives check");
+    assert(explain("4k3/8/8/8/8/8/P7/4K3 w - - 0 1", "a2"_sq, "a4"_sq) == "pushes a passed pawn");
+    assert(explain("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1"_sq, "a8"_sq) == "gives check");
+    assert(explain("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1", "e1"_sq, "d2"_sq) == "threatens mate");
+    assert(explain("r3k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1"_sq, "g1"_sq) == "castles");
+
+    // The variation shows material won later, as with a fork
+    auto position = fen::parsePosition("r3k3/8/8/3N4/8/8/8/4K3 w - - 0 1");
+    Move fork("d5"_sq, "c7"_sq, MoveKind::QUIET_MOVE);
+    Move escape("e8"_sq, "d7"_sq, MoveKind::QUIET_MOVE);
+    Move capture("c7"_sq, "a8"_sq, MoveKind::CAPTURE);
+    assert(explainMove(position, {fork, true, false, 3, 3}, {fork, escape, capture}) ==
+           "wins material later on and gives check");
+    std::cout << "All explain move tests passed!" << std::endl;
+}
+
+void testHint() {
+    // Scholar's mate
+    auto position =
+        fen::parsePosition("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4");
+    auto hint = ::hint(position, 2);
+    assert(std::string(hint.best.move) == "h5f7" && hint.explanation == "mates");
+    assert(hint.pv.size() == 1 && hint.pv.front() == hint.best.move);
+
+    hint = ::hint(fen::parsePosition("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1"), 2);
+    assert(std::string(hint.best.move) == "d1d5" && hint.explanation == "wins a knight");
+
+    // Mated players get no hint
+    hint = ::hint(fen::parsePosition("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), 2);
+    assert(!hint.best.move && hint.explanation.empty());
+    std::cout << "All hint search tests passed!" << std::endl;
+}
+
+int main() {
+    testExplainMove();
+    testHint();
+    std::cout << "All hint tests passed!" << std::endl;
+    return 0;
+}
@@ -0,0 +1,82 @@
+#include <cmath>
+#include <cstdlib>  // For std::exit
+#include <iostream>
+#include <string>
+
+#include "eval.h"
+#include "fen.h"
+#include "moves.h"
+
+/**
+ * Checks move generation and evaluation for asymmetries between the colors. For each position read
+ * from standard input, one FEN or EPD per line with anything after a '#' ignored, the position and
+ * its mirror, with the board flipped and colors swapped, must have the same perft counts, and the
+ * same evaluation with the sign reversed. The evaluations are also compared for all positions after
+ * one move. Prints the positions that differ, and exits with a non-zero status if there are any.
+ */
+
+// Evaluations are sums of floats, which may be added in a different order for the mirror
+static constexpr float kTolerance = 0.001f;
+
+/**
+ * Returns true if the evaluations of the position and its mirror are the same, apart from the sign.
+ */
+bool isSymmetric(const Position& position) {
+    auto evaluation = evaluateBoard(position.board);
+    auto mirrored = evaluateBoard(mirror(position).board);
+    if (std::abs(evaluation + mirrored) <= kTolerance) return true;
+
+    std::cout << "Evaluation " << evaluation << " differs from " << -mirrored
+              << " for the mirror of " << fen::to_string(position) << std::endl;
+    return false;
+}
+
+/**
+ * Returns true if the position and its mirror have the same perft counts for the given depth. If
+ * not, prints the counts and the first move with different counts.
+ */
+bool isSymmetric(const Position& position, int depth) {
+    auto mirrored = mirror(position);
+    auto count = perft(position, depth);
+    auto mirroredCount = perft(mirrored, depth);
+    if (count == mirroredCount) return true;
+
+    std::cout << "Perft " << depth << " is " << count << " instead of " << mirroredCount
+              << " for the mirror of " << fen::to_string(position) << std::endl;
+    for (auto& [move, newPosition] : allLegalMoves(position)) {
+        auto mirroredMove = Move(Square(kNumRanks - 1 - move.from.rank(), move.from.file()),
+                                 Square(kNumRanks - 1 - move.to.rank(), move.to.file()),
+                                 move.kind);
+        auto count = perft(newPosition, depth - 1);
+        auto mirroredCount = perft(applyMove(mirrored, mirroredMove), depth - 1);
+        if (count == mirroredCount) continue;
+        std::cout << "    after " << std::string(move) << ": " << count << " instead of "
+                  << mirroredCount << std::endl;
+        break;
+    }
+    return false;
+}
+
+int main(int argc, char** argv) {
+    if (argc > 2) {
+        std::cerr << "Usage: " << argv[0] << " [depth] < positions.epd" << std::endl;
+        std::exit(1);
+    }
+    int depth = argc > 1 ? std::atoi(argv[1]) : 3;
+
+    int numPositions = 0;
+    int numAsymmetric = 0;
+    auto errors = fen::parseMany(std::cin, [&](Position position) {
+        ++numPositions;
+        bool symmetric = isSymmetric(position, depth) && isSymmetric(position);
+        for (auto& [move, newPosition] : allLegalMoves(position))
+            symmetric = isSymmetric(newPosition) && symmetric;
+        numAsymmetric += !symmetric;
+    });
+    for (auto& error : errors)
+        std::cerr << "Skipping invalid position on line " << error.line << ": " << error.text
+                  << std::endl;
+    std::cout << numPositions << " positions checked, " << numAsymmetric << " asymmetric"
+              << std::endl;
+    return numAsymmetric != 0;
+}
@@ -0,0 +1,96 @@
+#include <chrono>
+#include <cstdlib>  // For std::exit
+#include <iomanip>
+#include <iostream>
+#include <string>
+#include <vector>
+
+#include "fen.h"
+#include "moves.h"
+#include "parallel.h"
+
+/**
+ * Measures move generation throughput over a corpus of positions read from standard input, one FEN
+ * or EPD per line, with anything after a '#' ignored. The corpus is extended with all positions
+ * reachable in the given number of plies. Pseudo-legal generation, legal generation and applying
+ * the legal moves are timed separately, to show where the cost of move generation is.
+ */
+
+using Clock = std::chrono::steady_clock;
+
+// Results of applying moves are added here, so the compiler can't optimize them away
+static uint64_t sink = 0;
+
+/**
+ * Returns the positions, followed by all positions reachable from them in up to depth plies. Moves
+ * are generated in parallel, as corpora grow quickly with the depth.
+ */
+std::vector<Position> expand(std::vector<Position> positions, int depth) {
+    auto begin = size_t(0);
+    for (int ply = 0; ply < depth; ++ply) {
+        auto end = positions.size();
+        auto frontier = std::vector<Position>(positions.begin() + begin, positions.end());
+        for (auto& moves : parallelMap(frontier, allLegalMoves))
+            for (auto& [move, newPosition] : moves) positions.push_back(newPosition);
+        begin = end;
+    }
+    return positions;
+}
+
+/**
+ * Runs the function, which returns a number of moves, on all positions, and prints the rates.
+ */
+template <typename F>
+void measure(const std::string& name, const std::vector<Position>& positions, const F& fun) {
+    uint64_t moves = 0;
+    auto start = Clock::now();
+    for (auto& position : positions) moves += fun(position);
+    auto seconds = std::chrono::duration<double>(Clock::now() - start).count();
+
+    std::cout << std::left << std::setw(12) << name << std::right << std::fixed
+              << std::setprecision(1) << std::setw(10) << positions.size() / seconds / 1000
+              << "K positions/sec" << std::setw(10) << moves / seconds / 1000 << "K moves/sec"
+              << std::endl;
+}
+
+int main(int argc, char** argv) {
+    if (argc > 2) {
+        std::cerr << "Usage: " << argv[0] << " [depth] < positions.epd" << std::endl;
+        std::exit(1);
+    }
+    int depth = argc > 1 ? std::atoi(argv[1]) : 0;
+
+    std::vector<Position> positions;
+    auto errors =
+        fen::parseMany(std::cin, [&](Position position) { positions.push_back(position); });
+    for (auto& error : errors)
+        std::cerr << "Skipping invalid position on line " << error.line << ": " << error.text
+                  << std::endl;
+    positions = expand(positions, depth);
+    std::cout << positions.size() << " positions" << std::endl;
+
+    measure("pseudolegal", positions, [](const Position& position) {
+        MoveVector moves;
+        addAvailableMoves(moves, position.board, position.activeColor);
+        addAvailableCaptures(moves, position.board, position.activeColor);
+        auto target = position.enPassantTarget;
+        addAvailableEnPassant(moves, position.board, position.activeColor, target);
+        return moves.size();
+    });
+    measure("legal", positions, [](const Position& position) {
+        return allLegalMoves(position).size();
+    });
+
+    // Applying the legal moves, which allLegalMoves does as well, to separate it from generation
+    std::vector<MoveVector> legalMoves;
+    for (auto& position : positions) {
+        legalMoves.emplace_back();
+        for (auto& [move, newPosition] : allLegalMoves(position)) legalMoves.back().push_back(move);
+    }
+    size_t next = 0;
+    measure("applyMove", positions, [&](const Position& position) {
+        for (auto move : legalMoves[next]) sink += applyMove(position, move).halfmoveClock;
+        return legalMoves[next++].size();
+    });
+    return sink == UINT64_MAX;  // Use the sink, but it never gets there
+}
@@ -1,62 +1,39 @@
 #include <algorithm>
 #include <cassert>
 #include <cmath>
+#include <cstring>
 #include <iomanip>
 #include <iostream>
 
-#include "moves.h"
-
-struct MovesTable {
-    // precomputed possible moves for each piece type on each square
-    SquareSet moves[kNumPieces][kNumSquares];
+#ifdef __SSE2__
+#include <emmintrin.h>
+#endif
 
-    // precomputed possible captures for each piece type on each square
-    SquareSet captures[kNumPieces][kNumSquares];
-
-    // precomputed paths from each square to each other square
-    SquareSet paths[kNumSquares][kNumSquares];  // from, to
-
-    // precomputed squares required to be clear for castling
-    SquareSet castlingClear[2][index(MoveKind::QUEEN_CASTLE) + 1];  // color, moveKind
-
-    // precomputed from squares for en passant targets
-    SquareSet enPassantFrom[2][kNumFiles];  // color, file
+#include "moves.h"
 
-    MovesTable();
-} movesTable;
+#ifdef __SSE2__
+uint64_t equalSetSSE2(const Board& board, Piece piece, bool invert) {
+    const auto& squares = board.squares();
+    const auto pieces = _mm_set1_epi8(static_cast<char>(piece));
+    constexpr size_t kStride = sizeof(__m128i);
 
-MovesTable::MovesTable() {
-    for (Square from = 0; from != kNumSquares; ++from) {
-        for (int piece = 0; piece != kNumPieces; ++piece) {
-            moves[piece][from.index()] = possibleMoves(Piece(piece), from);
-            captures[piece][from.index()] = possibleCaptures(Piece(piece), from);
-        }
-    }
-    for (int from = 0; from < kNumSquares; ++from) {
-        for (int to = 0; to < kNumSquares; ++to) {
-            paths[from][to] = SquareSet::path(Square(from), Square(to));
-        }
-    }
-    for (int color = 0; color < 2; ++color) {
-        int fromRank = color == 0 ? kNumRanks - 4 : 3;  // skipping 3 ranks from either side
-        for (int fromFile = 0; fromFile < kNumFiles; ++fromFile) {
-            enPassantFrom[color][fromFile] = {SquareSet::valid(fromRank, fromFile - 1) |
-                                              SquareSet::valid(fromRank, fromFile + 1)};
-        }
-        castlingClear[color][index(MoveKind::QUEEN_CASTLE)] =
-            castlingPath(Color(color), MoveKind::QUEEN_CASTLE);
-        castlingClear[color][index(MoveKind::KING_CASTLE)] =
-            castlingPath(Color(color), MoveKind::KING_CASTLE);
+    uint64_t set = 0;
+    for (size_t j = 0; j < sizeof(squares); j += kStride) {
+        auto input = _mm_loadu_si128(reinterpret_cast<const __m128i*>(&squares[j]));
+        uint64_t mask = _mm_movemask_epi8(_mm_cmpeq_epi8(input, pieces)) & 0xffff;
+        set |= mask << j;
     }
+
+    return invert ? ~set : set;
 }
+#endif
 
+namespace {
 /**
  * Returns the bits corresponding to the bytes in the input that contain the nibble.
  * Note: nibble is assumed to be at most 4 bits.
  */
 uint64_t equalSet(uint64_t input, uint8_t nibble) {
-    const uint64_t occupancyNibbles = 0x80402010'08040201ull;  // high nibbles to indicate occupancy
-    const uint64_t oneNibbles = 0x01010101'01010101ull;        // low nibbles set to one
     input ^= 0x01010101'01010101ull * nibble;  // 0 nibbles in the input indicate the piece
     input += 0x7f3f1f0f'7f3f1f0full;           // Cause overflow in the right bits
     input &= 0x80402010'80402010ull;           // These are them, the 8 occupancy bits
@@ -69,11 +46,21 @@ uint64_t equalSet(uint64_t input, uint8_t nibble) {
     return input;
 }
 
-uint64_t equalSet(std::array<Piece, 64> squares, Piece piece, bool invert) {
+uint64_t equalSet(const Board& board, Piece piece, bool invert) {
+#ifdef __SSE2__
+    return equalSetSSE2(board, piece, invert);
+#else
+    return equalSetSWAR(board, piece, invert);
+#endif
+}
+}  // namespace
+
+uint64_t equalSetSWAR(const Board& board, Piece piece, bool invert) {
     static_assert(kNumPieces <= 16, "Piece must fit in 4 bits");
+    const auto& squares = board.squares();
 
     uint64_t set = 0;
-    for (int j = 0; j < sizeof(squares); j += sizeof(uint64_t)) {
+    for (size_t j = 0; j < sizeof(squares); j += sizeof(uint64_t)) {
         uint64_t input;
         memcpy(&input, &squares[j], sizeof(input));
         set |= equalSet(input, static_cast<uint8_t>(piece)) << j;
@@ -83,14 +70,18 @@ uint64_t equalSet(std::array<Piece, 64> squares, Piece piece, bool invert) {
 }
 
 SquareSet SquareSet::occupancy(const Board& board) {
-    return equalSet(board.squares(), Piece::NONE, true);
+    return equalSet(board, Piece::NONE, true);
 }
 
 SquareSet SquareSet::find(const Board& board, Piece piece) {
-    return equalSet(board.squares(), piece, false);
+    return equalSet(board, piece, false);
+}
+
+constexpr int absolute(int value) {
+    return value < 0 ? -value : value;
 }
 
-SquareSet rookMoves(Square from) {
+constexpr SquareSet rookMoves(Square from) {
     SquareSet moves;
     for (int rank = 0; rank < kNumRanks; ++rank)
         if (rank != from.rank()) moves.insert(Square(rank, from.file()));
@@ -101,7 +92,7 @@ SquareSet rookMoves(Square from) {
     return moves;
 }
 
-SquareSet bishopMoves(Square from) {
+constexpr SquareSet bishopMoves(Square from) {
     SquareSet moves;
     for (int i = 1; i < std::min(kNumRanks, kNumFiles); ++i) {
         moves.insert(SquareSet::valid(from.rank() + i, from.file() + i));
@@ -112,42 +103,42 @@ SquareSet bishopMoves(Square from) {
     return moves;
 }
 
-SquareSet queenMoves(Square from) {
+constexpr SquareSet queenMoves(Square from) {
     // Combine the moves of a rook and a bishop
     return rookMoves(from) | bishopMoves(from);
 }
 
-SquareSet knightMoves(Square from) {
+constexpr SquareSet knightMoves(Square from) {
     int vectors[8][2] = {{-2, -1}, {-1, -2}, {1, -2}, {2, -1}, {2, 1}, {1, 2}, {-1, 2}, {-2, 1}};
 
     SquareSet moves;
-    for (auto [rank, file] : vectors)
-        moves.insert(SquareSet::valid(from.rank() + rank, from.file() + file));
+    for (auto& vector : vectors)
+        moves.insert(SquareSet::valid(from.rank() + vector[0], from.file() + vector[1]));
     return moves;
 }
 
-SquareSet kingMoves(Square from) {
+constexpr SquareSet kingMoves(Square from) {
     int vectors[8][2] = {{-1, -1}, {-1, 0}, {-1, 1}, {0, -1}, {0, 1}, {1, -1}, {1, 0}, {1, 1}};
 
     SquareSet moves;
-    for (auto [rank, file] : vectors)
-        moves.insert(SquareSet::valid(from.rank() + rank, from.file() + file));
+    for (auto& vector : vectors)
+        moves.insert(SquareSet::valid(from.rank() + vector[0], from.file() + vector[1]));
     return moves;
 }
 
-SquareSet whitePawnMoves(Square from) {
+constexpr SquareSet whitePawnMoves(Square from) {
     SquareSet moves = SquareSet::valid(from.rank() + 1, from.file());
     if (from.rank() == 1) moves.insert(SquareSet::valid(from.rank() + 2, from.file()));
     return moves;
 }
 
-SquareSet blackPawnMoves(Square from) {
+constexpr SquareSet blackPawnMoves(Square from) {
     SquareSet moves = SquareSet::valid(from.rank() - 1, from.file());
     if (from.rank() == kNumRanks - 2) moves.insert(SquareSet::valid(from.rank() - 2, from.file()));
     return moves;
 }
 
-SquareSet possibleMoves(Piece piece, Square from) {
+constexpr SquareSet computeMoves(Piece piece, Square from) {
     switch (piece) {
     case Piece::NONE: break;
     case Piece::WHITE_PAWN: return whitePawnMoves(from);
@@ -166,7 +157,7 @@ SquareSet possibleMoves(Piece piece, Square from) {
     return {};
 }
 
-SquareSet possibleCaptures(Piece piece, Square from) {
+constexpr SquareSet computeCaptures(Piece piece, Square from) {
     switch (piece) {
     case Piece::NONE: break;
     case Piece::WHITE_PAWN:                                        // White Pawn
@@ -189,19 +180,19 @@ SquareSet possibleCaptures(Piece piece, Square from) {
     return {};
 }
 
-SquareSet SquareSet::path(Square from, Square to) {
+constexpr SquareSet computePath(Square from, Square to) {
     SquareSet path;
     int rankDiff = to.rank() - from.rank();
     int fileDiff = to.file() - from.file();
 
     // Check if the move isn't horizontal, vertical, or diagonal
-    if (rankDiff != 0 && fileDiff != 0 && abs(rankDiff) != abs(fileDiff)) {
+    if (rankDiff != 0 && fileDiff != 0 && absolute(rankDiff) != absolute(fileDiff)) {
         return path;  // It's not in straight line, thus no need to check further
     }
 
     // Calculate the direction of movement for rank and file
-    int rankStep = (rankDiff != 0) ? rankDiff / abs(rankDiff) : 0;
-    int fileStep = (fileDiff != 0) ? fileDiff / abs(fileDiff) : 0;
+    int rankStep = (rankDiff != 0) ? rankDiff / absolute(rankDiff) : 0;
+    int fileStep = (fileDiff != 0) ? fileDiff / absolute(fileDiff) : 0;
 
     int rankPos = from.rank() + rankStep;
     int filePos = from.file() + fileStep;
@@ -214,22 +205,22 @@ SquareSet SquareSet::path(Square from, Square to) {
     return path;
 }
 
-SquareSet castlingPath(Color color, MoveKind side) {
+constexpr SquareSet computeCastlingPath(Color color, MoveKind side) {
     SquareSet path;
     int rank = color == Color::WHITE ? 0 : kNumRanks - 1;
 
     if (side == MoveKind::QUEEN_CASTLE) {
         // Note the paths are reversed, so the start point is excluded and the endpoint included.
-        path |= SquareSet::path(Square(rank, Position::kKingCastledQueenSideFile),
+        path |= computePath(Square(rank, Position::kKingCastledQueenSideFile),
                                 Square(rank, Position::kKingFile));
-        path |= SquareSet::path(Square(rank, Position::kRookCastledQueenSideFile),
+        path |= computePath(Square(rank, Position::kRookCastledQueenSideFile),
                                 Square(rank, Position::kQueenSideRookFile));
     } else {
         assert(side == MoveKind::KING_CASTLE);
         // Note the paths are reversed, so the start point is excluded and the endpoint included.
-        path |= SquareSet::path(Square(rank, Position::kKingCastledKingSideFile),
+        path |= computePath(Square(rank, Position::kKingCastledKingSideFile),
                                 Square(rank, Position::kKingFile));
-        path |= SquareSet::path(Square(rank, Position::kRookCastledKingSideFile),
+        path |= computePath(Square(rank, Position::kRookCastledKingSideFile),
                                 Square(rank, Position::kKingSideRookFile));
     }
 
@@ -239,6 +230,147 @@ SquareSet castlingPath(Color color, MoveKind side) {
     return path;
 }
 
+constexpr SquareSet computeCastlingSafe(Color color, MoveKind side) {
+    int rank = color == Color::WHITE ? 0 : kNumRanks - 1;
+    int file = side == MoveKind::QUEEN_CASTLE ? Position::kKingCastledQueenSideFile
+                                              : Position::kKingCastledKingSideFile;
+
+    Square king(rank, Position::kKingFile);
+    return computePath(king, Square(rank, file)) | SquareSet(king);
+}
+
+struct MovesTable {
+    // precomputed possible moves for each piece type on each square
+    SquareSet moves[kNumPieces][kNumSquares];
+
+    // precomputed possible captures for each piece type on each square
+    SquareSet captures[kNumPieces][kNumSquares];
+
+    // precomputed paths from each square to each other square
+    SquareSet paths[kNumSquares][kNumSquares];  // from, to
+
+    // precomputed squares required to be clear for castling
+    SquareSet castlingClear[2][index(MoveKind::QUEEN_CASTLE) + 1];  // color, moveKind
+
+    // precomputed squares the king starts on or crosses, that may not be attacked for castling
+    SquareSet castlingSafe[2][index(MoveKind::QUEEN_CASTLE) + 1];  // color, moveKind
+
+    // precomputed from squares for en passant targets
+    SquareSet enPassantFrom[2][kNumFiles];  // color, file
+
+    // precomputed squares from which each piece type attacks each square, ignoring blockers
+    SquareSet attackers[kNumPieces][kNumSquares];  // piece, square attacked
+
+    // precomputed squares at a distance of one and two king moves, for king safety
+    SquareSet innerKingRing[kNumSquares];
+    SquareSet outerKingRing[kNumSquares];
+
+    // precomputed number of king moves between squares, for the rule of the square in pawn races
+    uint8_t kingDistance[kNumSquares][kNumSquares] = {};  // from, to
+
+    // precomputed squares in front of pawns, on the same file and including the adjacent files
+    SquareSet pawnFrontSpan[2][kNumSquares];   // color, square
+    SquareSet passedPawnMask[2][kNumSquares];  // color, square
+
+    constexpr MovesTable();
+};
+
+constexpr MovesTable::MovesTable() {
+    for (Square from = 0; from != kNumSquares; ++from) {
+        for (int piece = 0; piece != kNumPieces; ++piece) {
+            moves[piece][from.index()] = computeMoves(Piece(piece), from);
+            captures[piece][from.index()] = computeCaptures(Piece(piece), from);
+            for (Square to = 0; to != kNumSquares; ++to)
+                if (captures[piece][from.index()].contains(to))
+                    attackers[piece][to.index()].insert(from);
+        }
+    }
+    for (int from = 0; from < kNumSquares; ++from) {
+        for (int to = 0; to < kNumSquares; ++to) {
+            paths[from][to] = computePath(Square(from), Square(to));
+        }
+    }
+    for (int color = 0; color < 2; ++color) {
+        int fromRank = color == 0 ? kNumRanks - 4 : 3;  // skipping 3 ranks from either side
+        for (int fromFile = 0; fromFile < kNumFiles; ++fromFile) {
+            enPassantFrom[color][fromFile] = {SquareSet::valid(fromRank, fromFile - 1) |
+                                              SquareSet::valid(fromRank, fromFile + 1)};
+        }
+        castlingClear[color][index(MoveKind::QUEEN_CASTLE)] =
+            computeCastlingPath(Color(color), MoveKind::QUEEN_CASTLE);
+        castlingClear[color][index(MoveKind::KING_CASTLE)] =
+            computeCastlingPath(Color(color), MoveKind::KING_CASTLE);
+        for (auto side : {MoveKind::QUEEN_CASTLE, MoveKind::KING_CASTLE})
+            castlingSafe[color][index(side)] = computeCastlingSafe(Color(color), side);
+    }
+    for (Square square = 0; square != kNumSquares; ++square) {
+        int rank = square.rank(), file = square.file();
+        for (int dRank = -2; dRank <= 2; ++dRank) {
+            for (int dFile = -2; dFile <= 2; ++dFile) {
+                auto distance = std::max(absolute(dRank), absolute(dFile));
+                auto ring = SquareSet::valid(rank + dRank, file + dFile);
+                if (distance == 1) innerKingRing[square.index()] |= ring;
+                if (distance == 2) outerKingRing[square.index()] |= ring;
+            }
+        }
+        for (Square to = 0; to != kNumSquares; ++to)
+            kingDistance[square.index()][to.index()] =
+                std::max(absolute(to.rank() - rank), absolute(to.file() - file));
+        for (int color = 0; color < 2; ++color) {
+            int direction = Color(color) == Color::WHITE ? 1 : -1;
+            for (int front = rank + direction; front >= 0 && front < kNumRanks;
+                 front += direction) {
+                pawnFrontSpan[color][square.index()] |= SquareSet::valid(front, file);
+                passedPawnMask[color][square.index()] |= SquareSet::valid(front, file - 1) |
+                    SquareSet::valid(front, file) | SquareSet::valid(front, file + 1);
+            }
+        }
+    }
+}
+
+// Computed at compile time, so there is no startup cost and the tables are in read-only memory
+constexpr MovesTable movesTable;
+
+SquareSet possibleMoves(Piece piece, Square from) {
+    return movesTable.moves[index(piece)][from.index()];
+}
+
+SquareSet possibleCaptures(Piece piece, Square from) {
+    return movesTable.captures[index(piece)][from.index()];
+}
+
+SquareSet SquareSet::path(Square from, Square to) {
+    return movesTable.paths[from.index()][to.index()];
+}
+
+SquareSet castlingPath(Color color, MoveKind side) {
+    return movesTable.castlingClear[int(color)][index(side)];
+}
+
+SquareSet castlingSafe(Color color, MoveKind side) {
+    return movesTable.castlingSafe[int(color)][index(side)];
+}
+
+SquareSet innerKingRing(Square square) {
+    return movesTable.innerKingRing[square.index()];
+}
+
+SquareSet outerKingRing(Square square) {
+    return movesTable.outerKingRing[square.index()];
+}
+
+int kingDistance(Square from, Square to) {
+    return movesTable.kingDistance[from.index()][to.index()];
+}
+
+SquareSet pawnFrontSpan(Color color, Square square) {
+    return movesTable.pawnFrontSpan[int(color)][square.index()];
+}
+
+SquareSet passedPawnMask(Color color, Square square) {
+    return movesTable.passedPawnMask[int(color)][square.index()];
+}
+
 bool clearPath(SquareSet occupancy, Square from, Square to) {
     auto path = movesTable.paths[from.index()][to.index()];
     return (occupancy & path).empty();
@@ -275,32 +407,100 @@ void findMoves(const Board& board, Color activeColor, const F& fun) {
     }
 }
 
+namespace {
+/**
+ * Returns the squares that need to be empty for castling in chess960: those the king and rook
+ * cross or end up on, other than their own.
+ */
+SquareSet chess960CastlingPath(const CastlingSquares& squares) {
+    auto [king, rook, kingTo, rookTo] = squares;
+    auto path = SquareSet::path(king, kingTo) | SquareSet::path(rook, rookTo) | SquareSet(kingTo) |
+        SquareSet(rookTo);
+    path.erase(king);
+    path.erase(rook);
+    return path;
+}
+
+/**
+ * Returns true if the king would castle out of, through or into check in chess960. The king and
+ * rook are removed first, as the rook may shield the destination of the king.
+ */
+bool chess960CastlingAttacked(const Board& board, const CastlingSquares& squares) {
+    auto [king, rook, kingTo, rookTo] = squares;
+    auto opponent = !color(board[king]);
+    auto without = board;
+    without.set(king, Piece::NONE);
+    without.set(rook, Piece::NONE);
+    return isAttacked(
+        without, SquareSet::path(king, kingTo) | SquareSet(king) | SquareSet(kingTo), opponent);
+}
+
+/**
+ * Returns the castling right of the given color on the side of the king the target is on.
+ */
+CastlingMask castlingRight(Color color, Square king, Square to) {
+    bool kingSide = to.file() > king.file();
+    if (color == Color::WHITE)
+        return kingSide ? CastlingMask::WHITE_KINGSIDE : CastlingMask::WHITE_QUEENSIDE;
+    return kingSide ? CastlingMask::BLACK_KINGSIDE : CastlingMask::BLACK_QUEENSIDE;
+}
+}  // namespace
+
 template <typename F>
-void findCastles(const Board& board, Color activeColor, CastlingMask mask, const F& fun) {
+void findCastles(const Position& position, const F& fun) {
+    auto& board = position.board;
+    auto activeColor = position.activeColor;
+    auto mask = position.castlingAvailability;
     auto occupied = SquareSet::occupancy(board);
-    if (activeColor == Color::WHITE) {
-        if ((mask & CastlingMask::WHITE_KINGSIDE) != CastlingMask::NONE) {
-            auto path = movesTable.castlingClear[0][index(MoveKind::KING_CASTLE)];
-            if ((occupied & path).empty())
-                fun(Piece::WHITE_KING, Position::whiteKing, Position::whiteKingSideRook);
-        }
-        if ((mask & CastlingMask::WHITE_QUEENSIDE) != CastlingMask::NONE) {
-            auto path = movesTable.castlingClear[0][index(MoveKind::QUEEN_CASTLE)];
-            if ((occupied & path).empty())
-                fun(Piece::WHITE_KING, Position::whiteKing, Position::whiteQueenSideRook);
+    auto king = addColor(PieceType::KING, activeColor);
+    auto rook = addColor(PieceType::ROOK, activeColor);
+
+    // In chess960, the king moves onto the rook, and the destination is checked here as well
+    if (position.variant == Variant::CHESS960) {
+        for (auto right : castlingRights(mask)) {
+            auto squares = castlingSquares(position, right);
+            if (!squares || color(board[squares->king]) != activeColor) continue;
+            if (!(occupied & chess960CastlingPath(*squares)).empty()) continue;
+            if (chess960CastlingAttacked(board, *squares)) continue;
+            fun(king, squares->king, squares->rook);
         }
+        return;
+    }
+
+    // The king may not castle out of or through check. The caller checks the destination square.
+    auto tryCastle = [&](CastlingMask side, MoveKind kind, Square from, Square to, Square rookSq) {
+        if (!contains(mask, side)) return;
+        if (board[from] != king || board[rookSq] != rook) return;
+        auto path = movesTable.castlingClear[int(activeColor)][index(kind)];
+        if (!(occupied & path).empty()) return;
+        if (isAttacked(board, movesTable.castlingSafe[int(activeColor)][index(kind)], !activeColor))
+            return;
+        fun(king, from, to);
+    };
+
+    if (activeColor == Color::WHITE) {
+        tryCastle(CastlingMask::WHITE_KINGSIDE,
+                  MoveKind::KING_CASTLE,
+                  Position::whiteKing,
+                  Position::whiteKingCastledKingSide,
+                  Position::whiteKingSideRook);
+        tryCastle(CastlingMask::WHITE_QUEENSIDE,
+                  MoveKind::QUEEN_CASTLE,
+                  Position::whiteKing,
+                  Position::whiteKingCastledQueenSide,
+                  Position::whiteQueenSideRook);
     } else {
         assert(activeColor == Color::BLACK);
-        if ((mask & CastlingMask::BLACK_KINGSIDE) != CastlingMask::NONE) {
-            auto path = movesTable.castlingClear[1][index(MoveKind::KING_CASTLE)];
-            if ((occupied & path).empty())
-                fun(Piece::BLACK_KING, Position::blackKing, Position::blackKingSideRook);
-        }
-        if ((mask & CastlingMask::BLACK_QUEENSIDE) != CastlingMask::NONE) {
-            auto path = movesTable.castlingClear[1][index(MoveKind::QUEEN_CASTLE)];
-            if ((occupied & path).empty())
-                fun(Piece::BLACK_KING, Position::blackKing, Position::blackQueenSideRook);
-        }
+        tryCastle(CastlingMask::BLACK_KINGSIDE,
+                  MoveKind::KING_CASTLE,
+                  Position::blackKing,
+                  Position::blackKingCastledKingSide,
+                  Position::blackKingSideRook);
+        tryCastle(CastlingMask::BLACK_QUEENSIDE,
+                  MoveKind::QUEEN_CASTLE,
+                  Position::blackKing,
+                  Position::blackKingCastledQueenSide,
+                  Position::blackQueenSideRook);
     }
 }
 
@@ -323,24 +523,27 @@ void findCaptures(const Board& board, Color activeColor, const F& fun) {
 }
 
 template <typename F>
-void findEnPassant(const Board& board, Color activeColor, Square enPassantTarget, const F& fun) {
-    if (enPassantTarget != Position::noEnPassantTarget) {
+void findEnPassant(const Board& board,
+                   Color activeColor,
+                   std::optional<Square> enPassantTarget,
+                   const F& fun) {
+    if (auto target = enPassantTarget) {
         if (false)
-            std::cout << "En passant target: " << std::string(enPassantTarget) << " (rank "
-                      << enPassantTarget.rank() << ")" << std::endl;
-        assert((activeColor == Color::WHITE && enPassantTarget.rank() == 5) ||
-               (activeColor == Color::BLACK && enPassantTarget.rank() == 2));
+            std::cout << "En passant target: " << std::string(*target) << " (rank "
+                      << target->rank() << ")" << std::endl;
+        assert((activeColor == Color::WHITE && target->rank() == 5) ||
+               (activeColor == Color::BLACK && target->rank() == 2));
         // For a given en passant target, there are two potential from squares. If either or
         // both have a pawn of the active color, then capture is possible.
         auto pawn = activeColor == Color::WHITE ? Piece::WHITE_PAWN : Piece::BLACK_PAWN;
-        for (auto from : movesTable.enPassantFrom[int(activeColor)][enPassantTarget.file()]) {
+        for (auto from : movesTable.enPassantFrom[int(activeColor)][target->file()]) {
             // std::cout << "trying from square " << std::string(from) << std::endl;
             if (board[from] == pawn) {
                 if (false)
                     std::cout << "En passant from " << std::string(from) << " to "
-                              << std::string(enPassantTarget) << " (" << to_string(activeColor)
+                              << std::string(*target) << " (" << to_string(activeColor)
                               << ")" << std::endl;
-                fun(pawn, from, enPassantTarget);
+                fun(pawn, from, *target);
             }
         }
     }
@@ -361,25 +564,67 @@ void addAvailableCaptures(MoveVector& captures, const Board& board, Color active
 void addAvailableEnPassant(MoveVector& captures,
                            const Board& board,
                            Color activeColor,
-                           Square enPassantTarget) {
+                           std::optional<Square> enPassantTarget) {
     findEnPassant(
         board, activeColor, enPassantTarget, [&captures](Piece piece, Square from, Square to) {
             addMove(captures, piece, from, to, MoveKind::EN_PASSANT);
         });
 }
 
+bool isCastling(const Board& board, Move move) {
+    auto piece = board[move.from];
+    return type(piece) == PieceType::KING &&
+        (abs(move.from.file() - move.to.file()) == 2 ||
+         board[move.to] == addColor(PieceType::ROOK, color(piece)));
+}
+
 void applyMove(Board& board, Move move) {
-    auto& piece = board[move.from];
-    auto& target = board[move.to];
-    if (type(piece) == PieceType::PAWN && target == Piece::NONE &&
+    auto piece = std::as_const(board)[move.from];
+    if (type(piece) == PieceType::PAWN && board[move.to] == Piece::NONE &&
         move.from.file() != move.to.file()) {
         // En passant capture
-        board[Square{move.from.rank(), move.to.file()}] = Piece::NONE;
+        board.set(Square{move.from.rank(), move.to.file()}, Piece::NONE);
+    }
+    if (isCastling(board, move)) {
+        // Castling, so also move the rook, which in chess960 is on the target square. Empty both
+        // starting squares first, as the king or rook may end up on the other's.
+        bool kingSide = move.to.file() > move.from.file();
+        auto rank = move.from.rank();
+        auto rook = std::as_const(board)[move.to] != Piece::NONE
+            ? move.to
+            : Square{rank, kingSide ? Position::kKingSideRookFile : Position::kQueenSideRookFile};
+        auto rookPiece = std::as_const(board)[rook];
+        board.set(rook, Piece::NONE);
+        board.set(move.from, Piece::NONE);
+        board.set(Square{rank, kingSide ? Position::kRookCastledKingSideFile
+                                        : Position::kRookCastledQueenSideFile},
+                  rookPiece);
+        board.set(Square{rank, kingSide ? Position::kKingCastledKingSideFile
+                                        : Position::kKingCastledQueenSideFile},
+                  piece);
+        return;
     }
 
     // Update the target, including promotion if applicable
-    target = move.isPromotion() ? addColor(promotionType(move.kind), color(piece)) : piece;
-    piece = Piece::NONE;  // Empty the source square
+    board.set(move.to,
+              move.isPromotion() ? addColor(promotionType(move.kind), color(piece)) : piece);
+    board.set(move.from, Piece::NONE);  // Empty the source square
+}
+
+Move inferMove(const Board& board, Square from, Square to, std::optional<PieceType> promotion) {
+    auto piece = type(board[from]);
+    bool capture = board[to] != Piece::NONE;
+    bool sameFile = from.file() == to.file();
+    auto kind = capture ? MoveKind::CAPTURE : MoveKind::QUIET_MOVE;
+    if (piece == PieceType::PAWN && !capture && !sameFile) kind = MoveKind::EN_PASSANT;
+    if (piece == PieceType::PAWN && abs(from.rank() - to.rank()) == 2)
+        kind = MoveKind::DOUBLE_PAWN_PUSH;
+    if (isCastling(board, {from, to, kind}))
+        kind = to.file() > from.file() ? MoveKind::KING_CASTLE : MoveKind::QUEEN_CASTLE;
+    if (promotion)
+        kind = MoveKind::PROMOTION_MASK | MoveKind(index(*promotion) - index(PieceType::KNIGHT)) |
+            (capture ? MoveKind::CAPTURE : MoveKind::QUIET_MOVE);
+    return {from, to, kind};
 }
 
 CastlingMask castlingMask(Square from, Square to) {
@@ -399,38 +644,72 @@ CastlingMask castlingMask(Square from, Square to) {
     return CM::NONE;
 }
 
+/**
+ * Returns the castling rights cancelled by the move in chess960, where the squares of the kings and
+ * castling rooks are those of the position before the move.
+ */
+CastlingMask chess960CastlingMask(const Position& position, Move move) {
+    auto cancelled = CastlingMask::NONE;
+    for (auto right : kCastlingRights) {
+        auto squares = castlingSquares(position, right);
+        if (squares && (move.from == squares->king || move.from == squares->rook ||
+                        move.to == squares->rook))
+            cancelled |= right;
+    }
+    return cancelled;
+}
+
+/**
+ * Removes the piece at the given square and all pieces other than pawns next to it, along with
+ * the castling availability of any rooks and kings removed.
+ */
+void explode(Position& position, Square square) {
+    for (auto exploded : kingMoves(square) | SquareSet(square)) {
+        Piece piece = position.board[exploded];
+        if (exploded != square && piece != Piece::NONE && type(piece) == PieceType::PAWN) continue;
+        position.board[exploded] = Piece::NONE;
+        position.castlingAvailability &= ~castlingMask(exploded, exploded);
+    }
+}
+
 Position applyMove(Position position, Move move) {
     // Check if the move is a capture or pawn move before applying it to the board
-    bool capture = position.board[move.to] != Piece::NONE;
-    auto piece = position.board[move.from];
+    Piece piece = position.board[move.from];
     bool pawnMove = type(piece) == PieceType::PAWN;
+    bool capture = !isCastling(position.board, move) &&
+        (position.board[move.to] != Piece::NONE ||
+         (pawnMove && move.from.file() != move.to.file()));  // En passant
+    auto cancelled = position.variant == Variant::CHESS960
+        ? chess960CastlingMask(position, move)
+        : castlingMask(move.from, move.to);
 
     // Apply the move to the board
     applyMove(position.board, move);
+    if (capture && position.variant == Variant::ATOMIC) explode(position, move.to);
 
     // Update enPassantTarget
     // Set the en passant target if a pawn moves two squares forward, otherwise reset it.
-    position.enPassantTarget = Position::noEnPassantTarget;
+    position.enPassantTarget.reset();
     if (pawnMove && abs(move.from.rank() - move.to.rank()) == 2) {
-        position.enPassantTarget = {(move.from.rank() + move.to.rank()) / 2, move.from.file()};
+        position.enPassantTarget = {{(move.from.rank() + move.to.rank()) / 2, move.from.file()}};
         if (false)
-            std::cout << "Setting en passant target: " << std::string(position.enPassantTarget)
+            std::cout << "Setting en passant target: " << std::string(*position.enPassantTarget)
                       << std::endl;
     }
     // Update castlingAvailability
-    position.castlingAvailability &= ~castlingMask(move.from, move.to);
+    position.castlingAvailability &= ~cancelled;
 
     // Update halfMoveClock
-    // Reset on pawn advance or capture, else increment
-    ++position.halfmoveClock;
+    // Reset on pawn advance or capture, else increment, saturating instead of overflowing
+    if (position.halfmoveClock < UINT8_MAX) ++position.halfmoveClock;
     if (pawnMove || capture) position.halfmoveClock = 0;
 
     // Update fullMoveNumber
     // Increment after black's move
-    if (position.activeColor == Color::BLACK) ++position.fullmoveNumber;
+    if (position.activeColor == Color::BLACK && position.fullmoveNumber < UINT16_MAX)
+        ++position.fullmoveNumber;
 
     // Update activeColor
-    auto oldColor = position.activeColor;
     position.activeColor = !position.activeColor;
 
     return position;
@@ -440,16 +719,24 @@ bool isAttacked(const Board& board, Square square) {
     auto piece = board[square];
     if (piece == Piece::NONE) return false;  // The square is empty, so it is not attacked.
 
-    Color opponentColor = !color(piece);
+    return isAttacked(board, square, !color(piece));
+}
+
+bool isAttacked(const Board& board, Square square, Color opponentColor) {
     auto occupancy = SquareSet::occupancy(board);
-    for (Square from : occupancy) {
+
+    // Only pieces on squares from which a queen or knight could attack need to be considered
+    auto& attackers = movesTable.attackers;
+    auto queenOrKnight = attackers[index(Piece::WHITE_QUEEN)][square.index()] |
+        attackers[index(Piece::WHITE_KNIGHT)][square.index()];
+    for (Square from : occupancy & queenOrKnight) {
         auto piece = board[from];
 
         // Check if the piece is of the opponent's color
         if (color(piece) != opponentColor) continue;
 
-        auto possibleCaptureSquares = movesTable.captures[index(piece)][from.index()];
-        if (possibleCaptureSquares.contains(square) && clearPath(occupancy, from, square))
+        if (attackers[index(piece)][square.index()].contains(from) &&
+            clearPath(occupancy, from, square))
             return true;
     }
     return false;
@@ -462,6 +749,272 @@ bool isAttacked(const Board& board, SquareSet squares) {
     return false;
 }
 
+bool isAttacked(const Board& board, SquareSet squares, Color opponentColor) {
+    for (auto square : squares) {
+        if (isAttacked(board, square, opponentColor)) return true;
+    }
+    return false;
+}
+
+SquareSet attackedSquares(const Board& board, Color color) {
+    auto occupancy = SquareSet::occupancy(board);
+    SquareSet attacked;
+    for (auto from : occupancy) {
+        auto piece = board[from];
+        if (!isColor(piece, color)) continue;
+        for (auto to : possibleCaptures(piece, from))
+            if (clearPath(occupancy, from, to)) attacked.insert(to);
+    }
+    return attacked;
+}
+
+SquareSet checkSquares(const Board& board, Color color, PieceType type) {
+    auto kings = SquareSet::find(board, addColor(PieceType::KING, !color));
+    if (type == PieceType::KING || kings.size() != 1) return {};
+    auto king = *kings.begin();
+
+    // Attacks are symmetric, except for pawns, which attack the king from where an opponent pawn
+    // on the king's square would capture
+    auto reverse = addColor(type, type == PieceType::PAWN ? !color : color);
+    auto occupancy = SquareSet::occupancy(board);
+    SquareSet squares;
+    for (auto from : possibleCaptures(reverse, king))
+        if (clearPath(occupancy, king, from)) squares.insert(from);
+    return squares;
+}
+
+AttackMap attackMap(const Board& board) {
+    auto occupancy = SquareSet::occupancy(board);
+    AttackMap map;
+    for (auto from : occupancy) {
+        auto piece = board[from];
+        for (auto to : possibleCaptures(piece, from)) {
+            if (!clearPath(occupancy, from, to)) continue;
+            map.attacks[from.index()].insert(to);
+            if (board[to] == Piece::NONE) continue;
+            auto& arrows = color(board[to]) == color(piece) ? map.defenses : map.threats;
+            arrows.push_back({from, to});
+        }
+    }
+    return map;
+}
+
+bool isValidEnPassantTarget(const Position& position) {
+    if (!position.enPassantTarget) return true;
+    auto target = *position.enPassantTarget;
+
+    bool white = position.activeColor == Color::WHITE;
+    if (target.rank() != (white ? 5 : 2)) return false;
+    Square pawn(white ? 4 : 3, target.file());
+    Square origin(white ? 6 : 1, target.file());
+    return position.board[pawn] == addColor(PieceType::PAWN, !position.activeColor) &&
+        position.board[target] == Piece::NONE && position.board[origin] == Piece::NONE;
+}
+
+bool normalizeEnPassant(Position& position, bool strict) {
+    if (!position.enPassantTarget) return true;
+    auto target = *position.enPassantTarget;
+    if (!isValidEnPassantTarget(position)) {
+        if (strict) return false;
+        position.enPassantTarget.reset();
+        return true;
+    }
+
+    for (auto& [move, newPosition] : allLegalMoves(position))
+        if (move.to == target && type(position.board[move.from]) == PieceType::PAWN) return true;
+    position.enPassantTarget.reset();
+    return true;
+}
+
+CastlingMask possibleCastling(const Board& board, Variant variant) {
+    auto rights = CastlingMask::NONE;
+    if (variant == Variant::CHESS960) {
+        for (auto right : kCastlingRights)
+            if (castlingSquares(board, right)) rights |= right;
+        return rights;
+    }
+    auto white = board[Position::whiteKing] == Piece::WHITE_KING;
+    auto black = board[Position::blackKing] == Piece::BLACK_KING;
+    if (white && board[Position::whiteKingSideRook] == Piece::WHITE_ROOK)
+        rights |= CastlingMask::WHITE_KINGSIDE;
+    if (white && board[Position::whiteQueenSideRook] == Piece::WHITE_ROOK)
+        rights |= CastlingMask::WHITE_QUEENSIDE;
+    if (black && board[Position::blackKingSideRook] == Piece::BLACK_ROOK)
+        rights |= CastlingMask::BLACK_KINGSIDE;
+    if (black && board[Position::blackQueenSideRook] == Piece::BLACK_ROOK)
+        rights |= CastlingMask::BLACK_QUEENSIDE;
+    return rights;
+}
+
+namespace {
+/**
+ * Returns the castling rights of the position that still have their king and rook in place. In
+ * chess960, that is the rook on the start file kept for the right, see castlingSquares.
+ */
+CastlingMask availableCastling(const Position& position) {
+    if (position.variant != Variant::CHESS960) return possibleCastling(position.board);
+    auto rights = CastlingMask::NONE;
+    for (auto right : kCastlingRights)
+        if (castlingSquares(position, right)) rights |= right;
+    return rights;
+}
+}  // namespace
+
+void putPiece(Position& position, Square square, Piece piece) {
+    position.board[square] = piece;
+    position.castlingAvailability &= availableCastling(position);
+    if (!isValidEnPassantTarget(position)) position.enPassantTarget.reset();
+}
+
+void removePiece(Position& position, Square square) {
+    putPiece(position, square, Piece::NONE);
+}
+
+void setSideToMove(Position& position, Color color) {
+    if (color != position.activeColor) position.enPassantTarget.reset();
+    position.activeColor = color;
+}
+
+bool setCastling(Position& position, CastlingMask rights) {
+    if (!contains(possibleCastling(position.board, position.variant), rights)) return false;
+
+    // New rights are for the outermost rook, as for "KQkq" in X-FEN
+    for (auto right : castlingRights(rights & ~position.castlingAvailability))
+        position.castlingRookFiles[castlingIndex(right)] =
+            castlingSquares(position.board, right)->rook.file();
+    position.castlingAvailability = rights;
+    return true;
+}
+
+std::string to_string(IllegalPositionReason reason) {
+    switch (reason) {
+    case IllegalPositionReason::NONE: return "legal position";
+    case IllegalPositionReason::MISSING_KING: return "missing king";
+    case IllegalPositionReason::TOO_MANY_KINGS: return "too many kings";
+    case IllegalPositionReason::PAWN_ON_BACK_RANK: return "pawn on first or last rank";
+    case IllegalPositionReason::OPPONENT_IN_CHECK: return "side not to move is in check";
+    case IllegalPositionReason::INVALID_CASTLING: return "castling without king or rook";
+    case IllegalPositionReason::INVALID_EN_PASSANT: return "invalid en passant target";
+    }
+    return "unknown reason";
+}
+
+IllegalPositionReason validatePosition(const Position& position) {
+    using R = IllegalPositionReason;
+    auto& board = position.board;
+    for (auto color : {Color::WHITE, Color::BLACK}) {
+        auto kings = SquareSet::find(board, addColor(PieceType::KING, color)).size();
+        if (kings == 0) return R::MISSING_KING;
+        if (kings > 1) return R::TOO_MANY_KINGS;
+    }
+    auto pawns =
+        SquareSet::find(board, Piece::WHITE_PAWN) | SquareSet::find(board, Piece::BLACK_PAWN);
+    if (!(pawns & (SquareSet::rank(0) | SquareSet::rank(kNumRanks - 1))).empty())
+        return R::PAWN_ON_BACK_RANK;
+    auto opponentKing = SquareSet::find(board, addColor(PieceType::KING, !position.activeColor));
+    if (isAttacked(board, opponentKing)) return R::OPPONENT_IN_CHECK;
+    if (!contains(availableCastling(position), position.castlingAvailability))
+        return R::INVALID_CASTLING;
+    if (!isValidEnPassantTarget(position)) return R::INVALID_EN_PASSANT;
+    return R::NONE;
+}
+
+Position mirror(const Position& position) {
+    auto mirrored = position;
+    for (Square square = 0; square != kNumSquares; ++square) {
+        auto piece = position.board[square];
+        auto flipped = Square(kNumRanks - 1 - square.rank(), square.file());
+        mirrored.board[flipped] =
+            piece == Piece::NONE ? Piece::NONE : addColor(type(piece), !color(piece));
+    }
+    mirrored.activeColor = !position.activeColor;
+    auto castling = uint8_t(position.castlingAvailability);
+    mirrored.castlingAvailability = CastlingMask((castling & 3) << 2 | castling >> 2);
+    auto& files = position.castlingRookFiles;  // Swap the white and black entries with the rights
+    mirrored.castlingRookFiles = {files[2], files[3], files[0], files[1]};
+    if (auto target = position.enPassantTarget)
+        mirrored.enPassantTarget = Square(kNumRanks - 1 - target->rank(), target->file());
+    return mirrored;
+}
+
+Position canonical(Position position, CanonicalOptions options) {
+    normalizeEnPassant(position);
+    if (options.flipColors && position.activeColor == Color::BLACK) position = mirror(position);
+    if (options.ignoreClocks) {
+        position.halfmoveClock = 0;
+        position.fullmoveNumber = 1;
+    }
+    return position;
+}
+
+/**
+ * Returns true if the king of the given color is in check in atomic chess, or exploded. Kings next
+ * to each other are safe, as capturing one would explode both.
+ */
+bool isInAtomicCheck(const Board& board, Color color) {
+    auto king = SquareSet::find(board, addColor(PieceType::KING, color));
+    auto opponentKing = SquareSet::find(board, addColor(PieceType::KING, !color));
+    if (king.empty()) return true;
+    if (opponentKing.empty()) return false;  // The game is over, as the opponent's king exploded
+    for (auto square : king)
+        if (!(kingMoves(square) & opponentKing).empty()) return false;
+    return isAttacked(board, king, !color);
+}
+
+bool isInCheck(const Position& position) {
+    if (position.variant == Variant::ATOMIC)
+        return isInAtomicCheck(position.board, position.activeColor);
+    auto king = SquareSet::find(position.board, addColor(PieceType::KING, position.activeColor));
+    return isAttacked(position.board, king);
+}
+
+/**
+ * Describes the moves of pieces other than the king that don't leave the king in check, so their
+ * legality is known without applying them. When in check, a move must capture the checking piece or
+ * block its path, which is impossible in double check. A pinned piece, the only piece between the
+ * king and a sliding piece of the opponent, may only move along the line between them.
+ */
+struct CheckMasks {
+    Square king = 0;
+    SquareSet blocks;  // Squares that capture the checking piece or block the check, if any
+    SquareSet pinned;
+
+    bool allows(Square from, Square to) const {
+        if (!blocks.contains(to)) return false;
+        if (!pinned.contains(from)) return true;
+        return SquareSet::path(king, to).contains(from) || SquareSet::path(king, from).contains(to);
+    }
+};
+
+CheckMasks checkMasks(const Board& board, Square king) {
+    auto occupancy = SquareSet::occupancy(board);
+    auto us = color(board[king]);
+    CheckMasks masks;
+    masks.king = king;
+    SquareSet checkers;
+
+    // As in isAttacked, only pieces where a queen or knight would attack the king need a look
+    auto& attackers = movesTable.attackers;
+    auto queenOrKnight = attackers[index(Piece::WHITE_QUEEN)][king.index()] |
+        attackers[index(Piece::WHITE_KNIGHT)][king.index()];
+    for (Square from : occupancy & queenOrKnight) {
+        auto piece = board[from];
+        if (color(piece) == us || !attackers[index(piece)][king.index()].contains(from))
+            continue;
+        auto path = SquareSet::path(from, king);
+        auto blockers = path & occupancy;
+        if (blockers.empty()) {
+            checkers.insert(from);
+            masks.blocks = path | SquareSet(from);
+        } else if (blockers.size() == 1 && isColor(board[*blockers.begin()], us)) {
+            masks.pinned |= blockers;
+        }
+    }
+    if (checkers.empty()) masks.blocks = !SquareSet();
+    if (checkers.size() > 1) masks.blocks = {};
+    return masks;
+}
+
 /**
  * Computes all legal moves from a given chess position, mapping each move to the resulting
  * chess position after the move is applied. This function checks for moves that do not leave
@@ -476,24 +1029,56 @@ ComputedMoveVector allLegalMoves(const Position& position) {
 
     auto ourKing = addColor(PieceType::KING, position.activeColor);
     auto oldKing = SquareSet::find(position.board, ourKing);
+    bool atomic = position.variant == Variant::ATOMIC;
+    if (atomic && oldKing.empty()) return legalMoves;  // The king exploded, so the game is over
+
+    // With a single king, and except in atomic chess, legality is known before applying moves: for
+    // king moves from the squares attacked by the opponent with the king removed, computed on the
+    // first king move, and for others from the check masks. En passant captures remove two pieces
+    // from the rank of the king, so they are checked after applying them, as are all other moves.
+    bool useMasks = !atomic && oldKing.size() == 1;
+    auto masks = useMasks ? checkMasks(position.board, *oldKing.begin()) : CheckMasks();
+    std::optional<SquareSet> kingDanger;
 
     // Iterate over all moves and captures
     auto addIfLegal = [&](Piece piece, Square from, Square to) {
-        // If we move the king, reflect that in the king squares
-        auto newKing = oldKing;
-        if (piece == ourKing) {
-            newKing.erase(from);
-            newKing.insert(to);
-        }
-
         auto kind = position.board[to] == Piece::NONE ? MoveKind::QUIET_MOVE : MoveKind::CAPTURE;
+        bool castling = piece == ourKing && isCastling(position.board, {from, to, kind});
+        if (castling)
+            kind = to.file() > from.file() ? MoveKind::KING_CASTLE : MoveKind::QUEEN_CASTLE;
         Move move = {from, to, kind};  // For now assume no promotion applies
+        bool enPassant = type(piece) == PieceType::PAWN && kind == MoveKind::QUIET_MOVE &&
+            from.file() != to.file();
+        bool checkAfter = !useMasks || enPassant;
+
+        if (checkAfter) {
+            // Checked below
+        } else if (castling && position.variant == Variant::CHESS960) {
+            // Checked by findCastles, as the king doesn't end up on the target square
+        } else if (piece == ourKing) {
+            if (!kingDanger) {
+                auto board = position.board;
+                board.set(from, Piece::NONE);
+                kingDanger = attackedSquares(board, !position.activeColor);
+            }
+            if (kingDanger->contains(to)) return;
+        } else if (!masks.allows(from, to)) {
+            return;
+        }
 
         // Make a copy of the position to apply the move
         auto newPosition = applyMove(position, move);
 
-        // Check if the move would result in our king being in check.
-        if (isAttacked(newPosition.board, newKing)) return;
+        // Check if the move would result in our king being in check. In atomic chess, kings
+        // can't capture, and exploding the opponent's king is legal even if it leaves ours in check
+        if (atomic) {
+            if (piece == ourKing && kind == MoveKind::CAPTURE) return;
+            if (isInAtomicCheck(newPosition.board, position.activeColor)) return;
+        } else if (checkAfter) {
+            // If we move the king, reflect that in the king squares
+            auto newKing = piece == ourKing ? SquareSet::find(newPosition.board, ourKing) : oldKing;
+            if (isAttacked(newPosition.board, newKing)) return;
+        }
 
         // If promoted, add all possible promotions, legality is not affected
         if (type(piece) == PieceType::PAWN && (to.rank() == 0 || to.rank() == kNumRanks - 1)) {
@@ -501,7 +1086,9 @@ ComputedMoveVector allLegalMoves(const Position& position) {
                                    MoveKind::BISHOP_PROMOTION,
                                    MoveKind::ROOK_PROMOTION,
                                    MoveKind::QUEEN_PROMOTION}) {
-                newPosition.board[to] = addColor(promotionType(promotion), position.activeColor);
+                // An exploded pawn doesn't promote
+                auto promoted = addColor(promotionType(promotion), position.activeColor);
+                if (newPosition.board[to] != Piece::NONE) newPosition.board[to] = promoted;
                 legalMoves.emplace_back(Move{from, to, promotion}, newPosition);
             }
         } else {
@@ -512,6 +1099,125 @@ ComputedMoveVector allLegalMoves(const Position& position) {
     findCaptures(position.board, position.activeColor, addIfLegal);
     findEnPassant(position.board, position.activeColor, position.enPassantTarget, addIfLegal);
     findMoves(position.board, position.activeColor, addIfLegal);
+    findCastles(position, addIfLegal);
 
     return legalMoves;
 }
+
+namespace {
+template <typename F>
+ComputedMoveVector filterLegalMoves(const Position& position, const F& keep) {
+    auto moves = allLegalMoves(position);
+    moves.erase(std::remove_if(moves.begin(),
+                               moves.end(),
+                               [&](const ComputedMove& move) { return !keep(move.first); }),
+                moves.end());
+    return moves;
+}
+}  // namespace
+
+ComputedMoveVector legalMovesFrom(const Position& position, Square from) {
+    return filterLegalMoves(position, [from](Move move) { return move.from == from; });
+}
+
+ComputedMoveVector legalMovesTo(const Position& position, Square to) {
+    return filterLegalMoves(position, [to](Move move) { return move.to == to; });
+}
+
+ComputedMoveVector legalCaptures(const Position& position, PieceType captured) {
+    auto target = addColor(captured, !position.activeColor);
+    auto& board = position.board;
+    return filterLegalMoves(position, [&](Move move) {
+        if (type(board[move.from]) == PieceType::PAWN && board[move.to] == Piece::NONE &&
+            move.from.file() != move.to.file())
+            return board[Square(move.from.rank(), move.to.file())] == target;  // En passant
+        return board[move.to] == target;
+    });
+}
+
+SquareSet legalTargets(const Position& position, Square from) {
+    SquareSet targets;
+    for (auto& [move, newPosition] : legalMovesFrom(position, from)) targets.insert(move.to);
+    return targets;
+}
+
+std::string to_string(IllegalMoveReason reason) {
+    switch (reason) {
+    case IllegalMoveReason::NONE: return "legal move";
+    case IllegalMoveReason::NO_PIECE: return "no piece to move";
+    case IllegalMoveReason::NOT_YOUR_PIECE: return "not your piece";
+    case IllegalMoveReason::OWN_PIECE_ON_TARGET: return "can't capture your own piece";
+    case IllegalMoveReason::UNREACHABLE: return "piece doesn't move that way";
+    case IllegalMoveReason::PATH_BLOCKED: return "path is blocked";
+    case IllegalMoveReason::NO_CASTLING_RIGHTS: return "no castling rights";
+    case IllegalMoveReason::CASTLING_THROUGH_CHECK: return "can't castle out of or through check";
+    case IllegalMoveReason::INVALID_PROMOTION: return "invalid promotion";
+    case IllegalMoveReason::KING_CAPTURE: return "king can't capture";
+    case IllegalMoveReason::LEAVES_KING_IN_CHECK: return "king would be in check";
+    }
+    return "unknown reason";
+}
+
+IllegalMoveReason checkMove(const Position& position, Move move) {
+    using R = IllegalMoveReason;
+    auto [from, to, kind] = move;
+    for (auto& [legal, newPosition] : allLegalMoves(position))
+        if (legal.from == from && legal.to == to && legal.isPromotion() == move.isPromotion() &&
+            (!move.isPromotion() || promotionType(legal.kind) == promotionType(kind)))
+            return R::NONE;
+
+    auto& board = position.board;
+    auto active = position.activeColor;
+    auto piece = board[from];
+    auto target = board[to];
+    if (piece == Piece::NONE) return R::NO_PIECE;
+    if (color(piece) != active) return R::NOT_YOUR_PIECE;
+
+    // In chess960, castling moves the king onto its own rook
+    bool chess960 = position.variant == Variant::CHESS960;
+    int backRank = active == Color::WHITE ? 0 : kNumRanks - 1;
+    if (chess960 && type(piece) == PieceType::KING && target == addColor(PieceType::ROOK, active) &&
+        from.rank() == backRank && to.rank() == backRank) {
+        auto right = castlingRight(active, from, to);
+        auto squares = castlingSquares(position, right);
+        if (!contains(position.castlingAvailability, right) || !squares || squares->rook != to)
+            return R::NO_CASTLING_RIGHTS;
+        if (!(SquareSet::occupancy(board) & chess960CastlingPath(*squares)).empty())
+            return R::PATH_BLOCKED;
+        if (chess960CastlingAttacked(board, *squares)) return R::CASTLING_THROUGH_CHECK;
+        return R::LEAVES_KING_IN_CHECK;
+    }
+    if (target != Piece::NONE && color(target) == active) return R::OWN_PIECE_ON_TARGET;
+
+    auto occupancy = SquareSet::occupancy(board);
+    bool white = active == Color::WHITE;
+    auto home = white ? Position::whiteKing : Position::blackKing;
+    if (!chess960 && piece == addColor(PieceType::KING, active) && from == home &&
+        to.rank() == from.rank() &&
+        (to.file() == Position::kKingCastledKingSideFile ||
+         to.file() == Position::kKingCastledQueenSideFile)) {
+        bool kingSide = to.file() == Position::kKingCastledKingSideFile;
+        Square rook{from.rank(),
+                    kingSide ? Position::kKingSideRookFile : Position::kQueenSideRookFile};
+        if (!contains(position.castlingAvailability, castlingMask(rook, rook)))
+            return R::NO_CASTLING_RIGHTS;
+        auto side = kingSide ? MoveKind::KING_CASTLE : MoveKind::QUEEN_CASTLE;
+        if (!(occupancy & castlingPath(active, side)).empty()) return R::PATH_BLOCKED;
+        if (isAttacked(board, castlingSafe(active, side), !active)) return R::CASTLING_THROUGH_CHECK;
+        return R::LEAVES_KING_IN_CHECK;
+    }
+
+    bool pawn = type(piece) == PieceType::PAWN;
+    bool enPassant = pawn && target == Piece::NONE && to == position.enPassantTarget &&
+        possibleCaptures(piece, from).contains(to);
+    bool capture = target != Piece::NONE || enPassant;
+    if (!(capture ? possibleCaptures(piece, from) : possibleMoves(piece, from)).contains(to))
+        return capture && possibleMoves(piece, from).contains(to) ? R::PATH_BLOCKED
+                                                                  : R::UNREACHABLE;
+    if (!clearPath(occupancy, from, to)) return R::PATH_BLOCKED;
+    if (move.isPromotion() != (pawn && (to.rank() == 0 || to.rank() == kNumRanks - 1)))
+        return R::INVALID_PROMOTION;
+    if (position.variant == Variant::ATOMIC && capture && type(piece) == PieceType::KING)
+        return R::KING_CAPTURE;
+    return R::LEAVES_KING_IN_CHECK;  // All other rules are satisfied
+}
@@ -5,6 +5,8 @@
 
 #include "common.h"
 
+#pragma once
+
 /**
  * Represents a set of squares on a chess board. This class is like std::set<Square>, but
  * uses a bitset represented by a uint64_t to store the squares, which is more efficient.
@@ -14,8 +16,8 @@ class SquareSet {
     static_assert(kNumSquares <= sizeof(uint64_t) * CHAR_BIT);
 
 public:
-    SquareSet(uint64_t squares) : _squares(squares) {}
-    SquareSet(Square square) : _squares(1ull << square.index()) {}
+    constexpr SquareSet(uint64_t squares) : _squares(squares) {}
+    constexpr SquareSet(Square square) : _squares(1ull << square.index()) {}
     class iterator;
 
     SquareSet() = default;
@@ -32,32 +34,99 @@ public:
 
     static SquareSet find(const Board& board, Piece piece);
 
-    static SquareSet valid(int rank, int file) {
+    static constexpr SquareSet valid(int rank, int file) {
         return rank >= 0 && rank < kNumRanks && file >= 0 && file < kNumFiles
             ? SquareSet(Square(rank, file))
             : SquareSet();
     }
 
-    void erase(Square square) { _squares &= ~(1ull << square.index()); }
-    void insert(Square square) { _squares |= (1ull << square.index()); }
-    void insert(SquareSet other) { _squares |= other._squares; }
+    static SquareSet dark() { return 0xaa55aa55'aa55aa55ull; }
+    static SquareSet light() { return ~0xaa55aa55'aa55aa55ull; }
+
+    /**
+     * Returns the squares of the same color as the given one, like those a bishop there can reach.
+     */
+    static SquareSet sameColor(Square square) {
+        return square.colorComplex() == SquareColor::DARK ? dark() : light();
+    }
+
+    static SquareSet file(int file) { return 0x01010101'01010101ull << file; }
+    static SquareSet rank(int rank) { return 0xffull << (rank * kNumFiles); }
+
+    /**
+     * Returns the squares on the files next to the given one, as used for isolated pawns.
+     */
+    static SquareSet adjacentFiles(int file) {
+        return (file > 0 ? SquareSet::file(file - 1) : SquareSet()) |
+            (file < kNumFiles - 1 ? SquareSet::file(file + 1) : SquareSet());
+    }
+
+    /**
+     * Returns all squares on the files or ranks that contain any of the given squares.
+     */
+    static SquareSet filesOf(SquareSet squares) {
+        auto files = squares._squares;
+        files |= files >> 32;
+        files |= files >> 16;
+        files |= files >> 8;
+        return (files & 0xff) * 0x01010101'01010101ull;
+    }
+    static SquareSet ranksOf(SquareSet squares) {
+        auto ranks = squares._squares;
+        ranks |= ranks >> 4;
+        ranks |= ranks >> 2;
+        ranks |= ranks >> 1;
+        return (ranks & 0x01010101'01010101ull) * 0xff;
+    }
+
+    /**
+     * Transformations of the board: flipVertical swaps the ranks, so a1 becomes a8, while
+     * mirrorHorizontal swaps the files, so a1 becomes h1. flipDiagonal mirrors the squares in the
+     * a1-h8 diagonal, so a8 becomes h1, and rotate180 does both the vertical and horizontal flip.
+     */
+    SquareSet flipVertical() const { return __builtin_bswap64(_squares); }
+    SquareSet mirrorHorizontal() const {
+        constexpr uint64_t k1 = 0x55555555'55555555ull;
+        constexpr uint64_t k2 = 0x33333333'33333333ull;
+        constexpr uint64_t k4 = 0x0f0f0f0f'0f0f0f0full;
+        auto squares = _squares;
+        squares = ((squares >> 1) & k1) | ((squares & k1) << 1);
+        squares = ((squares >> 2) & k2) | ((squares & k2) << 2);
+        squares = ((squares >> 4) & k4) | ((squares & k4) << 4);
+        return squares;
+    }
+    SquareSet flipDiagonal() const {
+        auto squares = _squares;
+        auto swap = 0x0f0f0f0f'00000000ull & (squares ^ (squares << 28));
+        squares ^= swap ^ (swap >> 28);
+        swap = 0x33330000'33330000ull & (squares ^ (squares << 14));
+        squares ^= swap ^ (swap >> 14);
+        swap = 0x55005500'55005500ull & (squares ^ (squares << 7));
+        squares ^= swap ^ (swap >> 7);
+        return squares;
+    }
+    SquareSet rotate180() const { return flipVertical().mirrorHorizontal(); }
+
+    constexpr void erase(Square square) { _squares &= ~(1ull << square.index()); }
+    constexpr void insert(Square square) { _squares |= (1ull << square.index()); }
+    constexpr void insert(SquareSet other) { _squares |= other._squares; }
 
     void insert(iterator begin, iterator end) {
         for (auto it = begin; it != end; ++it) insert(*it);
     }
 
-    bool empty() const { return _squares == 0; }
+    constexpr bool empty() const { return _squares == 0; }
     size_t size() const { return __builtin_popcountll(_squares); }
-    bool contains(Square square) const { return (_squares >> square.index()) & 1; }
+    constexpr bool contains(Square square) const { return (_squares >> square.index()) & 1; }
 
-    SquareSet operator&(SquareSet other) const { return _squares & other._squares; }
-    SquareSet operator|(SquareSet other) const { return _squares | other._squares; }
-    SquareSet operator!(void) const { return ~_squares; }
+    constexpr SquareSet operator&(SquareSet other) const { return _squares & other._squares; }
+    constexpr SquareSet operator|(SquareSet other) const { return _squares | other._squares; }
+    constexpr SquareSet operator!(void) const { return ~_squares; }
 
-    SquareSet operator|=(SquareSet other) { return _squares |= other._squares; }
-    SquareSet operator&=(SquareSet other) { return _squares &= other._squares; }
+    constexpr SquareSet operator|=(SquareSet other) { return _squares |= other._squares; }
+    constexpr SquareSet operator&=(SquareSet other) { return _squares &= other._squares; }
 
-    bool operator==(SquareSet other) const { return _squares == other._squares; }
+    constexpr bool operator==(SquareSet other) const { return _squares == other._squares; }
 
     class iterator {
         friend class SquareSet;
@@ -77,20 +146,60 @@ public:
         bool operator!=(const iterator& other) { return !(_squares == other._squares); }
     };
 
-    iterator begin() { return {*this}; }
+    iterator begin() const { return {*this}; }
 
-    iterator end() { return SquareSet(); }
+    iterator end() const { return SquareSet(); }
 };
 
+/**
+ * Returns the squares of the board holding the piece as a bitset, or those not holding it if
+ * inverted. SquareSet::find and SquareSet::occupancy use the SSE2 version, which compares 16
+ * squares at a time, where available, and otherwise the portable SWAR version, which compares 8
+ * squares at a time in a 64-bit word. Both are exposed so they can be compared in benchmarks.
+ */
+uint64_t equalSetSWAR(const Board& board, Piece piece, bool invert);
+#ifdef __SSE2__
+uint64_t equalSetSSE2(const Board& board, Piece piece, bool invert);
+#endif
+
 using MoveVector = std::vector<Move>;
 using ComputedMove = std::pair<Move, Position>;
 using ComputedMoveVector = std::vector<ComputedMove>;
 
+/**
+ * Returns the squares at a distance of exactly one or two king moves from the given square, as
+ * used for evaluating king safety. The inner ring is the same as the moves of a king.
+ */
+SquareSet innerKingRing(Square square);
+SquareSet outerKingRing(Square square);
+
+/**
+ * Returns the number of king moves needed to go from one square to the other on an empty board.
+ */
+int kingDistance(Square from, Square to);
+
+/**
+ * Returns the squares in front of a pawn of the given color, on the same file.
+ */
+SquareSet pawnFrontSpan(Color color, Square square);
+
+/**
+ * Returns the squares that must be free of opponent pawns for a pawn of the given color to be
+ * passed: the squares in front of it on the same and the adjacent files.
+ */
+SquareSet passedPawnMask(Color color, Square square);
+
 /**
  * Returns the set of squares that needs to be empty for castling to be legal.
  */
 SquareSet castlingPath(Color color, MoveKind side);
 
+/**
+ * Returns the squares that may not be attacked for castling to be legal: the king's starting
+ * square and the squares it crosses. The destination is checked like for any other king move.
+ */
+SquareSet castlingSafe(Color color, MoveKind side);
+
 /**
  * This availableMoves function iterates over each square on the board. If a piece of the active
  * color is found, it calculates its possible moves using the possibleMoves function you already
@@ -111,7 +220,7 @@ void addAvailableCaptures(MoveVector& captures, const Board& board, Color active
 void addAvailableEnPassant(MoveVector& captures,
                            const Board& board,
                            Color activeColor,
-                           Square enPassantTarget);
+                           std::optional<Square> enPassantTarget);
 
 /**
  * Calculates all possible moves for a given chess piece on the board.
@@ -149,21 +258,213 @@ SquareSet possibleCaptures(Piece piece, Square from);
  */
 ComputedMoveVector allLegalMoves(const Position& position);
 
+/**
+ * Queries of the legal moves, as used by GUIs highlighting the destinations of a selected piece:
+ * the moves of the piece on the square, the moves to the square, and the captures of opponent
+ * pieces of the given type, including en passant for pawns. Moves are in the order of
+ * allLegalMoves, with all promotions of a pawn. legalTargets returns the squares the piece on the
+ * square can move to, which for castling is the target square of the king.
+ */
+ComputedMoveVector legalMovesFrom(const Position& position, Square from);
+ComputedMoveVector legalMovesTo(const Position& position, Square to);
+ComputedMoveVector legalCaptures(const Position& position, PieceType captured);
+SquareSet legalTargets(const Position& position, Square from);
+
+/**
+ * Reasons a move may be illegal, to give users actionable feedback. When several apply, the first
+ * one found is reported, checking the piece, its movement, and then the safety of the king.
+ */
+enum class IllegalMoveReason : uint8_t {
+    NONE,                    // The move is legal
+    NO_PIECE,                // There is no piece on the from square
+    NOT_YOUR_PIECE,          // The piece belongs to the side not to move
+    OWN_PIECE_ON_TARGET,     // The to square holds a piece of the side to move
+    UNREACHABLE,             // The piece doesn't move that way
+    PATH_BLOCKED,            // Another piece is in the way
+    NO_CASTLING_RIGHTS,      // The king or rook has moved before
+    CASTLING_THROUGH_CHECK,  // The king is in check, or passes an attacked square
+    INVALID_PROMOTION,       // A pawn reaching the last rank must promote, and only then
+    KING_CAPTURE,            // In atomic chess, kings can't capture
+    LEAVES_KING_IN_CHECK,    // The king is in check after the move, or exploded in atomic chess
+};
+std::string to_string(IllegalMoveReason reason);
+
+/**
+ * Returns why the move is illegal in the position, or IllegalMoveReason::NONE if it is legal. Only
+ * the squares and the promotion, if any, of the move are considered, not its kind otherwise.
+ */
+IllegalMoveReason checkMove(const Position& position, Move move);
+
 bool isAttacked(const Board& board, Square square);
 bool isAttacked(const Board& board, SquareSet squares);
 
 /**
- * Updates the board with the given move, which may be a capture.
- * Does not perform any legality checks.
+ * Returns true if any of the given squares is attacked by a piece of the opponent color. Unlike
+ * the functions above, the squares need not be occupied, as is the case when castling.
+ */
+bool isAttacked(const Board& board, Square square, Color opponentColor);
+bool isAttacked(const Board& board, SquareSet squares, Color opponentColor);
+
+/**
+ * Returns all squares attacked by pieces of the given color, including those occupied by its own
+ * pieces, which are defended. A king may move to a square only if it isn't attacked with the king
+ * itself removed from the board, so a single map decides the legality of all its moves.
+ */
+SquareSet attackedSquares(const Board& board, Color color);
+
+/**
+ * Returns the squares from which a piece of the given color and type would check the king of the
+ * opponent, given the other pieces on the board, as needed to find quiet checks or to judge the
+ * safety of a king. Squares holding pieces are included, so callers pick the ones they can reach.
+ * Kings never give check, and without a single opponent king there are no such squares.
+ */
+SquareSet checkSquares(const Board& board, Color color, PieceType type);
+
+/**
+ * A relationship between the pieces on two squares, as drawn with an arrow by GUIs.
+ */
+struct Arrow {
+    Square from;
+    Square to;
+
+    bool operator==(const Arrow& other) const { return from == other.from && to == other.to; }
+};
+
+/**
+ * The attacks of all pieces on a board, so frontends can show threats without their own attack
+ * logic. Like attackedSquares, these ignore pins and en passant, and a piece attacks a square even
+ * if it holds a piece of its own color, which is then defended.
+ */
+struct AttackMap {
+    std::array<SquareSet, kNumSquares> attacks;  // Squares attacked by the piece on each square
+    std::vector<Arrow> threats;                  // From attackers to opponent pieces attacked
+    std::vector<Arrow> defenses;                 // From defenders to own pieces defended
+};
+AttackMap attackMap(const Board& board);
+
+/**
+ * Returns true if the king of the active color is in check. In atomic chess, kings next to each
+ * other are never in check, and a king that exploded counts as checked.
+ */
+bool isInCheck(const Position& position);
+
+/**
+ * Returns true if the move is castling: a king moving two files, or as in chess960, a king moving
+ * onto a rook of its own color.
+ */
+bool isCastling(const Board& board, Move move);
+
+/**
+ * Updates the board with the given move, which may be a capture. For castling, see isCastling, the
+ * rook is moved as well. Does not perform any legality checks.
  */
 void applyMove(Board& board, Move move);
 
+/**
+ * Returns the move between the given squares, with the kind derived from the board: a capture if
+ * the target is occupied, en passant for a pawn moving diagonally to an empty square, castling for
+ * a king moving two files or onto its own rook, a double pawn push, or a promotion to the given
+ * type, if any. This way
+ * moves given only as coordinates, as by GUIs, books and databases, can be constructed without
+ * generating the legal moves. Does not perform any legality checks.
+ */
+Move inferMove(const Board& board,
+               Square from,
+               Square to,
+               std::optional<PieceType> promotion = std::nullopt);
+
 /**
  * Like the above, but also updates per turn state (active color, castling availability,
- * en passant target, halfmove clock, and fullmove number).
+ * en passant target, halfmove clock, and fullmove number), and explodes captures in atomic chess.
  */
 [[nodiscard]] Position applyMove(Position position, Move move);
 
+/**
+ * Returns true if the en passant target of the position could result from a double pawn push as
+ * the last move: the target is on the sixth rank (third for black) and empty, with the pawn of the
+ * opponent in front of it and the square it came from empty. No target is always valid.
+ */
+bool isValidEnPassantTarget(const Position& position);
+
+/**
+ * Clears the en passant target unless an en passant capture is legal, as required by many engines
+ * and book formats, so positions only differing in an unusable target compare and hash the same.
+ * In strict mode, returns false for an invalid target and leaves the position unchanged, instead
+ * of clearing the target.
+ */
+bool normalizeEnPassant(Position& position, bool strict = false);
+
+/**
+ * Returns the castling rights the board allows, those with the king and rook on their initial
+ * squares, regardless of whether they moved before. In chess960, any king on its back rank with a
+ * rook on that side of it allows castling, see castlingSquares.
+ */
+CastlingMask possibleCastling(const Board& board, Variant variant = Variant::STANDARD);
+
+/**
+ * Editing operations for board editors setting up positions. Unlike assigning to the board
+ * directly, they keep the rest of the position consistent with the board: castling rights are
+ * dropped once their king or rook leaves its initial square, and the en passant target once it is
+ * no longer valid, see isValidEnPassantTarget. Putting Piece::NONE removes the piece.
+ */
+void putPiece(Position& position, Square square, Piece piece);
+void removePiece(Position& position, Square square);
+
+/**
+ * Sets the side to move, which clears the en passant target, as that is for the other side.
+ */
+void setSideToMove(Position& position, Color color);
+
+/**
+ * Sets the castling rights. Returns false and leaves the position unchanged if the board doesn't
+ * allow all of them, see possibleCastling. Rights that are new castle with the outermost rook on
+ * their side of the king.
+ */
+bool setCastling(Position& position, CastlingMask rights);
+
+/**
+ * Reasons a position can't be played from, to give feedback on positions set up in an editor.
+ * When several apply, the first one found is reported, in the order listed.
+ */
+enum class IllegalPositionReason : uint8_t {
+    NONE,                // The position is legal
+    MISSING_KING,        // A side has no king
+    TOO_MANY_KINGS,      // A side has more than one king
+    PAWN_ON_BACK_RANK,   // Pawns can't be on the first or last rank
+    OPPONENT_IN_CHECK,   // The side not to move is in check, so its king could be captured
+    INVALID_CASTLING,    // A castling right lacks its king or rook on its initial square
+    INVALID_EN_PASSANT,  // The en passant target can't result from the last move
+};
+std::string to_string(IllegalPositionReason reason);
+
+/**
+ * Returns why the position can't be played from, or IllegalPositionReason::NONE if it can. Only
+ * checks what the rules require of any position, not whether it is reachable from the start.
+ */
+IllegalPositionReason validatePosition(const Position& position);
+
+/**
+ * Returns the position with the board flipped vertically and the colors of all pieces swapped, so
+ * the opponent is to move with the same rights. The number of legal moves, and the evaluation with
+ * the sign reversed, must be the same as for the original position.
+ */
+Position mirror(const Position& position);
+
+/**
+ * Options for canonical forms of positions, see canonical.
+ */
+struct CanonicalOptions {
+    bool ignoreClocks = true;  // Reset the halfmove clock and fullmove number
+    bool flipColors = false;   // Mirror positions with black to move, so white is always to move
+};
+
+/**
+ * Returns the canonical form of the position, so positions that are the same for training and
+ * tuning data compare equal: the en passant target is normalized, see normalizeEnPassant, and
+ * depending on the options, the clocks are reset and the colors flipped.
+ */
+Position canonical(Position position, CanonicalOptions options = {});
+
 /**
  *  Returns the castling mask for the castling rights cancelled by the given move.
  */
@@ -3,7 +3,9 @@
 #include <iostream>
 
 #include "fen.h"
+#include "hash.h"
 #include "moves.h"
+#include "testkit.h"
 
 std::string toString(SquareSet squares) {
     std::string str;
@@ -79,6 +81,9 @@ void testColor() {
     assert(color(Piece::BLACK_ROOK) == Color::BLACK);
     assert(color(Piece::BLACK_QUEEN) == Color::BLACK);
     assert(color(Piece::BLACK_KING) == Color::BLACK);
+    assert(isColor(Piece::WHITE_PAWN, Color::WHITE) && !isColor(Piece::WHITE_PAWN, Color::BLACK));
+    assert(isColor(Piece::BLACK_KING, Color::BLACK) && !isColor(Piece::BLACK_KING, Color::WHITE));
+    assert(!isColor(Piece::NONE, Color::WHITE) && !isColor(Piece::NONE, Color::BLACK));
 
     assert(addColor(PieceType::PAWN, Color::WHITE) == Piece::WHITE_PAWN);
     assert(addColor(PieceType::KNIGHT, Color::WHITE) == Piece::WHITE_KNIGHT);
@@ -96,6 +101,30 @@ void testColor() {
     std::cout << "All Color tests passed!" << std::endl;
 }
 
+void testCastlingMask() {
+    assert(to_string(CastlingMask::ALL) == "KQkq" && to_string(CastlingMask::NONE) == "-");
+    assert(to_string(CastlingMask::WHITE_QUEENSIDE | CastlingMask::BLACK_KINGSIDE) == "Qk");
+    for (auto str : {"KQkq", "Kq", "-", "k"}) assert(to_string(toCastlingMask(str)) == str);
+    assert(toCastlingMask("qK") == (CastlingMask::WHITE_KINGSIDE | CastlingMask::BLACK_QUEENSIDE));
+
+    assert(contains(CastlingMask::ALL, CastlingMask::BLACK));
+    assert(!contains(CastlingMask::WHITE_KINGSIDE, CastlingMask::WHITE));
+    assert(contains(CastlingMask::WHITE_KINGSIDE, CastlingMask::NONE));
+
+    auto rights = castlingRights(CastlingMask::WHITE_KINGSIDE | CastlingMask::BLACK);
+    assert(rights.size() == 3 && rights.front() == CastlingMask::WHITE_KINGSIDE);
+    assert(castlingRights(CastlingMask::NONE).empty());
+    assert(castlingRights(CastlingMask::ALL).size() == kCastlingRights.size());
+
+    // Squares to clear for castling differ from those that may not be attacked on the queen side
+    assert(toString(castlingPath(Color::WHITE, MoveKind::KING_CASTLE)) == "f1 g1");
+    assert(toString(castlingSafe(Color::WHITE, MoveKind::KING_CASTLE)) == "e1 f1");
+    assert(toString(castlingPath(Color::BLACK, MoveKind::QUEEN_CASTLE)) == "b8 c8 d8");
+    assert(toString(castlingSafe(Color::BLACK, MoveKind::QUEEN_CASTLE)) == "d8 e8");
+
+    std::cout << "All CastlingMask tests passed!" << std::endl;
+}
+
 void testPossibleMoves() {
     // Test rook moves
     {
@@ -312,6 +341,15 @@ void testOccupancy() {
         board["e8"_sq] = Piece::BLACK_KING;
         auto squares = SquareSet::occupancy(board);
         assert(squares.size() == 20);
+        for (auto piece = 0; piece != kNumPieces; ++piece) {
+            auto found = SquareSet::find(board, Piece(piece));
+            for (Square square = 0; square != kNumSquares; ++square)
+                assert(found.contains(square) == (board[square] == Piece(piece)));
+            assert(SquareSet(equalSetSWAR(board, Piece(piece), false)) == found);
+#ifdef __SSE2__
+            assert(SquareSet(equalSetSSE2(board, Piece(piece), false)) == found);
+#endif
+        }
     }
     std::cout << "All occupancy tests passed!" << std::endl;
 }
@@ -524,6 +562,14 @@ void testApplyMove() {
         assert(position.halfmoveClock == 2);
     }
 
+    // Test castling moves the rook as well
+    {
+        Board board = fen::parsePiecePlacement("r3k2r/8/8/8/8/8/8/R3K2R");
+        applyMove(board, Move("e1"_sq, "g1"_sq, MoveKind::KING_CASTLE));
+        applyMove(board, Move("e8"_sq, "c8"_sq, MoveKind::QUEEN_CASTLE));
+        assert(fen::to_string(board) == "2kr3r/8/8/8/8/8/8/R4RK1");
+    }
+
     // Test en passant capture
     {
         Position position;
@@ -538,6 +584,12 @@ void testApplyMove() {
         assert(position.activeColor == Color::BLACK);
         assert(position.halfmoveClock == 0);
     }
+    {
+        // The counters saturate instead of overflowing
+        auto position = fen::parsePosition("4k3/8/8/8/8/8/8/4K3 b - - 255 65535");
+        position = applyMove(position, Move("e8"_sq, "d8"_sq, MoveKind::QUIET_MOVE));
+        assert(position.halfmoveClock == 255 && position.fullmoveNumber == 65535);
+    }
 
     std::cout << "All applyMove tests passed!" << std::endl;
 }
@@ -608,15 +660,675 @@ void testAllLegalMoves() {
         assert(legalMoves.size() == 22);
     }
 
+    // Castling on both sides, but not through or out of check
+    {
+        auto kingSide = Move("e1"_sq, "g1"_sq, MoveKind::KING_CASTLE);
+        auto queenSide = Move("e1"_sq, "c1"_sq, MoveKind::QUEEN_CASTLE);
+        auto position = fen::parsePosition("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        auto moves = justMoves(allLegalMoves(position));
+        assert(std::count(moves.begin(), moves.end(), kingSide));
+        assert(std::count(moves.begin(), moves.end(), queenSide));
+
+        position = fen::parsePosition("r3k2r/8/8/8/4r3/8/8/R3K2R w KQkq - 0 1");
+        assert(allLegalMoves(position).size() == 4);  // King in check, so no castling
+
+        position = fen::parsePosition("r3k2r/8/8/8/8/8/3r4/R3K2R w KQkq - 0 1");
+        moves = justMoves(allLegalMoves(position));
+        assert(std::count(moves.begin(), moves.end(), kingSide));
+        assert(!std::count(moves.begin(), moves.end(), queenSide));
+    }
+
     std::cout << "All allLegalMoves tests passed!" << std::endl;
 }
 
+void testFilesAndRanks() {
+    assert(toString(SquareSet::file(2)) == "c1 c2 c3 c4 c5 c6 c7 c8");
+    assert(toString(SquareSet::rank(7)) == "a8 b8 c8 d8 e8 f8 g8 h8");
+    assert(SquareSet::adjacentFiles(0) == SquareSet::file(1));
+    assert(SquareSet::adjacentFiles(7) == SquareSet::file(6));
+    assert(SquareSet::adjacentFiles(4) == (SquareSet::file(3) | SquareSet::file(5)));
+
+    auto squares = SquareSet("b2"_sq) | SquareSet("b7"_sq) | SquareSet("h3"_sq);
+    assert(SquareSet::filesOf(squares) == (SquareSet::file(1) | SquareSet::file(7)));
+    assert(SquareSet::ranksOf(squares) ==
+           (SquareSet::rank(1) | SquareSet::rank(2) | SquareSet::rank(6)));
+    assert(SquareSet::filesOf(SquareSet()).empty() && SquareSet::ranksOf(SquareSet()).empty());
+
+    // Doubled pawns are on a file with more than one pawn of the same color
+    auto board = fen::parsePiecePlacement("4k3/pp6/8/8/8/1P5P/1P6/4K3");
+    auto whitePawns = SquareSet::find(board, Piece::WHITE_PAWN);
+    assert((whitePawns & SquareSet::file(1)).size() == 2);
+    assert((SquareSet::adjacentFiles(7) & whitePawns).empty());
+
+    std::cout << "All file and rank tests passed!" << std::endl;
+}
+
+void testSquareColors() {
+    assert("a1"_sq.colorComplex() == SquareColor::DARK);
+    assert("h1"_sq.colorComplex() == SquareColor::LIGHT);
+    assert("d1"_sq.colorComplex() == SquareColor::LIGHT);
+    assert("d8"_sq.colorComplex() == SquareColor::DARK);
+    assert(SquareSet::dark().size() == 32 && (SquareSet::dark() | SquareSet::light()).size() == 64);
+    assert((SquareSet::dark() & SquareSet::light()).empty());
+    for (Square square = 0; square != kNumSquares; ++square) {
+        auto sameColor = SquareSet::sameColor(square);
+        assert(sameColor.contains(square));
+        for (auto other : sameColor) assert(other.colorComplex() == square.colorComplex());
+    }
+    assert(SquareSet::sameColor("c1"_sq) == SquareSet::dark());
+
+    std::cout << "All square color tests passed!" << std::endl;
+}
+
+void testTransforms() {
+    for (Square square = 0; square != kNumSquares; ++square) {
+        auto rank = square.rank(), file = square.file();
+        auto set = SquareSet(square);
+        assert(set.flipVertical() == SquareSet(Square(kNumRanks - 1 - rank, file)));
+        assert(set.mirrorHorizontal() == SquareSet(Square(rank, kNumFiles - 1 - file)));
+        assert(set.flipDiagonal() == SquareSet(Square(file, rank)));
+        assert(set.rotate180() == SquareSet(Square(kNumSquares - 1 - square.index())));
+    }
+    assert(SquareSet::rank(1).flipVertical() == SquareSet::rank(6));
+    assert(SquareSet::file(0).mirrorHorizontal() == SquareSet::file(7));
+    assert(SquareSet::file(2).flipDiagonal() == SquareSet::rank(2));
+    assert(SquareSet::dark().rotate180() == SquareSet::dark());
+    assert(SquareSet::dark().mirrorHorizontal() == SquareSet::light());
+
+    std::cout << "All transform tests passed!" << std::endl;
+}
+
+void testMasks() {
+    assert(toString(innerKingRing("a1"_sq)) == "b1 a2 b2");
+    assert(innerKingRing("e4"_sq) == possibleMoves(Piece::WHITE_KING, "e4"_sq));
+    assert(outerKingRing("e4"_sq).size() == 16 && outerKingRing("a1"_sq).size() == 5);
+    assert(toString(outerKingRing("h8"_sq)) == "f6 g6 h6 f7 f8");
+    assert(kingDistance("a1"_sq, "h8"_sq) == 7 && kingDistance("e4"_sq, "f6"_sq) == 2);
+    assert(kingDistance("c3"_sq, "c3"_sq) == 0 && kingDistance("b7"_sq, "g6"_sq) == 5);
+
+    assert(toString(pawnFrontSpan(Color::WHITE, "e5"_sq)) == "e6 e7 e8");
+    assert(toString(pawnFrontSpan(Color::BLACK, "b3"_sq)) == "b1 b2");
+    assert(pawnFrontSpan(Color::WHITE, "c8"_sq).empty());
+    assert(toString(passedPawnMask(Color::WHITE, "a6"_sq)) == "a7 b7 a8 b8");
+    assert(toString(passedPawnMask(Color::BLACK, "e3"_sq)) == "d1 e1 f1 d2 e2 f2");
+
+    std::cout << "All mask tests passed!" << std::endl;
+}
+
+void testEnPassantTarget() {
+    // A usable target is kept
+    auto position = fen::parsePosition("4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3 0 42");
+    assert(isValidEnPassantTarget(position) && normalizeEnPassant(position, true));
+    assert(position.enPassantTarget == "f3"_sq);
+
+    // No pawn to capture, or a pinned one, clears the target
+    position =
+        fen::parsePosition("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+    assert(isValidEnPassantTarget(position) && normalizeEnPassant(position, true));
+    assert(!position.enPassantTarget);
+    position = fen::parsePosition("8/8/8/8/k2Pp2R/8/8/4K3 b - d3 0 1");
+    assert(isValidEnPassantTarget(position));
+    assert(normalizeEnPassant(position) && !position.enPassantTarget);
+
+    // Impossible targets: wrong rank, no pawn that just moved, or an occupied origin square
+    for (auto fen : {"4k3/8/8/3pP3/8/8/8/4K3 w - d5 0 1",
+                     "4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1",
+                     "4k3/3n4/8/3pP3/8/8/8/4K3 w - d6 0 1"}) {
+        position = fen::parsePosition(fen);
+        assert(!isValidEnPassantTarget(position));
+        assert(!normalizeEnPassant(position, true));
+        assert(position.enPassantTarget);
+        assert(normalizeEnPassant(position));
+        assert(!position.enPassantTarget);
+    }
+
+    std::cout << "All en passant target tests passed!" << std::endl;
+}
+
+void testMirror() {
+    auto position = fen::parsePosition("4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kq f3 0 42");
+    auto mirrored = mirror(position);
+    assert(fen::to_string(mirrored) == "4k2r/7p/8/5pP1/2Q5/8/8/4K3 w Qk f6 0 42");
+    assert(fen::to_string(mirror(mirrored)) == fen::to_string(position));
+    assert(allLegalMoves(mirrored).size() == allLegalMoves(position).size());
+
+    // The castling rights keep their rook files, which differ per side in Double Fischer Random
+    position = fen::chess960Position(0, 959);
+    for (auto rank : {0, kNumRanks - 1})
+        for (int file = 0; file != kNumFiles; ++file)
+            if (type(position.board[Square(rank, file)]) != PieceType::KING &&
+                type(position.board[Square(rank, file)]) != PieceType::ROOK)
+                position.board[Square(rank, file)] = Piece::NONE;
+    mirrored = mirror(position);
+    assert(fen::to_string(position, true) == "rkr5/pppppppp/8/8/8/8/PPPPPPPP/5RKR w HFca - 0 1");
+    assert(fen::to_string(mirrored, true) == "5rkr/pppppppp/8/8/8/8/PPPPPPPP/RKR5 b CAhf - 0 1");
+    assert(fen::to_string(mirror(mirrored), true) == fen::to_string(position, true));
+    auto castles = [](const Position& position) {
+        MoveVector moves;
+        for (auto [move, next] : allLegalMoves(position))
+            if (move.kind == MoveKind::KING_CASTLE || move.kind == MoveKind::QUEEN_CASTLE)
+                moves.push_back(Move(Square(0, move.from.file()), Square(0, move.to.file()),
+                                     move.kind));
+        return moves;
+    };
+    assert(castles(position).size() == 1 && castles(mirrored) == castles(position));
+    auto flip = CanonicalOptions{true, true};
+    assert(fen::to_string(canonical(mirrored, flip), true) ==
+           fen::to_string(canonical(position, flip), true));
+
+    std::cout << "All mirror tests passed!" << std::endl;
+}
+
+void testCanonical() {
+    // The en passant target can't be used, and the clocks are reset by default
+    auto position = fen::parsePosition("4k3/8/8/2q5/5P2/8/7P/4K2R b Kq f3 3 42");
+    assert(fen::to_string(canonical(position)) == "4k3/8/8/2q5/5P2/8/7P/4K2R b Kq - 0 1");
+    assert(fen::to_string(canonical(position, {false, false})) ==
+           "4k3/8/8/2q5/5P2/8/7P/4K2R b Kq - 3 42");
+    assert(fen::to_string(canonical(position, {true, true})) ==
+           "4k2r/7p/8/5p2/2Q5/8/8/4K3 w Qk - 0 1");
+
+    // Transpositions and mirrored positions are duplicates, depending on the options
+    auto initial = fen::parsePosition(fen::initialPosition);
+    auto after = [&](std::vector<Move> moves) {
+        auto position = initial;
+        for (auto move : moves) position = applyMove(position, move);
+        return position;
+    };
+    Move e3 = {"e2"_sq, "e3"_sq, MoveKind::QUIET_MOVE}, e6 = {"e7"_sq, "e6"_sq, MoveKind::QUIET_MOVE};
+    Move nf3 = {"g1"_sq, "f3"_sq, MoveKind::QUIET_MOVE}, nf6 = {"g8"_sq, "f6"_sq, MoveKind::QUIET_MOVE};
+    std::vector<Position> positions = {initial,
+                                       after({e3, e6, nf3}),
+                                       after({nf3, e6, e3}),
+                                       after({nf3}),
+                                       mirror(after({nf3})),
+                                       after({e3, e6, nf3, nf6})};
+    assert(deduplicate(positions).size() == 5);
+    assert(deduplicate(positions, {false, false}).size() == 6);
+    assert(deduplicate(positions, {true, true}).size() == 4);
+    assert(fen::to_string(deduplicate(positions, {true, true})[3]) ==
+           "rnbqkb1r/pppp1ppp/4pn2/8/8/4PN2/PPPP1PPP/RNBQKB1R w KQkq - 0 1");
+
+    std::cout << "All canonical tests passed!" << std::endl;
+}
+
+void testTranspositionTable() {
+    // A single bucket of four entries, so all hashes collide
+    TranspositionTable<int> table(0);
+    assert(table.capacity() == 4);
+    std::vector<Hash> hashes(6);
+    for (int i = 0; i < 6; ++i) hashes[i].toggle(i);
+
+    assert(!table.probe(hashes[0]));
+    for (int i = 0; i < 4; ++i) table.store(hashes[i], 4 - i, Bound::EXACT, i);
+    for (int i = 0; i < 4; ++i) assert(table.probe(hashes[i])->value == i);
+
+    // Storing the same hash replaces its entry, whatever the draft
+    table.store(hashes[1], 1, Bound::LOWER, 10);
+    auto entry = table.probe(hashes[1]);
+    assert(entry->draft == 1 && entry->bound == Bound::LOWER && entry->value == 10);
+
+    // When full, the entry with the least draft goes, and the earliest of those on ties
+    table.store(hashes[4], 5, Bound::UPPER, 4);
+    assert(!table.probe(hashes[1]) && table.probe(hashes[3])->value == 3);
+    table.store(hashes[5], 0, Bound::EXACT, 5);
+    assert(!table.probe(hashes[3]) && table.probe(hashes[5])->value == 5);
+    assert(table.probe(hashes[4])->draft == 5);
+
+    table.clear();
+    for (auto hash : hashes) assert(!table.probe(hash));
+
+    std::cout << "All transposition table tests passed!" << std::endl;
+}
+
+void testCheckMove() {
+    using R = IllegalMoveReason;
+    auto check = [](const char* fen, Move move) {
+        return checkMove(fen::parsePosition(fen), move);
+    };
+    auto quiet = [](Square from, Square to) { return Move(from, to, MoveKind::QUIET_MOVE); };
+
+    auto initial = fen::parsePosition(fen::initialPosition);
+    assert(checkMove(initial, quiet("e2"_sq, "e4"_sq)) == R::NONE);
+    assert(checkMove(initial, quiet("e3"_sq, "e4"_sq)) == R::NO_PIECE);
+    assert(checkMove(initial, quiet("e7"_sq, "e5"_sq)) == R::NOT_YOUR_PIECE);
+    assert(checkMove(initial, quiet("d1"_sq, "d2"_sq)) == R::OWN_PIECE_ON_TARGET);
+    assert(checkMove(initial, quiet("e2"_sq, "e5"_sq)) == R::UNREACHABLE);
+    assert(checkMove(initial, quiet("f1"_sq, "c4"_sq)) == R::PATH_BLOCKED);
+
+    // Pawns are blocked by pieces in front of them, and can't capture forward
+    assert(check("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1", quiet("e2"_sq, "e3"_sq)) == R::PATH_BLOCKED);
+    assert(check("4k3/8/8/8/4n3/8/4P3/4K3 w - - 0 1", quiet("e2"_sq, "e4"_sq)) == R::PATH_BLOCKED);
+
+    // Castling
+    auto castle = "r3k2r/8/8/8/8/8/8/R3K2R w Qk - 0 1";
+    assert(check(castle, quiet("e1"_sq, "c1"_sq)) == R::NONE);
+    assert(check(castle, quiet("e1"_sq, "g1"_sq)) == R::NO_CASTLING_RIGHTS);
+    assert(check("4k3/8/8/8/8/8/8/R3KB1R w KQ - 0 1", quiet("e1"_sq, "g1"_sq)) ==
+           R::PATH_BLOCKED);
+    assert(check("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1", quiet("e1"_sq, "g1"_sq)) ==
+           R::CASTLING_THROUGH_CHECK);
+    assert(check("4k3/8/8/8/8/8/6r1/R3K2R w KQ - 0 1", quiet("e1"_sq, "g1"_sq)) ==
+           R::LEAVES_KING_IN_CHECK);
+
+    // Promotions are required on the last rank, and only there
+    auto promote = "4k3/1P6/8/8/8/8/4P3/4K3 w - - 0 1";
+    assert(check(promote, Move("b7"_sq, "b8"_sq, MoveKind::QUEEN_PROMOTION)) == R::NONE);
+    assert(check(promote, quiet("b7"_sq, "b8"_sq)) == R::INVALID_PROMOTION);
+    assert(check(promote, Move("e2"_sq, "e3"_sq, MoveKind::QUEEN_PROMOTION)) ==
+           R::INVALID_PROMOTION);
+
+    // Pinned pieces and kings can't expose the king
+    auto pinned = "4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1";
+    assert(check(pinned, quiet("e2"_sq, "d3"_sq)) == R::LEAVES_KING_IN_CHECK);
+    assert(check(pinned, quiet("e1"_sq, "e2"_sq)) == R::OWN_PIECE_ON_TARGET);
+    assert(check("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1", quiet("e1"_sq, "e2"_sq)) ==
+           R::LEAVES_KING_IN_CHECK);
+
+    // En passant
+    auto enPassant = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+    assert(check(enPassant, Move("e5"_sq, "d6"_sq, MoveKind::EN_PASSANT)) == R::NONE);
+    assert(check(enPassant, quiet("e5"_sq, "f6"_sq)) == R::UNREACHABLE);
+
+    // Kings can't capture in atomic chess
+    auto atomic = fen::parsePosition("4k3/8/8/8/8/8/4p3/4K3 w - - 0 1");
+    atomic.variant = Variant::ATOMIC;
+    assert(checkMove(atomic, Move("e1"_sq, "e2"_sq, MoveKind::CAPTURE)) == R::KING_CAPTURE);
+
+    std::cout << "All move check tests passed!" << std::endl;
+}
+
+void testBoardEditor() {
+    using R = IllegalPositionReason;
+    auto position = fen::parsePosition("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 2");
+    assert(validatePosition(position) == R::NONE);
+
+    // Moving a rook away drops its castling right, and the en passant target goes with its pawn
+    removePiece(position, "h1"_sq);
+    putPiece(position, "h2"_sq, Piece::WHITE_ROOK);
+    assert(fen::to_string(position) == "r3k2r/8/8/3pP3/8/8/7R/R3K3 w Qkq d6 0 2");
+    removePiece(position, "d5"_sq);
+    assert(fen::to_string(position) == "r3k2r/8/8/4P3/8/8/7R/R3K3 w Qkq - 0 2");
+
+    // Putting the rook back doesn't restore the right, but it can be set again
+    putPiece(position, "h1"_sq, Piece::WHITE_ROOK);
+    assert(position.castlingAvailability == (CastlingMask::WHITE_QUEENSIDE | CastlingMask::BLACK));
+    assert(setCastling(position, CastlingMask::ALL));
+    assert(position.castlingAvailability == CastlingMask::ALL);
+    removePiece(position, "e8"_sq);
+    assert(position.castlingAvailability == CastlingMask::WHITE);
+    assert(!setCastling(position, CastlingMask::BLACK_KINGSIDE));
+    assert(validatePosition(position) == R::MISSING_KING);
+
+    // Changing the side to move clears the en passant target
+    position = fen::parsePosition("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2");
+    setSideToMove(position, Color::WHITE);
+    assert(position.enPassantTarget);
+    setSideToMove(position, Color::BLACK);
+    assert(!position.enPassantTarget && position.activeColor == Color::BLACK);
+
+    auto check = [](const char* fen) { return validatePosition(fen::parsePosition(fen)); };
+    assert(check("4k3/8/8/8/8/8/8/4K3 w - - 0 1") == R::NONE);
+    assert(check("4k3/8/8/8/8/8/8/3KK3 w - - 0 1") == R::TOO_MANY_KINGS);
+    assert(check("4k2P/8/8/8/8/8/8/4K3 w - - 0 1") == R::PAWN_ON_BACK_RANK);
+    assert(check("4k3/8/8/8/4R3/8/8/4K3 w - - 0 1") == R::OPPONENT_IN_CHECK);
+    assert(check("4k3/8/8/8/4R3/8/8/4K3 b - - 0 1") == R::NONE);
+    assert(check("4k3/8/8/8/8/8/8/4K2R b Q - 0 1") == R::INVALID_CASTLING);
+    assert(check("4k3/8/8/8/8/8/8/4K3 w - e6 0 1") == R::INVALID_EN_PASSANT);
+    assert(to_string(R::MISSING_KING) == "missing king");
+
+    std::cout << "All board editor tests passed!" << std::endl;
+}
+
+void testCheckSquares() {
+    auto board = fen::parsePiecePlacement("4k3/3p4/4n3/8/1q6/8/8/K7");
+    assert(toString(checkSquares(board, Color::WHITE, PieceType::PAWN)) == "d7 f7");
+    assert(toString(checkSquares(board, Color::WHITE, PieceType::KNIGHT)) == "d6 f6 c7 g7");
+    assert(toString(checkSquares(board, Color::WHITE, PieceType::ROOK)) ==
+           "e6 e7 a8 b8 c8 d8 f8 g8 h8");
+    assert(checkSquares(board, Color::WHITE, PieceType::KING).empty());
+    assert(toString(checkSquares(board, Color::BLACK, PieceType::BISHOP)) ==
+           "b2 c3 d4 e5 f6 g7 h8");
+
+    // Putting the piece on any of the squares checks the king, and on no other square
+    for (auto pieceType : {PieceType::PAWN, PieceType::KNIGHT, PieceType::ROOK, PieceType::QUEEN}) {
+        auto squares = checkSquares(board, Color::WHITE, pieceType);
+        for (Square square = 0; square.index() < kNumSquares; ++square) {
+            if (board[square] == Piece::WHITE_KING || board[square] == Piece::BLACK_KING) continue;
+            auto after = board;
+            after[square] = addColor(pieceType, Color::WHITE);
+            assert(isAttacked(after, "e8"_sq) == squares.contains(square));
+        }
+    }
+
+    board = fen::parsePiecePlacement("8/8/8/8/8/8/8/K7");
+    assert(checkSquares(board, Color::WHITE, PieceType::QUEEN).empty());
+
+    std::cout << "All check squares tests passed!" << std::endl;
+}
+
+void testInferMove() {
+    auto initial = fen::parsePosition(fen::initialPosition).board;
+    assert(inferMove(initial, "e2"_sq, "e3"_sq).kind == MoveKind::QUIET_MOVE);
+    assert(inferMove(initial, "e2"_sq, "e4"_sq).kind == MoveKind::DOUBLE_PAWN_PUSH);
+    assert(inferMove(initial, "g1"_sq, "f3"_sq).kind == MoveKind::QUIET_MOVE);
+
+    auto board = fen::parsePosition("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").board;
+    assert(inferMove(board, "e5"_sq, "d6"_sq).kind == MoveKind::EN_PASSANT);
+    assert(inferMove(board, "e1"_sq, "g1"_sq).kind == MoveKind::KING_CASTLE);
+    assert(inferMove(board, "e1"_sq, "c1"_sq).kind == MoveKind::QUEEN_CASTLE);
+    assert(inferMove(board, "a1"_sq, "a8"_sq).kind == MoveKind::CAPTURE);
+    assert(inferMove(board, "b7"_sq, "b8"_sq, PieceType::KNIGHT).kind ==
+           MoveKind::KNIGHT_PROMOTION);
+    assert(inferMove(board, "b7"_sq, "a8"_sq, PieceType::QUEEN).kind ==
+           MoveKind::QUEEN_PROMOTION_CAPTURE);
+
+    // All legal moves are inferred with the kind the move generator uses for them, apart from
+    // double pawn pushes and en passant captures, which it doesn't distinguish
+    std::mt19937 gen(7);
+    for (int game = 0; game < 20; ++game) {
+        auto position = testkit::randomPosition(gen, 30);
+        for (auto& [move, newPosition] : allLegalMoves(position)) {
+            auto promotion = move.isPromotion() ? std::optional(promotionType(move.kind))
+                                                : std::nullopt;
+            auto inferred = inferMove(position.board, move.from, move.to, promotion);
+            auto board = position.board;
+            applyMove(board, inferred);
+            assert(board == newPosition.board);
+            if (inferred.kind != MoveKind::DOUBLE_PAWN_PUSH &&
+                inferred.kind != MoveKind::EN_PASSANT)
+                assert(inferred == move);
+        }
+    }
+
+    std::cout << "All infer move tests passed!" << std::endl;
+}
+
+void testAttackedSquares() {
+    auto board = fen::parsePosition("4k3/8/8/8/8/8/3p4/R3K3 w - - 0 1").board;
+    auto white = attackedSquares(board, Color::WHITE);
+    assert(white.contains("a8"_sq) && white.contains("d2"_sq) && white.contains("f2"_sq));
+    assert(white.contains("e1"_sq) && !white.contains("g1"_sq));  // Defended, and blocked
+    auto black = attackedSquares(board, Color::BLACK);
+    assert(black.contains("c1"_sq) && black.contains("e1"_sq) && !black.contains("d1"_sq));
+
+    // The attack maps agree with isAttacked for every square
+    std::mt19937 gen(11);
+    for (int game = 0; game < 20; ++game) {
+        auto board = testkit::randomPosition(gen, 40).board;
+        for (auto color : {Color::WHITE, Color::BLACK}) {
+            auto attacked = attackedSquares(board, color);
+            for (Square square = 0; square != kNumSquares; ++square)
+                assert(attacked.contains(square) == isAttacked(board, square, color));
+        }
+    }
+
+    std::cout << "All attacked squares tests passed!" << std::endl;
+}
+
+void testAttackMap() {
+    auto board = fen::parsePosition("4k3/8/8/8/8/8/3p4/R3K3 w - - 0 1").board;
+    auto map = attackMap(board);
+    assert(toString(map.attacks["a1"_sq.index()]) == "b1 c1 d1 e1 a2 a3 a4 a5 a6 a7 a8");
+    assert(toString(map.attacks["d2"_sq.index()]) == "c1 e1");
+    assert(map.attacks["b1"_sq.index()].empty());
+    assert((map.threats == std::vector<Arrow>{{"e1"_sq, "d2"_sq}, {"d2"_sq, "e1"_sq}}));
+    assert((map.defenses == std::vector<Arrow>{{"a1"_sq, "e1"_sq}}));
+
+    // The attacks of all pieces of a color make up its attacked squares
+    std::mt19937 gen(13);
+    for (int game = 0; game < 20; ++game) {
+        auto board = testkit::randomPosition(gen, 40).board;
+        auto map = attackMap(board);
+        std::array<SquareSet, 2> attacked;
+        for (auto from : SquareSet::occupancy(board))
+            attacked[int(color(board[from]))] |= map.attacks[from.index()];
+        for (auto color : {Color::WHITE, Color::BLACK})
+            assert(attacked[int(color)] == attackedSquares(board, color));
+        for (auto arrow : map.threats)
+            assert(color(board[arrow.from]) != color(board[arrow.to]));
+        for (auto arrow : map.defenses)
+            assert(color(board[arrow.from]) == color(board[arrow.to]));
+    }
+
+    std::cout << "All attack map tests passed!" << std::endl;
+}
+
+void testPinsAndChecks() {
+    auto legal = [](const char* fen) {
+        std::vector<std::string> moves;
+        for (auto& [move, newPosition] : allLegalMoves(fen::parsePosition(fen)))
+            moves.push_back(std::string(move));
+        std::sort(moves.begin(), moves.end());
+        return moves;
+    };
+    using Moves = std::vector<std::string>;
+
+    // A pinned bishop moves along the pin only, and a pinned knight not at all
+    assert(legal("7k/8/8/8/4b3/8/2B5/1K6 w - - 0 1") ==
+           Moves({"b1a1", "b1a2", "b1b2", "b1c1", "c2d3", "c2e4"}));
+    assert(legal("7k/8/8/8/4b3/8/2N5/1K6 w - - 0 1") ==
+           Moves({"b1a1", "b1a2", "b1b2", "b1c1"}));
+
+    // In check, pieces must capture the checker or block, and in double check only the king moves
+    assert(legal("4r2k/8/8/8/8/8/3R4/4K3 w - - 0 1") ==
+           Moves({"d2e2", "e1d1", "e1f1", "e1f2"}));
+    assert(legal("4r2k/8/8/8/8/5n2/3R4/4K3 w - - 0 1") == Moves({"e1d1", "e1f1", "e1f2"}));
+
+    // En passant can't expose the king along the rank of both pawns
+    assert(legal("8/8/8/8/k2Pp2R/8/8/4K3 b - d3 0 1") ==
+           Moves({"a4a3", "a4a5", "a4b3", "a4b4", "a4b5", "e4e3"}));
+
+    std::cout << "All pin and check tests passed!" << std::endl;
+}
+
+void testLegalMoveQueries() {
+    auto names = [](const ComputedMoveVector& computed) {
+        std::vector<std::string> moves;
+        for (auto& [move, newPosition] : computed) moves.push_back(std::string(move));
+        std::sort(moves.begin(), moves.end());
+        return moves;
+    };
+    using Moves = std::vector<std::string>;
+
+    // The pinned bishop only moves along the pin, and the king may castle
+    auto position = fen::parsePosition("4k3/8/8/q7/8/5p2/3B4/4K2R w K - 0 1");
+    assert(names(legalMovesFrom(position, "d2"_sq)) == Moves({"d2a5", "d2b4", "d2c3"}));
+    assert(names(legalMovesFrom(position, "e1"_sq)) == Moves({"e1d1", "e1f1", "e1f2", "e1g1"}));
+    assert(toString(legalTargets(position, "e1"_sq)) == "d1 f1 g1 f2");
+    assert(legalMovesFrom(position, "e8"_sq).empty());
+    assert(legalMovesFrom(position, "a1"_sq).empty());
+    assert(names(legalMovesTo(position, "f1"_sq)) == Moves({"e1f1", "h1f1"}));
+    assert(names(legalCaptures(position, PieceType::QUEEN)) == Moves({"d2a5"}));
+    assert(legalCaptures(position, PieceType::PAWN).empty());
+
+    // Promotions are included, and en passant captures a pawn
+    position = fen::parsePosition("1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    assert(names(legalMovesTo(position, "b8"_sq)) == Moves(4, "a7b8"));
+    assert(names(legalCaptures(position, PieceType::PAWN)) == Moves({"e5d6"}));
+    assert(legalCaptures(position, PieceType::KNIGHT).size() == 4);
+
+    // Together, the moves from all squares are all legal moves
+    std::mt19937 gen(17);
+    for (int game = 0; game < 20; ++game) {
+        auto position = testkit::randomPosition(gen, 40);
+        size_t from = 0, to = 0;
+        for (Square square = 0; square != kNumSquares; ++square) {
+            from += legalMovesFrom(position, square).size();
+            to += legalMovesTo(position, square).size();
+        }
+        assert(from == allLegalMoves(position).size());
+        assert(to == from);
+    }
+
+    std::cout << "All legal move query tests passed!" << std::endl;
+}
+
+uint64_t countMoves(const Position& position, int depth) {
+    if (depth <= 0) return 1;
+    uint64_t count = 0;
+    for (auto& [move, newPosition] : allLegalMoves(position))
+        count += countMoves(newPosition, depth - 1);
+    return count;
+}
+
+bool isLegal(Position position, Variant variant, const std::string& move) {
+    position.variant = variant;
+    for (auto& [legal, newPosition] : allLegalMoves(position))
+        if (std::string(legal) == move) return true;
+    return false;
+}
+
+void testAtomic() {
+    // Captures explode the capturing piece and all adjacent pieces other than pawns
+    {
+        auto position = fen::parsePosition("4k3/8/5b2/3pn3/8/8/8/4R1K1 w - - 0 1");
+        position.variant = Variant::ATOMIC;
+        position = applyMove(position, Move("e1"_sq, "e5"_sq, MoveKind::CAPTURE));
+        assert(position.board["e1"_sq] == Piece::NONE && position.board["e5"_sq] == Piece::NONE);
+        assert(position.board["f6"_sq] == Piece::NONE);
+        assert(position.board["d5"_sq] == Piece::BLACK_PAWN);
+        assert(position.halfmoveClock == 0);
+    }
+
+    // Kings can't capture, and no move may explode the own king
+    auto kingCapture = fen::parsePosition("4k3/8/8/8/8/8/4p3/4K3 w - - 0 1");
+    assert(isLegal(kingCapture, Variant::STANDARD, "e1e2"));
+    assert(!isLegal(kingCapture, Variant::ATOMIC, "e1e2"));
+    auto suicide = fen::parsePosition("4k3/8/8/8/8/8/3n4/3QK3 w - - 0 1");
+    assert(isLegal(suicide, Variant::STANDARD, "d1d2"));
+    assert(!isLegal(suicide, Variant::ATOMIC, "d1d2"));
+
+    // Exploding the opponent's king wins, even when in check
+    {
+        auto position = fen::parsePosition("4k3/3n4/8/8/Q7/8/8/r3K3 w - - 0 1");
+        assert(!isLegal(position, Variant::STANDARD, "a4d7"));
+        assert(isLegal(position, Variant::ATOMIC, "a4d7"));
+        position.variant = Variant::ATOMIC;
+        position = applyMove(position, Move("a4"_sq, "d7"_sq, MoveKind::CAPTURE));
+        assert(isInCheck(position) && allLegalMoves(position).empty());
+    }
+
+    // Kings next to each other are never in check
+    auto adjacent = fen::parsePosition("8/8/8/8/8/3k4/r2K4/8 w - - 0 1");
+    assert(isInCheck(adjacent));
+    adjacent.variant = Variant::ATOMIC;
+    assert(!isInCheck(adjacent));
+
+    auto initial = fen::parsePosition(fen::initialPosition);
+    initial.variant = Variant::ATOMIC;
+    assert(countMoves(initial, 4) == 197326);
+
+    std::cout << "All atomic tests passed!" << std::endl;
+}
+
+void testChess960() {
+    using R = IllegalMoveReason;
+
+    // The king castles by moving onto its rook, and the two may swap squares
+    auto position = fen::parsePosition("4k3/8/8/8/8/8/8/1R3KR1 w GB - 0 1");
+    assert(position.variant == Variant::CHESS960);
+    assert(isLegal(position, Variant::CHESS960, "f1g1"));
+    assert(isLegal(position, Variant::CHESS960, "f1b1"));
+    auto kingSide = inferMove(position.board, "f1"_sq, "g1"_sq);
+    auto queenSide = inferMove(position.board, "f1"_sq, "b1"_sq);
+    assert(kingSide.kind == MoveKind::KING_CASTLE && queenSide.kind == MoveKind::QUEEN_CASTLE);
+    assert(fen::to_string(applyMove(position, kingSide)) == "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1");
+    assert(fen::to_string(applyMove(position, queenSide)) == "4k3/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+
+    // Moving a castling rook only cancels its own right
+    auto moved = applyMove(position, Move("g1"_sq, "g2"_sq, MoveKind::QUIET_MOVE));
+    assert(moved.castlingAvailability == CastlingMask::WHITE_QUEENSIDE);
+
+    // Rights castle with the rook on their start file, also when it isn't the outermost one
+    position = fen::parsePosition("4k3/8/8/8/8/8/8/RR2K3 w B - 0 1");
+    assert(isLegal(position, Variant::CHESS960, "e1b1"));
+    assert(!isLegal(position, Variant::CHESS960, "e1a1"));
+    queenSide = inferMove(position.board, "e1"_sq, "b1"_sq);
+    assert(fen::to_string(applyMove(position, queenSide)) == "4k3/8/8/8/8/8/8/R1KR4 b - - 1 1");
+
+    // Another rook moving onto the outer square doesn't take over the right
+    position = fen::parsePosition("4k3/8/8/8/8/8/7R/3K2R1 w G - 0 1");
+    position = applyMove(position, Move("h2"_sq, "h1"_sq, MoveKind::QUIET_MOVE));
+    position = applyMove(position, Move("e8"_sq, "e7"_sq, MoveKind::QUIET_MOVE));
+    assert(position.castlingAvailability == CastlingMask::WHITE_KINGSIDE);
+    kingSide = inferMove(position.board, "d1"_sq, "g1"_sq);
+    assert(fen::to_string(applyMove(position, kingSide)) == "8/4k3/8/8/8/8/8/5RKR b - - 3 2");
+    moved = applyMove(position, Move("g1"_sq, "g2"_sq, MoveKind::QUIET_MOVE));
+    assert(moved.castlingAvailability == CastlingMask::NONE);
+
+    // Editing the board keeps a right only while its own rook is in place
+    auto edited = fen::parsePosition("4k3/8/8/8/8/8/8/3K2RR w G - 0 1");
+    removePiece(edited, "g1"_sq);
+    assert(edited.castlingAvailability == CastlingMask::NONE);
+    assert(setCastling(edited, CastlingMask::WHITE_KINGSIDE));
+    assert(fen::to_string(edited, true) == "4k3/8/8/8/8/8/8/3K3R w H - 0 1");
+
+    // The squares the king and rook cross or end up on must be empty, and those of the king safe
+    auto check = [](const char* fen, Square from, Square to) {
+        return checkMove(fen::parsePosition(fen), Move(from, to, MoveKind::CAPTURE));
+    };
+    assert(check("4k3/8/8/8/8/8/8/1RN2KR1 w GB - 0 1", "f1"_sq, "b1"_sq) == R::PATH_BLOCKED);
+    assert(check("3rk3/8/8/8/8/8/8/1R3KR1 w GB - 0 1", "f1"_sq, "b1"_sq) ==
+           R::CASTLING_THROUGH_CHECK);
+    assert(check("4k3/8/8/8/8/8/8/1R3KR1 w G - 0 1", "f1"_sq, "b1"_sq) == R::NO_CASTLING_RIGHTS);
+
+    // The rook may shield the destination of the king, but moves away when castling
+    assert(check("4k3/8/8/8/8/8/8/rRK5 w B - 0 1", "c1"_sq, "b1"_sq) == R::CASTLING_THROUGH_CHECK);
+
+    // Perft results from https://www.chessprogramming.org/Chess960_Perft_Results
+    assert(countMoves(fen::parsePosition(
+                          "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9"),
+                      3) == 12189);
+    assert(countMoves(fen::parsePosition(
+                          "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9"),
+                      3) == 18002);
+    assert(countMoves(fen::parsePosition(
+                          "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9"),
+                      3) == 10471);
+
+    // Standard castling is the same in chess960, apart from the notation
+    auto kiwipete = fen::parsePosition(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    kiwipete.variant = Variant::CHESS960;
+    assert(countMoves(kiwipete, 3) == 97862);
+    assert(countMoves(fen::chess960Position(fen::kStandardChess960Position), 3) == 8902);
+
+    // Rights for different rooks hash differently, but standard rook files hash as usual
+    auto hash = [](const char* fen) { return Hash(fen::parsePosition(fen))(); };
+    assert(hash("rr2k2r/8/8/8/8/8/8/RR2K2R w HBb - 0 1") !=
+           hash("rr2k2r/8/8/8/8/8/8/RR2K2R w HAb - 0 1"));
+    assert(hash("rr2k2r/8/8/8/8/8/8/RR2K2R w HBb - 0 1") !=
+           hash("rr2k2r/8/8/8/8/8/8/RR2K2R w HBa - 0 1"));
+    assert(hash("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1") ==
+           hash("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+    assert(Hash(canonical(mirror(kiwipete), {true, true}))() == Hash(canonical(kiwipete))());
+
+    std::cout << "All chess960 tests passed!" << std::endl;
+}
+
+void testInvariants() {
+    std::mt19937 gen(42);
+    auto atomic = fen::parsePosition(fen::initialPosition);
+    atomic.variant = Variant::ATOMIC;
+    for (int game = 0; game < 100; ++game) {
+        auto start = game % 4 == 0   ? fen::chess960Position(game * 9)
+                     : game % 4 == 1 ? atomic
+                                     : fen::parsePosition(fen::initialPosition);
+        for (int plies : {0, 10, 40, 100})
+            testkit::checkMoves(testkit::randomPosition(gen, plies, start));
+    }
+
+    std::cout << "All invariant tests passed!" << std::endl;
+}
+
 int main() {
     testSquare();
     testSquareSet();
     testPiece();
     testPieceType();
     testColor();
+    testCastlingMask();
     testPromotionKind();
     testPossibleMoves();
     testPossibleCaptures();
@@ -627,6 +1339,25 @@ int main() {
     testApplyMove();
     testIsAttacked();
     testAllLegalMoves();
+    testFilesAndRanks();
+    testSquareColors();
+    testTransforms();
+    testMasks();
+    testEnPassantTarget();
+    testMirror();
+    testCanonical();
+    testTranspositionTable();
+    testCheckMove();
+    testBoardEditor();
+    testCheckSquares();
+    testInferMove();
+    testAttackedSquares();
+    testAttackMap();
+    testPinsAndChecks();
+    testLegalMoveQueries();
+    testAtomic();
+    testChess960();
+    testInvariants();
     std::cout << "All move tests passed!" << std::endl;
     return 0;
 }
@@ -0,0 +1,38 @@
+#include <algorithm>
+#include <atomic>
+#include <thread>
+#include <type_traits>
+#include <vector>
+
+#pragma once
+
+/**
+ * Returns the results of calling fun on each of the items, in the order of the items, using the
+ * given number of threads, by default one per hardware thread. Threads take chunks of items from a
+ * shared counter, so items taking more work than others don't leave threads idle. As fun is called
+ * concurrently, it may not update shared state, like the node and evaluation counts of the search.
+ * Meant for processing corpora of millions of positions, as read by fen::parseMany.
+ */
+template <typename T, typename F>
+auto parallelMap(const std::vector<T>& items,
+                 const F& fun,
+                 unsigned threads = std::thread::hardware_concurrency()) {
+    using Result = std::decay_t<decltype(fun(items.front()))>;
+    static_assert(!std::is_same_v<Result, bool>, "Threads can't set elements of std::vector<bool>");
+    static constexpr size_t kChunkSize = 256;
+
+    std::vector<Result> results(items.size());
+    std::atomic<size_t> next = 0;
+    auto work = [&]() {
+        for (size_t begin; (begin = next.fetch_add(kChunkSize)) < items.size();)
+            for (size_t i = begin; i < std::min(begin + kChunkSize, items.size()); ++i)
+                results[i] = fun(items[i]);
+    };
+
+    threads = std::max<size_t>(1, std::min<size_t>(threads, items.size() / kChunkSize + 1));
+    std::vector<std::thread> workers;
+    for (unsigned i = 1; i < threads; ++i) workers.emplace_back(work);
+    work();
+    for (auto& worker : workers) worker.join();
+    return results;
+}
@@ -1,13 +1,65 @@
+#include <array>
 #include <chrono>
 #include <cstdlib>  // For std::exit
 #include <iostream>
+#include <optional>
 #include <string>
 
 #include "eval.h"
 #include "fen.h"
 #include "moves.h"
 
-void perftWithDivide(Position position, int depth, int expectedCount) {
+/**
+ * Counts of the moves at the final depth of a perft, by kind and by the type of the piece moved, as
+ * in the tables at https://www.chessprogramming.org/Perft_Results. Captures include en passants.
+ */
+struct PerftStats {
+    uint64_t nodes = 0;
+    uint64_t captures = 0;
+    uint64_t enPassants = 0;
+    uint64_t castles = 0;
+    uint64_t promotions = 0;
+    uint64_t checks = 0;
+    uint64_t checkmates = 0;
+    std::array<uint64_t, kNumPiecesTypes> pieces = {};
+};
+
+void perftStats(PerftStats& stats, const Position& position, int depth) {
+    for (auto& [move, newPosition] : allLegalMoves(position)) {
+        if (depth > 1) {
+            perftStats(stats, newPosition, depth - 1);
+            continue;
+        }
+        // Legal moves don't mark en passant captures or promotions that capture, so use the board
+        auto piece = type(position.board[move.from]);
+        bool enPassant = piece == PieceType::PAWN && position.board[move.to] == Piece::NONE &&
+            move.from.file() != move.to.file();
+        ++stats.nodes;
+        ++stats.pieces[index(piece)];
+        stats.captures += position.board[move.to] != Piece::NONE || enPassant;
+        stats.enPassants += enPassant;
+        stats.castles += move.kind == MoveKind::KING_CASTLE || move.kind == MoveKind::QUEEN_CASTLE;
+        stats.promotions += move.isPromotion();
+        if (!isInCheck(newPosition)) continue;
+        ++stats.checks;
+        stats.checkmates += allLegalMoves(newPosition).empty();
+    }
+}
+
+void printPerftStats(const Position& position, int depth) {
+    PerftStats stats;
+    perftStats(stats, position, depth);
+    std::cout << "Nodes: " << stats.nodes << ", captures: " << stats.captures
+              << ", en passants: " << stats.enPassants << ", castles: " << stats.castles
+              << ", promotions: " << stats.promotions << ", checks: " << stats.checks
+              << ", checkmates: " << stats.checkmates << std::endl;
+    std::cout << "Moves by piece:";
+    for (uint8_t type = 0; type < kNumPiecesTypes; ++type)
+        std::cout << " " << to_char(PieceType(type), Color::WHITE) << " " << stats.pieces[type];
+    std::cout << std::endl;
+}
+
+void perftWithDivide(Position position, int depth, int expectedCount, bool hashed) {
     struct Division {
         Move move;
         uint64_t count;
@@ -15,13 +67,20 @@ void perftWithDivide(Position position, int depth, int expectedCount) {
     std::vector<Division> divisions;
     std::cout << "Fen: " << fen::to_string(position) << std::endl;
 
+    // With hashing, the table is shared by the divisions and the total, so the latter is quick
+    std::optional<TranspositionTable<uint64_t>> table;
+    if (hashed) table.emplace(20);
+    auto countNodes = [&](const Position& position, int depth) {
+        return table ? perft(position, depth, *table) : perft(position, depth);
+    };
+
     auto startTime = std::chrono::high_resolution_clock::now();
     for (auto& [move, newPosition] : allLegalMoves(position)) {
-        auto count = perft(newPosition, depth - 1);
+        auto count = countNodes(newPosition, depth - 1);
         std::cout << static_cast<std::string>(move) << ": " << count << std::endl;
         divisions.push_back({move, count});
     }
-    auto count = perft(position, depth);
+    auto count = countNodes(position, depth);
     auto endTime = std::chrono::high_resolution_clock::now();
 
     auto duration = std::chrono::duration_cast<std::chrono::microseconds>(endTime - startTime);
@@ -30,7 +89,7 @@ void perftWithDivide(Position position, int depth, int expectedCount) {
     std::cout << count << " nodes in " << duration.count() / 1000 << " ms @ " << rate / 1000.0
               << "K nodes/sec" << std::endl;
 
-    if (expectedCount && count != expectedCount) {
+    if (expectedCount && count != uint64_t(expectedCount)) {
         std::cerr << "Expected " << expectedCount << " nodes, got " << count << std::endl;
         std::exit(1);
     }
@@ -49,12 +108,20 @@ bool maybeMove(const std::string& str) {
         str[2] >= 'a' && str[2] <= 'h' && str[3] >= '1' && str[3] <= '8';
 }
 
-Move parseMove(const std::string& str) {
-    return Move(
-        Square(str[1] - '1', str[0] - 'a'), Square(str[3] - '1', str[2] - 'a'), Move::QUIET);
+Move parseMove(const Board& board, const std::string& str) {
+    return inferMove(board, Square(str[1] - '1', str[0] - 'a'), Square(str[3] - '1', str[2] - 'a'));
 }
 
 int main(int argc, char** argv) {
+    // With --stats, also print counts by move kind and piece at the final depth. With --hash, cache
+    // the counts of transposed subtrees.
+    bool stats = false;
+    bool hashed = false;
+    while (argc >= 2 && (std::string(argv[1]) == "--stats" || std::string(argv[1]) == "--hash")) {
+        (std::string(argv[1]) == "--stats" ? stats : hashed) = true;
+        argv++;
+        argc--;
+    }
 
     std::vector<Position> positions;
 
@@ -67,7 +134,7 @@ int main(int argc, char** argv) {
             argc--;
         }
         while (argc >= 2 && maybeMove(argv[1])) {
-            Move move = parseMove(argv[1]);
+            Move move = parseMove(positions.back().board, argv[1]);
             auto pos = applyMove(positions.back(), move);
             std::cout << "applied move " << static_cast<std::string>(move) << std::endl;
             positions.pop_back();
@@ -82,13 +149,18 @@ int main(int argc, char** argv) {
     }
 
     if (argc < 2) {
-        std::cerr << "Usage: " << argv[0] << " <depth> [expected-count]" << std::endl;
-        std::cerr << "Usage: " << argv[0] << " {fen} <depth> [expected-count]" << std::endl;
+        std::cerr << "Usage: " << argv[0] << " [--stats] [--hash] <depth> [expected-count]"
+                  << std::endl;
+        std::cerr << "Usage: " << argv[0] << " [--stats] [--hash] {fen} <depth> [expected-count]"
+                  << std::endl;
         std::exit(1);
     }
 
     int depth = std::atoi(argv[1]);
     int expectedCount = argc > 2 ? std::atoi(argv[2]) : 0;
 
-    for (auto& position : positions) perftWithDivide(position, depth, expectedCount);
+    for (auto& position : positions) {
+        perftWithDivide(position, depth, expectedCount, hashed);
+        if (stats && depth > 0) printPerftStats(position, depth);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,315 @@
+#include <cctype>
+#include <iostream>
+#include <string>
+
+#include "fen.h"
+#include "pgn.h"
+
+namespace pgn {
+namespace {
+// Maximum line length of the exported movetext, per the PGN standard
+static constexpr size_t kMaxLineLength = 79;
+
+struct Token {
+    enum Kind { MOVE, NAG, COMMENT, OPEN, CLOSE } kind;
+    std::string text;
+    bool restOfLine = false;  // For comments
+};
+
+bool isResult(const std::string& token) {
+    return token == "1-0" || token == "0-1" || token == "1/2-1/2" || token == "*";
+}
+
+/**
+ * Reads a tag pair after the opening bracket, such as `Event "F/S Return Match"]`.
+ */
+std::pair<std::string, std::string> readTag(std::istream& in) {
+    std::string name, value;
+    char ch;
+    while (in.get(ch) && std::isspace(ch)) continue;
+    while (in && !std::isspace(ch) && ch != '"' && ch != ']') name += ch, in.get(ch);
+    while (in && ch != '"' && ch != ']') in.get(ch);
+    if (ch == '"') {
+        while (in.get(ch) && ch != '"') {
+            if (ch == '\\') in.get(ch);  // Escaped quote or backslash
+            value += ch;
+        }
+        while (in.get(ch) && ch != ']') continue;
+    }
+    return {name, value};
+}
+
+/**
+ * Returns true if the token, without its leading '$', is a numeric annotation glyph: a decimal
+ * number from 0 to 255.
+ */
+bool isNag(const std::string& str) {
+    if (str.empty() || str.size() > 3 || str.find_first_not_of("0123456789") != std::string::npos)
+        return false;
+    return std::stoi(str) <= 255;
+}
+
+/**
+ * Adds the line of moves starting at the given token to the parent node, until the end of the
+ * variation or the tokens. Moves are collected in a flat list first, and only linked into the
+ * tree at the end, so that no references into growing vectors are kept. Returns false if a NAG
+ * precedes the first move of the line, as there is no move for it to annotate.
+ */
+bool buildLine(const std::vector<Token>& tokens, size_t& i, GameNode& parent) {
+    std::vector<GameNode> line;
+    std::vector<std::vector<GameNode>> alternatives;  // Variations replacing the move in the line
+    std::vector<Comment> pending;                     // Comments preceding the next move
+
+    while (i < tokens.size()) {
+        auto& token = tokens[i++];
+        if (token.kind == Token::CLOSE) break;
+        switch (token.kind) {
+        case Token::MOVE:
+            line.emplace_back().move = token.text;
+            line.back().before = std::move(pending);
+            pending.clear();
+            alternatives.emplace_back();
+            break;
+        case Token::NAG:
+            if (line.empty()) return false;
+            line.back().nags.push_back(std::stoi(token.text));
+            break;
+        case Token::COMMENT:
+            (line.empty() ? pending : line.back().after).push_back({token.text, token.restOfLine});
+            break;
+        case Token::OPEN: {
+            GameNode variation;
+            if (!buildLine(tokens, i, variation)) return false;
+            if (line.empty()) break;  // Variation without a move to replace, so ignore it
+            for (auto& child : variation.children) alternatives.back().push_back(std::move(child));
+            break;
+        }
+        case Token::CLOSE: break;
+        }
+    }
+
+    // Comments without any moves following them, such as in a game without moves
+    for (auto& comment : pending) parent.after.push_back(std::move(comment));
+
+    for (size_t k = line.size(); k-- > 1;) {
+        line[k - 1].children.push_back(std::move(line[k]));
+        for (auto& alternative : alternatives[k])
+            line[k - 1].children.push_back(std::move(alternative));
+    }
+    if (line.empty()) return true;
+    parent.children.push_back(std::move(line[0]));
+    for (auto& alternative : alternatives[0]) parent.children.push_back(std::move(alternative));
+    return true;
+}
+
+/**
+ * Joins movetext tokens into lines of limited length. Rest-of-line comments end the line.
+ * Parentheses of variations are attached to the first and last token of the variation.
+ */
+class LineWriter {
+    std::ostream& out;
+    size_t column = 0;
+    std::string prefix;  // Opening parentheses to prepend to the next token
+
+public:
+    LineWriter(std::ostream& out) : out(out) {}
+    ~LineWriter() {
+        if (column) out << std::endl;
+    }
+
+    void write(const std::string& str, bool endOfLine = false) {
+        auto token = prefix + str;
+        prefix.clear();
+        if (column && column + 1 + token.size() > kMaxLineLength) out << std::endl, column = 0;
+        if (column) out << ' ', ++column;
+        out << token;
+        column += token.size();
+        if (endOfLine) out << std::endl, column = 0;
+    }
+
+    void openVariation() { prefix += '('; }
+    void closeVariation() {
+        out << ')';
+        ++column;
+    }
+
+    void write(const std::vector<Comment>& comments) {
+        for (auto& comment : comments) {
+            if (comment.restOfLine)
+                write(";" + comment.text, true);
+            else
+                write("{" + comment.text + "}");
+        }
+    }
+};
+
+/**
+ * Writes a move with its comments and annotations. Moves by white always have a move number,
+ * moves by black only at the start of a line, or after intervening comments or variations.
+ */
+void writeNode(LineWriter& writer, const GameNode& node, Color color, int number, bool needNumber) {
+    writer.write(node.before);
+    if (color == Color::WHITE)
+        writer.write(std::to_string(number) + ".");
+    else if (needNumber || !node.before.empty())
+        writer.write(std::to_string(number) + "...");
+    writer.write(node.move);
+    for (auto nag : node.nags) writer.write("$" + std::to_string(nag));
+    writer.write(node.after);
+}
+
+/**
+ * Writes the line continuing from the given node, with the variations for each move.
+ */
+void writeLine(LineWriter& writer, const GameNode* node, Color color, int number, bool needNumber) {
+    while (!node->children.empty()) {
+        auto& main = node->children.front();
+        writeNode(writer, main, color, number, needNumber);
+        needNumber = !main.after.empty();
+
+        auto nextColor = !color;
+        auto nextNumber = color == Color::BLACK ? number + 1 : number;
+        for (size_t i = 1; i < node->children.size(); ++i) {
+            auto& variation = node->children[i];
+            writer.openVariation();
+            writeNode(writer, variation, color, number, true);
+            writeLine(writer, &variation, nextColor, nextNumber, !variation.after.empty());
+            writer.closeVariation();
+            needNumber = true;
+        }
+
+        node = &main;
+        color = nextColor;
+        number = nextNumber;
+    }
+}
+
+std::string escape(const std::string& value) {
+    std::string escaped;
+    for (char ch : value) {
+        if (ch == '"' || ch == '\\') escaped += '\\';
+        escaped += ch;
+    }
+    return escaped;
+}
+}  // namespace
+
+std::string Game::tag(const std::string& name) const {
+    for (auto& [tagName, value] : tags)
+        if (tagName == name) return value;
+    return "";
+}
+
+Position Game::startPosition() const {
+    auto fen = tag("FEN");
+    return fen::parsePosition(fen.empty() ? fen::initialPosition : fen);
+}
+
+std::vector<std::string> Game::mainLine() const {
+    std::vector<std::string> moves;
+    for (auto node = &root; !node->children.empty(); node = &node->children.front())
+        moves.push_back(node->children.front().move);
+    return moves;
+}
+
+ReadResult readGame(std::istream& in, Game& game) {
+    game = {};
+    bool found = false;
+    bool malformed = false;
+    int depth = 0;  // Nesting depth of recursive annotation variations
+    std::vector<Token> tokens;
+    std::string token;
+
+    // Processes the current token, returning true if it terminates the game.
+    auto endToken = [&]() {
+        auto str = std::move(token);
+        token.clear();
+        if (str.empty()) return false;
+        found = true;
+        if (depth == 0 && isResult(str)) return game.result = str, true;
+        if (str[0] == '$') {
+            if (!isNag(str.substr(1)))
+                malformed = true;  // Keep reading to the end of the game
+            else
+                tokens.push_back({Token::NAG, str.substr(1)});
+            return false;
+        }
+
+        // Strip move numbers, like "12." or "12...", which may be directly followed by a move.
+        auto start = str.find_first_not_of("0123456789");
+        if (start == std::string::npos) return false;  // Bare move number
+        if (str[start] == '.') str.erase(0, str.find_first_not_of('.', start));
+        if (!str.empty()) tokens.push_back({Token::MOVE, str});
+        return false;
+    };
+
+    char ch;
+    bool done = false;
+    while (!done && in.get(ch)) {
+        switch (ch) {
+        case '[':
+            if ((done = endToken())) break;
+            if (!tokens.empty()) {
+                in.unget();  // Next game, result was missing
+                done = true;
+                break;
+            }
+            game.tags.push_back(readTag(in));
+            found = true;
+            break;
+        case '{': {
+            if ((done = endToken())) break;
+            std::string text;
+            while (in.get(ch) && ch != '}') text += ch;
+            tokens.push_back({Token::COMMENT, text});
+            found = true;
+            break;
+        }
+        case ';': {
+            if ((done = endToken())) break;
+            std::string text;
+            while (in.get(ch) && ch != '\n') text += ch;
+            tokens.push_back({Token::COMMENT, text, true});
+            found = true;
+            break;
+        }
+        case '(':
+            if ((done = endToken())) break;
+            tokens.push_back({Token::OPEN, ""});
+            ++depth;
+            break;
+        case ')':
+            if ((done = endToken())) break;
+            if (depth > 0) tokens.push_back({Token::CLOSE, ""}), --depth;
+            break;
+        default:
+            if (!std::isspace(ch))
+                token += ch;
+            else
+                done = endToken();
+        }
+    }
+    if (!done) endToken();
+    if (!found) return ReadResult::END;
+    if (malformed) return ReadResult::MALFORMED;
+
+    size_t i = 0;
+    while (i < tokens.size())
+        if (!buildLine(tokens, i, game.root)) return ReadResult::MALFORMED;
+    return ReadResult::GAME;
+}
+
+void writeGame(std::ostream& out, const Game& game) {
+    for (auto& [name, value] : game.tags) out << "[" << name << " \"" << escape(value) << "\"]\n";
+    out << std::endl;
+
+    auto position = game.startPosition();
+    {
+        LineWriter writer(out);
+        writer.write(game.root.after);
+        writeLine(writer, &game.root, position.activeColor, position.fullmoveNumber, true);
+        writer.write(game.result);
+    }
+    out << std::endl;
+}
+}  // namespace pgn
@@ -0,0 +1,85 @@
+#include <iostream>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "common.h"
+
+#pragma once
+
+namespace pgn {
+/**
+ * A comment in the movetext, either a brace comment `{...}` or a rest-of-line comment `;...`.
+ */
+struct Comment {
+    std::string text;
+    bool restOfLine = false;
+};
+
+/**
+ * A node in the tree of moves of a game. The first child continues the current line, further
+ * children are variations (RAV) that are alternatives to the first child. The root node of a game
+ * has no move, so its children are the first move of the game and its alternatives.
+ */
+struct GameNode {
+    std::string move;                // The move in SAN, including any "!" or "?" suffixes
+    std::vector<int> nags;           // Numeric annotation glyphs following the move, like $1
+    std::vector<Comment> before;     // Comments preceding the move, at the start of a line
+    std::vector<Comment> after;      // Comments following the move
+    std::vector<GameNode> children;  // Continuation of the line, followed by variations
+};
+
+/**
+ * A game as read from a PGN file: the tag pairs in order of appearance, the tree of moves and the
+ * game termination marker ("1-0", "0-1", "1/2-1/2" or "*").
+ */
+struct Game {
+    std::vector<std::pair<std::string, std::string>> tags;
+    GameNode root;
+    std::string result = "*";
+
+    /**
+     * Returns the value of the named tag, or the empty string if there is no such tag.
+     */
+    std::string tag(const std::string& name) const;
+
+    /**
+     * Returns the position at the start of the game, taking the FEN tag into account.
+     */
+    Position startPosition() const;
+
+    /**
+     * Returns the moves of the main line in SAN notation, omitting any variations.
+     */
+    std::vector<std::string> mainLine() const;
+};
+
+/**
+ * The outcome of reading a game from a PGN input stream.
+ */
+enum class ReadResult {
+    END,        // No more games in the input
+    GAME,       // A game was read
+    MALFORMED,  // A game was skipped because its movetext is malformed
+};
+
+/**
+ * @brief Reads the next game from a PGN input stream, including comments, variations and
+ * numeric annotation glyphs.
+ *
+ * @param in The input stream to read from.
+ * @param game The game to fill in.
+ * @return GAME if a game was read, END at the end of the input, or MALFORMED if the movetext is
+ * malformed, such as a NAG that isn't a number from 0 to 255 or that doesn't follow a move. A
+ * malformed game is read up to its end, so reading can continue with the next game.
+ */
+ReadResult readGame(std::istream& in, Game& game);
+
+/**
+ * @brief Writes a game in PGN format, such that reading it back results in the same game.
+ *
+ * @param out The output stream to write to.
+ * @param game The game to write.
+ */
+void writeGame(std::ostream& out, const Game& game);
+}  // namespace pgn
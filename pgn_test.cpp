@@ -0,0 +1,166 @@
+#include <cassert>
+#include <iostream>
+#include <sstream>
+#include <string>
+
+#include "fen.h"
+#include "pgn.h"
+
+void testReadGame() {
+    std::stringstream in(R"([Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 (4. Bxc6 dxc6 (4... bxc6) 5. O-O) Nf6 $1 5. O-O ; The king is safe
+Be7 1/2-1/2
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]
+
+1.O-O Kd7 *
+)");
+    pgn::Game game;
+    assert(pgn::readGame(in, game) == pgn::ReadResult::GAME);
+    assert(game.tags.size() == 5);
+    assert(game.tag("White") == "Fischer, Robert J.");
+    assert(game.tag("Round") == "");
+    assert(game.result == "1/2-1/2");
+    std::vector<std::string> moves = {
+        "e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O", "Be7"};
+    assert(game.mainLine() == moves);
+    assert(fen::to_string(game.startPosition()) == fen::initialPosition);
+
+    assert(pgn::readGame(in, game) == pgn::ReadResult::GAME);
+    assert(game.tag("Event") == "Second");
+    assert(game.result == "*");
+    assert(game.mainLine() == std::vector<std::string>({"O-O", "Kd7"}));
+    assert(fen::to_string(game.startPosition()) == "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+
+    assert(pgn::readGame(in, game) == pgn::ReadResult::END);
+
+    std::cout << "All readGame tests passed!" << std::endl;
+}
+
+void testMissingResult() {
+    std::stringstream in("[Event \"One\"]\n1. d4\n\n[Event \"Two\"]\n1. c4 c5\n");
+    pgn::Game game;
+    assert(pgn::readGame(in, game) == pgn::ReadResult::GAME);
+    assert(game.tag("Event") == "One" && game.mainLine().size() == 1);
+    assert(pgn::readGame(in, game) == pgn::ReadResult::GAME);
+    assert(game.tag("Event") == "Two" && game.mainLine().size() == 2);
+    assert(pgn::readGame(in, game) == pgn::ReadResult::END);
+
+    std::cout << "All missing result tests passed!" << std::endl;
+}
+
+void testAnnotations() {
+    std::stringstream in(R"([Event "Annotated"]
+
+{Start} 1. e4 $1 e5 {Open game} 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) (2. Bc4)
+2... Nc6 ; Three knights?
+3. Bb5 a6!? 1-0
+)");
+    pgn::Game game;
+    assert(pgn::readGame(in, game) == pgn::ReadResult::GAME);
+    auto& root = game.root;
+    assert(root.children.size() == 1);
+    auto& e4 = root.children[0];
+    assert(e4.move == "e4" && e4.nags == std::vector<int>{1});
+    assert(e4.before.size() == 1 && e4.before[0].text == "Start");
+    auto& e5 = e4.children[0];
+    assert(e5.after.size() == 1 && e5.after[0].text == "Open game");
+    assert(e5.children.size() == 3);
+    assert(e5.children[1].move == "f4" && e5.children[2].move == "Bc4");
+    auto& exf4 = e5.children[1].children[0];
+    assert(exf4.move == "exf4" && exf4.children.size() == 1);
+    assert(e5.children[1].children.size() == 2 && e5.children[1].children[1].move == "d5");
+    auto& nc6 = e5.children[0].children[0];
+    assert(nc6.after.size() == 1 && nc6.after[0].restOfLine);
+    assert(nc6.after[0].text == " Three knights?");
+    assert(game.mainLine().back() == "a6!?");
+
+    // Writing and reading back results in the same game
+    std::stringstream out;
+    pgn::writeGame(out, game);
+    pgn::Game copy;
+    assert(pgn::readGame(out, copy) == pgn::ReadResult::GAME);
+    std::stringstream again;
+    pgn::writeGame(again, copy);
+    assert(again.str() == out.str());
+    assert(out.str() == R"([Event "Annotated"]
+
+{Start} 1. e4 $1 e5 {Open game} 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) (2. Bc4)
+2... Nc6 ; Three knights?
+3. Bb5 a6!? 1-0
+
+)");
+
+    std::cout << "All annotation tests passed!" << std::endl;
+}
+
+void testMalformedNags() {
+    for (auto movetext : {"1. e4 $ e5 *", "1. e4 $x e5 *", "1. e4 $256 *", "1. e4 $1234 *"}) {
+        std::stringstream in(movetext);
+        pgn::Game game;
+        assert(pgn::readGame(in, game) == pgn::ReadResult::MALFORMED);
+        assert(pgn::readGame(in, game) == pgn::ReadResult::END);
+    }
+
+    // A NAG must follow the move it annotates, also at the start of a variation
+    for (auto movetext : {"$1 1. e4 e5 *", "1. e4 ($2 1. d4) e5 *"}) {
+        std::stringstream in(movetext);
+        pgn::Game game;
+        assert(pgn::readGame(in, game) == pgn::ReadResult::MALFORMED);
+        assert(pgn::readGame(in, game) == pgn::ReadResult::END);
+    }
+
+    std::stringstream in("1. e4 $0 e5 $255 *");
+    pgn::Game game;
+    assert(pgn::readGame(in, game) == pgn::ReadResult::GAME);
+    assert(game.root.children[0].nags == std::vector<int>{0});
+    assert(game.root.children[0].children[0].nags == std::vector<int>{255});
+
+    // A malformed game is skipped as a whole, and reading continues with the next game
+    std::stringstream games("[Event \"One\"]\n1. e4 *\n\n[Event \"Two\"]\n1. d4 $999 d5 2. c4 *\n\n"
+                            "[Event \"Three\"]\n1. c4 *\n");
+    assert(pgn::readGame(games, game) == pgn::ReadResult::GAME && game.tag("Event") == "One");
+    assert(pgn::readGame(games, game) == pgn::ReadResult::MALFORMED);
+    assert(game.tag("Event") == "Two");
+    assert(pgn::readGame(games, game) == pgn::ReadResult::GAME && game.tag("Event") == "Three");
+    assert(pgn::readGame(games, game) == pgn::ReadResult::END);
+
+    std::cout << "All malformed NAG tests passed!" << std::endl;
+}
+
+void testWriteGame() {
+    pgn::Game game;
+    game.tags = {{"Event", "Quote \"me\""}, {"FEN", "4k3/8/8/8/8/8/8/4K2R b K - 0 30"}};
+    game.root.children.emplace_back().move = "Kd7";
+    game.root.children[0].children.emplace_back().move = "O-O";
+    std::stringstream out;
+    pgn::writeGame(out, game);
+    assert(out.str() == R"([Event "Quote \"me\""]
+[FEN "4k3/8/8/8/8/8/8/4K2R b K - 0 30"]
+
+30... Kd7 31. O-O *
+
+)");
+    pgn::Game copy;
+    assert(pgn::readGame(out, copy) == pgn::ReadResult::GAME);
+    assert(copy.tag("Event") == "Quote \"me\"");
+
+    std::cout << "All writeGame tests passed!" << std::endl;
+}
+
+int main() {
+    testReadGame();
+    testMissingResult();
+    testAnnotations();
+    testMalformedNags();
+    testWriteGame();
+    std::cout << "All PGN tests passed!" << std::endl;
+    return 0;
+}
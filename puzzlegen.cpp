@@ -0,0 +1,74 @@
+#include <cstdlib>  // For std::exit
+#include <iostream>
+#include <string>
+
+#include "eval.h"
+#include "fen.h"
+#include "moves.h"
+#include "pgn.h"
+#include "san.h"
+
+/**
+ * Returns the best move in the position if it is better than all alternatives by at least the
+ * given margin (in pawns), or no move otherwise. As mates evaluate far above any material
+ * advantage, a unique mating line always stands out.
+ */
+EvaluatedMove findStandoutMove(const Position& position, int depth, float margin) {
+    auto legalMoves = allLegalMoves(position);
+    if (legalMoves.size() < 2) return {};  // Forced moves don't make for puzzles
+
+    ComputedMoveVector moves;
+    moves.push_back({Move(), position});
+    EvaluatedMove best, second;
+    for (auto& computedMove : legalMoves) {
+        auto ourMove = evaluateMove(moves, computedMove, depth);
+        if (best < ourMove)
+            second = best, best = ourMove;
+        else if (second < ourMove)
+            second = ourMove;
+    }
+    return best.evaluation - second.evaluation >= margin ? best : EvaluatedMove();
+}
+
+void extractPuzzles(const pgn::Game& game, int depth, float margin) {
+    auto position = game.startPosition();
+    int ply = 0;
+    for (auto& token : game.mainLine()) {
+        auto bestMove = findStandoutMove(position, depth, margin);
+        if (bestMove.move) {
+            std::cout << fen::to_epd(position) << " bm " << san::to_string(position, bestMove.move)
+                      << "; id \"" << game.tag("White") << " - " << game.tag("Black") << ", "
+                      << game.tag("Date") << ", ply " << ply + 1 << "\";" << std::endl;
+        }
+
+        auto move = san::parseMove(position, token);
+        if (!move) {
+            std::cerr << "Illegal move \"" << token << "\" at ply " << ply + 1 << " in "
+                      << fen::to_string(position) << ", skipping rest of game" << std::endl;
+            return;
+        }
+        position = applyMove(position, move);
+        ++ply;
+    }
+}
+
+int main(int argc, char** argv) {
+    if (argc > 3) {
+        std::cerr << "Usage: " << argv[0] << " [search-depth] [margin] < games.pgn" << std::endl;
+        std::exit(1);
+    }
+    int depth = argc > 1 ? std::atoi(argv[1]) : 2;
+    float margin = argc > 2 ? std::atof(argv[2]) : 2.0f;
+
+    pgn::Game game;
+    int numMalformed = 0;
+    pgn::ReadResult read;
+    while ((read = pgn::readGame(std::cin, game)) != pgn::ReadResult::END)
+        if (read == pgn::ReadResult::MALFORMED)
+            ++numMalformed;
+        else
+            extractPuzzles(game, depth, margin);
+    if (numMalformed) std::cerr << numMalformed << " malformed games skipped" << std::endl;
+
+    return 0;
+}
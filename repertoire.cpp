@@ -0,0 +1,194 @@
+#include <algorithm>
+#include <random>
+#include <set>
+#include <sstream>
+#include <string>
+
+#include "fen.h"
+#include "moves.h"
+#include "repertoire.h"
+#include "san.h"
+
+namespace {
+/**
+ * Returns the key of the position, which ignores unusable en passant targets and the clocks.
+ */
+std::string key(Position position) {
+    normalizeEnPassant(position);
+    return fen::to_epd(position);
+}
+
+/**
+ * Returns the move in SAN, preceded by the move number, like "3. Nf3" or "3... Nc6".
+ */
+std::string numbered(const Position& position, Move move) {
+    auto dots = position.activeColor == Color::WHITE ? ". " : "... ";
+    return std::to_string(position.fullmoveNumber) + dots + san::to_string(position, move);
+}
+
+/**
+ * Returns true if the user is due to be asked in the position or any position following it in
+ * the repertoire. Positions already visited are skipped, so transpositions can't loop.
+ */
+bool hasDue(const Repertoire& repertoire,
+            const Schedule& schedule,
+            Color user,
+            const Position& position,
+            std::set<std::string>& visited) {
+    auto& moves = repertoire.moves(position);
+    if (moves.empty() || !visited.insert(key(position)).second) return false;
+    if (position.activeColor == user && schedule.due(position)) return true;
+    for (auto move : moves)
+        if (hasDue(repertoire, schedule, user, applyMove(position, move), visited)) return true;
+    return false;
+}
+
+/**
+ * Returns the repertoire moves in the position that lead to positions where the user is due.
+ */
+std::vector<Move> dueMoves(const Repertoire& repertoire,
+                           const Schedule& schedule,
+                           Color user,
+                           const Position& position) {
+    std::vector<Move> due;
+    for (auto move : repertoire.moves(position)) {
+        std::set<std::string> visited = {key(position)};
+        if (hasDue(repertoire, schedule, user, applyMove(position, move), visited))
+            due.push_back(move);
+    }
+    return due;
+}
+}  // namespace
+
+bool Repertoire::add(const pgn::Game& game) {
+    if (_moves.empty()) _start = game.startPosition();
+    return add(game.startPosition(), game.root);
+}
+
+bool Repertoire::add(Position position, const pgn::GameNode& node) {
+    bool legal = true;
+    for (auto& child : node.children) {
+        auto move = san::parseMove(position, child.move);
+        if (!move) {
+            legal = false;
+            continue;
+        }
+        auto& moves = _moves[key(position)];
+        if (std::find(moves.begin(), moves.end(), move) == moves.end()) moves.push_back(move);
+        legal &= add(applyMove(position, move), child);
+    }
+    return legal;
+}
+
+const std::vector<Move>& Repertoire::moves(const Position& position) const {
+    static const std::vector<Move> none;
+    auto it = _moves.find(key(position));
+    return it == _moves.end() ? none : it->second;
+}
+
+bool Schedule::due(const Position& position) const {
+    auto found = card(position);
+    return !found || found->due <= _session;
+}
+
+void Schedule::record(const Position& position, bool correct) {
+    auto& card = _cards[key(position)];
+    if (correct) {
+        ++card.correct;
+        card.box = std::min(card.box + 1, kMaxBox);
+        card.due = _session + (1 << (card.box - 1));
+    } else {
+        ++card.wrong;
+        card.box = 0;
+        card.due = _session + 1;
+    }
+}
+
+const Schedule::Card* Schedule::card(const Position& position) const {
+    auto it = _cards.find(key(position));
+    return it == _cards.end() ? nullptr : &it->second;
+}
+
+bool Schedule::read(std::istream& in) {
+    std::string line, word;
+    if (!std::getline(in, line)) return true;  // No statistics yet
+    std::stringstream header(line);
+    if (!(header >> word >> _session) || word != "session") return false;
+
+    while (std::getline(in, line)) {
+        if (line.empty()) continue;
+        std::stringstream ss(line);
+        std::string placement, color, castling, enPassant;
+        Card card;
+        if (!(ss >> placement >> color >> castling >> enPassant >> card.box >> card.due >>
+              card.correct >> card.wrong))
+            return false;
+        auto epd = placement + " " + color + " " + castling + " " + enPassant;
+        if (!fen::isValid(epd + " 0 1")) return false;
+        _cards[key(fen::parsePosition(epd + " 0 1"))] = card;
+    }
+    return true;
+}
+
+void Schedule::write(std::ostream& out) const {
+    out << "session " << _session << std::endl;
+    for (auto& [epd, card] : _cards) {
+        out << epd << " " << card.box << " " << card.due << " " << card.correct << " "
+            << card.wrong << std::endl;
+    }
+}
+
+DrillResult drill(const Repertoire& repertoire,
+                  Schedule& schedule,
+                  Color user,
+                  std::istream& in,
+                  std::ostream& out,
+                  int maxLines,
+                  uint32_t seed) {
+    DrillResult result;
+    std::mt19937 gen(seed);
+    for (; result.lines < maxLines; ++result.lines) {
+        auto position = repertoire.start();
+        std::set<std::string> visited;
+        if (!hasDue(repertoire, schedule, user, position, visited)) break;
+        out << "Line " << result.lines + 1 << std::endl;
+
+        while (!repertoire.moves(position).empty()) {
+            auto& moves = repertoire.moves(position);
+            Move move;
+            if (position.activeColor == user && schedule.due(position)) {
+                out << position.fullmoveNumber
+                    << (position.activeColor == Color::WHITE ? ". ?" : "... ?") << std::endl;
+                std::string answer;
+                do {
+                    if (!std::getline(in, answer)) return result;
+                    answer.erase(0, answer.find_first_not_of(" \t"));
+                    answer.erase(answer.find_last_not_of(" \t\r") + 1);
+                } while (answer.empty());
+
+                auto given = san::parseMove(position, answer);
+                bool correct = given && std::find(moves.begin(), moves.end(), given) != moves.end();
+                schedule.record(position, correct);
+                ++result.asked;
+                result.correct += correct;
+                if (correct) {
+                    move = given;
+                    out << "Correct: " << numbered(position, move) << std::endl;
+                } else {
+                    move = moves.front();
+                    out << "Wrong, expected " << numbered(position, move);
+                    for (auto other : moves)
+                        if (!(other == move)) out << " or " << san::to_string(position, other);
+                    out << std::endl;
+                }
+            } else {
+                auto due = dueMoves(repertoire, schedule, user, position);
+                if (due.empty()) break;  // Nothing left to ask in this line
+                move = due[std::uniform_int_distribution<size_t>(0, due.size() - 1)(gen)];
+                out << numbered(position, move) << std::endl;
+            }
+            position = applyMove(position, move);
+        }
+    }
+    return result;
+}
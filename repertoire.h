@@ -0,0 +1,113 @@
+#include <cstdint>
+#include <iostream>
+#include <map>
+#include <string>
+#include <vector>
+
+#include "common.h"
+#include "pgn.h"
+
+#pragma once
+
+/**
+ * An opening repertoire: the tree of moves prepared for both sides, as read from PGN games with
+ * their variations. Positions are looked up regardless of the move order reaching them, so lines
+ * that transpose share their continuations.
+ */
+class Repertoire {
+public:
+    /**
+     * Adds the moves of the game, including all variations. The first game added sets the start
+     * position. Returns false if the game has an illegal move, in which case the moves before it,
+     * and other variations, are still added.
+     */
+    bool add(const pgn::Game& game);
+
+    /**
+     * Returns the repertoire moves in the position, in the order they were first added, or an
+     * empty vector if the position is not in the repertoire.
+     */
+    const std::vector<Move>& moves(const Position& position) const;
+
+    const Position& start() const { return _start; }
+    size_t size() const { return _moves.size(); }
+
+private:
+    bool add(Position position, const pgn::GameNode& node);
+
+    Position _start;
+    std::map<std::string, std::vector<Move>> _moves;  // Keyed by EPD
+};
+
+/**
+ * Spaced repetition statistics for drilling a repertoire, using Leitner boxes. Each position where
+ * the user is to move has a card in a box. Answering correctly moves the card to the next box, and
+ * makes it due again after twice as many sessions as before, while a wrong answer puts it back in
+ * the first box, due the next session. Positions without a card are always due.
+ */
+class Schedule {
+public:
+    struct Card {
+        int box = 0;
+        int due = 0;  // First session in which the card is asked again
+        int correct = 0;
+        int wrong = 0;
+    };
+
+    static constexpr int kMaxBox = 10;
+
+    /**
+     * Starts a new session, making the cards due in it available again.
+     */
+    void nextSession() { ++_session; }
+    int session() const { return _session; }
+
+    bool due(const Position& position) const;
+    void record(const Position& position, bool correct);
+
+    /**
+     * Returns the card for the position, or nullptr if it was never asked.
+     */
+    const Card* card(const Position& position) const;
+
+    /**
+     * Reads the statistics, starting with a line with the session number, followed by one line per
+     * card with an EPD position, the box, the session it's due and the number of correct and wrong
+     * answers. Returns false if a line could not be parsed, in which case the cards read so far are
+     * kept.
+     */
+    bool read(std::istream& in);
+
+    /**
+     * Writes the statistics in the format accepted by read.
+     */
+    void write(std::ostream& out) const;
+
+    size_t size() const { return _cards.size(); }
+
+private:
+    int _session = 0;
+    std::map<std::string, Card> _cards;  // Keyed by EPD
+};
+
+struct DrillResult {
+    int lines = 0;    // Lines played through
+    int asked = 0;    // Positions the user was quizzed on
+    int correct = 0;  // Correct answers
+};
+
+/**
+ * Drills the repertoire for the user playing the given color. Each line starts from the start of
+ * the repertoire and plays the opponent's moves, chosen at random among those leading to positions
+ * that are due. In due positions, the user is asked for a move in SAN, which is correct if it is
+ * any of the repertoire moves, and otherwise the expected moves are shown. Other positions are
+ * played through with a repertoire move. Stops after the given number of lines, once nothing is due
+ * anymore, or at the end of the input.
+ */
+DrillResult drill(const Repertoire& repertoire,
+                  Schedule& schedule,
+                  Color user,
+                  std::istream& in,
+                  std::ostream& out,
+                  int maxLines,
+                  uint32_t seed = 0);
@@ -0,0 +1,121 @@
+#include <cassert>
+#include <iostream>
+#include <sstream>
+#include <string>
+
+#include "fen.h"
+#include "moves.h"
+#include "pgn.h"
+#include "repertoire.h"
+#include "san.h"
+
+Repertoire makeRepertoire(const std::string& movetext) {
+    std::stringstream in(movetext);
+    pgn::Game game;
+    Repertoire repertoire;
+    while (pgn::readGame(in, game) == pgn::ReadResult::GAME) repertoire.add(game);
+    return repertoire;
+}
+
+void testRepertoire() {
+    auto repertoire = makeRepertoire("1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 3. Bb5 *\n");
+    assert(repertoire.size() == 6);
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(repertoire.moves(position).size() == 1);
+
+    position = applyMove(position, san::parseMove(position, "e4"));
+    auto& replies = repertoire.moves(position);
+    assert(replies.size() == 2);
+    assert(replies[0] == san::parseMove(position, "e5"));
+    assert(replies[1] == san::parseMove(position, "c5"));
+
+    // Illegal moves end their line, but the moves before them are kept
+    std::stringstream in("1. d4 Ke7 *\n");
+    pgn::Game game;
+    assert(pgn::readGame(in, game) == pgn::ReadResult::GAME);
+    assert(!repertoire.add(game));
+    assert(repertoire.size() == 6);
+    assert(repertoire.moves(fen::parsePosition(fen::initialPosition)).size() == 2);
+
+    std::cout << "All repertoire tests passed!" << std::endl;
+}
+
+void testSchedule() {
+    Schedule schedule;
+    schedule.nextSession();
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(schedule.due(position) && !schedule.card(position));
+
+    // Correct answers double the interval, a wrong one resets it
+    schedule.record(position, true);
+    assert(schedule.card(position)->box == 1 && schedule.card(position)->due == 2);
+    assert(!schedule.due(position));
+    schedule.nextSession();
+    schedule.record(position, true);
+    assert(schedule.card(position)->box == 2 && schedule.card(position)->due == 4);
+    schedule.record(position, false);
+    auto card = *schedule.card(position);
+    assert(card.box == 0 && card.due == 3 && card.correct == 2 && card.wrong == 1);
+
+    // Statistics survive writing and reading back
+    std::stringstream out;
+    schedule.write(out);
+    Schedule copy;
+    std::stringstream in(out.str());
+    assert(copy.read(in));
+    assert(copy.session() == 2 && copy.size() == 1);
+    std::stringstream again;
+    copy.write(again);
+    assert(again.str() == out.str());
+
+    std::stringstream bad("session 1\nnot a card\n");
+    assert(!copy.read(bad));
+    std::stringstream empty;
+    assert(Schedule().read(empty));
+
+    std::cout << "All schedule tests passed!" << std::endl;
+}
+
+void testDrill() {
+    auto repertoire = makeRepertoire("1. e4 e5 2. Nf3 Nc6 3. Bb5 (3. Bc4) *\n");
+    Schedule schedule;
+    schedule.nextSession();
+
+    // Any repertoire move is correct, and wrong answers are corrected
+    std::stringstream in("e4\nNf4\n\nBc4\n"), out;
+    auto result = drill(repertoire, schedule, Color::WHITE, in, out, 10);
+    assert(result.lines == 1 && result.asked == 3 && result.correct == 2);
+    assert(out.str() ==
+           "Line 1\n1. ?\nCorrect: 1. e4\n1... e5\n2. ?\nWrong, expected 2. Nf3\n2... Nc6\n"
+           "3. ?\nCorrect: 3. Bc4\n");
+
+    // In the next session, all positions are due again
+    schedule.nextSession();
+    std::stringstream in2("e4\nNf3\nBb5\n"), out2;
+    result = drill(repertoire, schedule, Color::WHITE, in2, out2, 10);
+    assert(result.lines == 1 && result.asked == 3 && result.correct == 3);
+
+    // Then only the position answered wrongly before, after moves played through
+    schedule.nextSession();
+    std::stringstream in3("Nf3\n"), out3;
+    result = drill(repertoire, schedule, Color::WHITE, in3, out3, 10);
+    assert(result.lines == 1 && result.asked == 1 && result.correct == 1);
+    assert(out3.str() == "Line 1\n1. e4\n1... e5\n2. ?\nCorrect: 2. Nf3\n");
+
+    // Drilling as black, stopping at the end of the input
+    repertoire = makeRepertoire("1. e4 c5 (1... e5 2. Nf3 Nc6) *\n");
+    Schedule black;
+    std::stringstream in4("c5\n"), out4;
+    result = drill(repertoire, black, Color::BLACK, in4, out4, 10, 1);
+    assert(result.asked >= 1 && result.asked <= 2 && result.correct == 1);
+    assert(out4.str().find("1. e4\n1... ?\n") != std::string::npos);
+
+    std::cout << "All drill tests passed!" << std::endl;
+}
+
+int main() {
+    testRepertoire();
+    testSchedule();
+    testDrill();
+    return 0;
+}
@@ -0,0 +1,157 @@
+#include <string>
+#include <vector>
+
+#include "moves.h"
+#include "san.h"
+
+namespace san {
+namespace {
+bool isFile(char ch) {
+    return ch >= 'a' && ch < 'a' + kNumFiles;
+}
+
+bool isRank(char ch) {
+    return ch >= '1' && ch < '1' + kNumRanks;
+}
+
+bool isCapture(const Board& board, Move move) {
+    return board[move.to] != Piece::NONE ||
+        (type(board[move.from]) == PieceType::PAWN && move.from.file() != move.to.file());
+}
+
+/**
+ * Returns true if the legal move is the given move. The kind of the given move is only used to
+ * determine the promotion piece, so moves parsed from coordinate notation match as well.
+ */
+bool sameMove(Move legal, Move move) {
+    return legal.from == move.from && legal.to == move.to &&
+        legal.isPromotion() == move.isPromotion() &&
+        (!move.isPromotion() || promotionType(legal.kind) == promotionType(move.kind));
+}
+
+/**
+ * Returns the legal move given in UCI long algebraic notation, like "g1f3" or "e7e8q", or an
+ * invalid move if there is no such move.
+ */
+Move parseUCIMove(const Position& position, const std::string& uci) {
+    for (auto& [move, newPosition] : allLegalMoves(position)) {
+        auto str = std::string(move);
+        if (move.isPromotion()) str += to_char(promotionType(move.kind), Color::BLACK);
+        if (str == uci) return move;
+    }
+    return {};
+}
+}  // namespace
+
+Move parseMove(const Position& position, const std::string& san) {
+    // Strip check and mate indicators, as well as any annotations
+    auto str = san.substr(0, san.find_first_of("+#!?"));
+    auto legalMoves = allLegalMoves(position);
+
+    if (str == "O-O" || str == "0-0" || str == "O-O-O" || str == "0-0-0") {
+        auto kind = str.size() == 3 ? MoveKind::KING_CASTLE : MoveKind::QUEEN_CASTLE;
+        for (auto& [move, newPosition] : legalMoves)
+            if (move.kind == kind) return move;
+        return {};
+    }
+
+    auto pieceType = PieceType::PAWN;
+    if (!str.empty() && std::string("NBRQK").find(str.front()) != std::string::npos) {
+        pieceType = toPieceType(str.front());
+        str.erase(0, 1);
+    }
+
+    bool promotion = false;
+    auto promotionPiece = PieceType::QUEEN;
+    if (str.size() > 2 && std::string("NBRQ").find(str.back()) != std::string::npos) {
+        promotion = true;
+        promotionPiece = toPieceType(str.back());
+        str.pop_back();
+        if (str.back() == '=') str.pop_back();
+    }
+
+    if (str.size() < 2 || !isFile(str[str.size() - 2]) || !isRank(str.back())) return {};
+    Square to(str.back() - '1', str[str.size() - 2] - 'a');
+    str.resize(str.size() - 2);
+
+    // What remains is an optional file and/or rank for disambiguation, and a capture indicator
+    int fromFile = -1, fromRank = -1;
+    for (char ch : str) {
+        if (isFile(ch))
+            fromFile = ch - 'a';
+        else if (isRank(ch))
+            fromRank = ch - '1';
+        else if (ch != 'x')
+            return {};
+    }
+
+    Move found;
+    int count = 0;
+    for (auto& [move, newPosition] : legalMoves) {
+        if (move.to != to || type(position.board[move.from]) != pieceType) continue;
+        if (isCastling(position.board, move)) continue;  // Only written as O-O or O-O-O
+        if (fromFile >= 0 && move.from.file() != fromFile) continue;
+        if (fromRank >= 0 && move.from.rank() != fromRank) continue;
+        if (move.isPromotion() != promotion) continue;
+        if (promotion && promotionType(move.kind) != promotionPiece) continue;
+        found = move;
+        ++count;
+    }
+    return count == 1 ? found : Move();
+}
+
+std::string to_string(const Position& position, Move move) {
+    auto legalMoves = allLegalMoves(position);
+    auto piece = position.board[move.from];
+    std::string san;
+
+    if (isCastling(position.board, move)) {
+        san = move.to.file() > move.from.file() ? "O-O" : "O-O-O";
+    } else {
+        bool capture = isCapture(position.board, move);
+        if (type(piece) == PieceType::PAWN) {
+            if (capture) san += 'a' + move.from.file();
+        } else {
+            san += to_char(type(piece), Color::WHITE);
+
+            // Disambiguate between pieces of the same kind that can move to the same square
+            bool ambiguous = false, sameFile = false, sameRank = false;
+            for (auto& [other, newPosition] : legalMoves) {
+                if (other.to != move.to || other.from == move.from) continue;
+                if (position.board[other.from] != piece) continue;
+                ambiguous = true;
+                sameFile |= other.from.file() == move.from.file();
+                sameRank |= other.from.rank() == move.from.rank();
+            }
+            if (ambiguous && (!sameFile || sameRank)) san += 'a' + move.from.file();
+            if (sameFile) san += '1' + move.from.rank();
+        }
+        if (capture) san += 'x';
+        san += std::string(move.to);
+        if (move.isPromotion()) {
+            san += '=';
+            san += to_char(promotionType(move.kind), Color::WHITE);
+        }
+    }
+
+    for (auto& [legal, newPosition] : legalMoves) {
+        if (!sameMove(legal, move)) continue;
+        if (isInCheck(newPosition))
+            san += allLegalMoves(newPosition).empty() ? '#' : '+';
+        break;
+    }
+    return san;
+}
+
+size_t playMoves(Position& position, const std::vector<std::string>& moves) {
+    size_t played = 0;
+    for (auto& str : moves) {
+        auto move = parseMove(position, str);
+        if (!move) move = parseUCIMove(position, str);
+        if (!move) break;
+        position = applyMove(position, move);
+        ++played;
+    }
+    return played;
+}
+}  // namespace san
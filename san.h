@@ -0,0 +1,41 @@
+#include <string>
+#include <vector>
+
+#include "common.h"
+
+#pragma once
+
+namespace san {
+/**
+ * @brief Parses a move in Standard Algebraic Notation (SAN), such as "e4", "Nbd7", "exd6",
+ * "O-O-O" or "e8=Q+", in the context of the given position. Check and mate indicators as well as
+ * move annotations like "!?" are accepted but ignored.
+ *
+ * @param position The position in which the move is to be made.
+ * @param san The move in SAN notation.
+ * @return Move The legal move described, or an invalid Move if there is no such move or the
+ *         description is ambiguous.
+ */
+Move parseMove(const Position& position, const std::string& san);
+
+/**
+ * @brief Converts a legal move to Standard Algebraic Notation, with disambiguation where needed
+ * and a "+" or "#" suffix for moves that check or mate the opponent.
+ *
+ * @param position The position in which the move is to be made.
+ * @param move The move to convert, which must be legal in the position.
+ * @return std::string The SAN representation of the move.
+ */
+std::string to_string(const Position& position, Move move);
+
+/**
+ * @brief Plays a sequence of moves, each in SAN or in UCI long algebraic notation like "e7e8q",
+ * stopping at the first move that is not legal.
+ *
+ * @param position The starting position, updated to the position after the moves played.
+ * @param moves The moves to play, in either notation.
+ * @return size_t The number of moves played, which is the index of the failing move if it is
+ *         less than the number of moves.
+ */
+size_t playMoves(Position& position, const std::vector<std::string>& moves);
+}  // namespace san
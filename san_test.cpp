@@ -0,0 +1,125 @@
+#include <cassert>
+#include <iostream>
+#include <string>
+
+#include "fen.h"
+#include "moves.h"
+#include "san.h"
+
+void testParseMove() {
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(san::parseMove(position, "e4") == Move("e2"_sq, "e4"_sq, Move::QUIET));
+    assert(san::parseMove(position, "Nf3") == Move("g1"_sq, "f3"_sq, Move::QUIET));
+    assert(san::parseMove(position, "Nf3!?") == Move("g1"_sq, "f3"_sq, Move::QUIET));
+    assert(!san::parseMove(position, "e5"));
+    assert(!san::parseMove(position, "Nd4"));
+    assert(!san::parseMove(position, "O-O"));
+    assert(!san::parseMove(position, ""));
+    assert(!san::parseMove(position, "xyzzy"));
+
+    // Disambiguation by file and by rank
+    position = fen::parsePosition("4k3/8/8/8/8/8/R6R/R3K3 w - - 0 1");
+    assert(!san::parseMove(position, "Rd2"));
+    assert(san::parseMove(position, "Rhd2") == Move("h2"_sq, "d2"_sq, Move::QUIET));
+    assert(san::parseMove(position, "Rad2") == Move("a2"_sq, "d2"_sq, Move::QUIET));
+    assert(san::parseMove(position, "R1a2") == Move());  // a2 is occupied by our own rook
+    assert(san::parseMove(position, "R2a3") == Move("a2"_sq, "a3"_sq, Move::QUIET));
+
+    // Castling
+    position = fen::parsePosition("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert(san::parseMove(position, "O-O") == Move("e1"_sq, "g1"_sq, MoveKind::KING_CASTLE));
+    assert(san::parseMove(position, "0-0-0").kind == MoveKind::QUEEN_CASTLE);
+    assert(!san::parseMove(position, "Kg1"));  // Castling is only written as such
+
+    // In chess960, the king castles by moving onto its rook
+    position = fen::parsePosition("4k3/8/8/8/8/8/8/1R3KR1 w GB - 0 1");
+    assert(san::parseMove(position, "O-O") == Move("f1"_sq, "g1"_sq, MoveKind::KING_CASTLE));
+    assert(san::parseMove(position, "O-O-O") == Move("f1"_sq, "b1"_sq, MoveKind::QUEEN_CASTLE));
+    assert(!san::parseMove(position, "Kxg1"));
+
+    // Promotions and en passant
+    position = fen::parsePosition("1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    assert(san::parseMove(position, "a8=Q").kind == MoveKind::QUEEN_PROMOTION);
+    assert(san::parseMove(position, "a8N").kind == MoveKind::KNIGHT_PROMOTION);
+    assert(san::parseMove(position, "axb8=R+").isPromotion());
+    assert(!san::parseMove(position, "a8"));
+    assert(san::parseMove(position, "exd6").to == "d6"_sq);
+
+    std::cout << "All parseMove tests passed!" << std::endl;
+}
+
+void testToString() {
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(san::to_string(position, Move("e2"_sq, "e4"_sq, Move::QUIET)) == "e4");
+    assert(san::to_string(position, Move("b1"_sq, "c3"_sq, Move::QUIET)) == "Nc3");
+
+    position = fen::parsePosition("4k3/8/8/8/8/8/R6R/R3K3 w - - 0 1");
+    assert(san::to_string(position, Move("h2"_sq, "d2"_sq, Move::QUIET)) == "Rhd2");
+    assert(san::to_string(position, Move("a2"_sq, "a3"_sq, Move::QUIET)) == "Ra3");
+    assert(san::to_string(position, Move("h2"_sq, "h8"_sq, Move::QUIET)) == "Rh8+");
+
+    position = fen::parsePosition("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1");
+    assert(san::to_string(position, Move("a5"_sq, "a3"_sq, Move::QUIET)) == "R5a3");
+
+    position = fen::parsePosition("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+    assert(san::to_string(position, Move("e8"_sq, "c8"_sq, Move::QUIET)) == "O-O-O");
+    assert(san::to_string(position, Move("a8"_sq, "a1"_sq, Move::CAPTURE)) == "Rxa1+");
+
+    position = fen::parsePosition("1r3kr1/8/8/8/8/8/8/4K3 b gb - 0 1");
+    assert(san::to_string(position, Move("f8"_sq, "b8"_sq, MoveKind::QUEEN_CASTLE)) == "O-O-O");
+    assert(san::to_string(position, Move("f8"_sq, "g8"_sq, MoveKind::KING_CASTLE)) == "O-O");
+
+    position = fen::parsePosition("6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1");
+    assert(san::to_string(position, Move("e7"_sq, "g7"_sq, Move::QUIET)) == "Qg7#");
+
+    position = fen::parsePosition("1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    assert(san::to_string(position, Move("e5"_sq, "d6"_sq, MoveKind::EN_PASSANT)) == "exd6");
+    auto move = Move("a7"_sq, "b8"_sq, MoveKind::QUEEN_PROMOTION_CAPTURE);
+    assert(san::to_string(position, move) == "axb8=Q+");
+
+    std::cout << "All to_string tests passed!" << std::endl;
+}
+
+void testRoundTrip() {
+    for (auto fen : {fen::initialPosition,
+                     "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                     "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"}) {
+        auto position = fen::parsePosition(fen);
+        for (auto& [move, newPosition] : allLegalMoves(position)) {
+            auto str = san::to_string(position, move);
+            auto parsed = san::parseMove(position, str);
+            assert(parsed.from == move.from && parsed.to == move.to && parsed.kind == move.kind);
+        }
+    }
+    std::cout << "All SAN round trip tests passed!" << std::endl;
+}
+
+void testPlayMoves() {
+    // Mixed notations, including a promotion in UCI notation
+    auto position = fen::parsePosition(fen::initialPosition);
+    assert(san::playMoves(position, {"e4", "d7d5", "exd5", "g8f6", "Bb5+", "c6"}) == 6);
+    assert(fen::to_string(position) ==
+           "rnbqkb1r/pp2pppp/2p2n2/1B1P4/8/8/PPPP1PPP/RNBQK1NR w KQkq - 0 4");
+
+    position = fen::parsePosition("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1");
+    assert(san::playMoves(position, {"e7e8n", "g7f7"}) == 2);
+    assert(position.board["e8"_sq] == Piece::WHITE_KNIGHT);
+
+    // Stops at the first illegal move, leaving the position before it
+    position = fen::parsePosition(fen::initialPosition);
+    assert(san::playMoves(position, {"e4", "e5", "Ke3", "Nf3"}) == 2);
+    assert(fen::to_string(position) ==
+           "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+    assert(san::playMoves(position, {"e2e4"}) == 0);
+
+    std::cout << "All playMoves tests passed!" << std::endl;
+}
+
+int main() {
+    testParseMove();
+    testToString();
+    testRoundTrip();
+    testPlayMoves();
+    std::cout << "All SAN tests passed!" << std::endl;
+    return 0;
+}
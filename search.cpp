@@ -0,0 +1,154 @@
+#include <algorithm>
+#include <cmath>
+
+#include "moves.h"
+#include "search.h"
+
+namespace search {
+Duration allocateTime(const GameClock& clock, Duration moveOverhead, const config::Params& params) {
+    auto movesToGo = clock.movesToGo > 0 ? clock.movesToGo : params.movesToGo;
+    auto usable = clock.time - std::min(kEmergencyReserve, clock.time / 10);
+    auto allocation = std::min(usable / movesToGo + clock.increment, usable);
+    return std::max(allocation - moveOverhead, Duration(0));
+}
+
+Duration maximumTime(const GameClock& clock, Duration moveOverhead, const config::Params& params) {
+    auto allocation = allocateTime(clock, moveOverhead, params);
+    auto usable = clock.time - std::min(kEmergencyReserve, clock.time / 10);
+    auto extended = params.maxTimeFactor * allocation;
+    return std::max(allocation, std::min(extended, usable / 5 - moveOverhead));
+}
+
+int mateDistance(const EvaluatedMove& move) {
+    // The depth of a mate is the number of plies until the mate, counting from 1
+    if (move.evaluation == bestEval) return (move.depth + 1) / 2;
+    if (move.evaluation == worstEval) return -(move.depth / 2);
+    return 0;
+}
+
+/**
+ * Like computeBestMove, but for the root moves in order, updating their evaluation and node count.
+ * Returns false if the search stopped before completing the iteration. Calls the current move
+ * function, if any, before searching each move.
+ */
+bool searchRoot(const Position& position,
+                std::vector<RootMove>& rootMoves,
+                int depth,
+                const CurrentMoveFunction& currentMove) {
+    ComputedMoveVector moves;
+    moves.push_back({Move(), position});
+    std::vector<RootMove> searched;
+    EvaluatedMove best;
+    for (auto& rootMove : rootMoves) {
+        if (currentMove) currentMove(rootMove.move.first, searched.size() + 1);
+        auto startNodes = nodeCount;
+        auto evaluated = evaluateMove(moves, rootMove.move, depth);
+        if (searchStopped()) return false;
+
+        searched.push_back(rootMove);
+        searched.back().evaluation = evaluated;
+        searched.back().nodes = nodeCount - startNodes;
+        searched.back().pv = {rootMove.move.first};
+        auto pv = principalVariation(2);
+        searched.back().pv.insert(searched.back().pv.end(), pv.begin(), pv.end());
+
+        if (best < evaluated) best = evaluated;
+        if (best.mate && best.check) break;  // Can't do better than mate
+    }
+
+    // Sort the best moves first, keeping the previous order for equal ones
+    std::copy(searched.begin(), searched.end(), rootMoves.begin());
+    std::stable_sort(rootMoves.begin(), rootMoves.end(), [](auto& lhs, auto& rhs) {
+        return rhs.evaluation < lhs.evaluation;
+    });
+    return true;
+}
+
+EvaluatedMove iterativeDeepening(const Position& position,
+                                 const Limits& limits,
+                                 const InfoFunction& info,
+                                 std::vector<Position> history) {
+    auto start = std::chrono::steady_clock::now();
+    auto elapsed = [start]() {
+        return std::chrono::duration_cast<Duration>(std::chrono::steady_clock::now() - start);
+    };
+
+    auto& params = limits.params;
+
+    // Time to search, depending on the stability of the best move
+    auto budget = limits.time;
+    auto startNodes = nodeCount;
+    int depth = 1;
+    auto lastProgress = Duration(0);
+    auto stop = [&]() {
+        if (limits.progress && elapsed() - lastProgress >= limits.progressInterval) {
+            lastProgress = elapsed();
+            limits.progress({depth, selDepth, nodeCount - startNodes, lastProgress});
+        }
+        return elapsed() >= std::min(budget, limits.moveTime) ||
+            nodeCount - startNodes >= limits.nodes || (limits.stop && limits.stop());
+    };
+
+    auto currentMove = [&](Move move, int number) {
+        if (limits.currentMove && elapsed() >= limits.currentMoveDelay)
+            limits.currentMove(move, number);
+    };
+
+    // A mate in N moves takes 2N - 1 plies, which is the depth of computeBestMove that finds it
+    auto maxDepth = limits.mate ? std::min(limits.depth, 2 * limits.mate - 1) : limits.depth;
+
+    std::vector<RootMove> rootMoves;
+    for (auto& computedMove : allLegalMoves(position)) {
+        auto& searchMoves = limits.searchMoves;
+        if (searchMoves.empty() ||
+            std::find(searchMoves.begin(), searchMoves.end(), computedMove.first) !=
+                searchMoves.end())
+            rootMoves.emplace_back(computedMove);
+    }
+    if (rootMoves.empty()) return {};
+
+    history.push_back(position);
+    setGameHistory(history);
+
+    EvaluatedMove best;
+    int stableIterations = 0;
+    setStopFunction({});  // Always complete the first iteration
+    selDepth = 0;
+    for (; depth <= std::clamp(maxDepth, 1, kMaxDepth); ++depth) {
+        if (!searchRoot(position, rootMoves, depth, currentMove)) break;
+
+        auto& bestRoot = rootMoves.front();
+        bool stable = best.move == bestRoot.evaluation.move &&
+            std::abs(best.evaluation - bestRoot.evaluation.evaluation) <= params.stableScoreMargin;
+        bool changed = best.move && best.move != bestRoot.evaluation.move;
+        bool failLow =
+            best.move && bestRoot.evaluation.evaluation < best.evaluation - params.failLowMargin;
+        stableIterations = stable ? stableIterations + 1 : 0;
+        best = bestRoot.evaluation;
+        if (info) info(bestRoot, depth, elapsed());
+        if (best.evaluation == bestEval || best.evaluation == worstEval) break;
+        bool timed = !limits.mate && limits.time != Duration::max();
+        if (timed && rootMoves.size() == 1) break;  // A forced move needs no further search
+
+        // Search longer when the best move changed or its score dropped, as it may be wrong
+        if (timed) {
+            auto factor = failLow ? params.failLowTimeFactor
+                : changed         ? params.unstableTimeFactor
+                                  : 1;
+            auto extended = Duration(int64_t(limits.time.count() * factor));
+            budget = std::clamp(extended, limits.time, std::max(limits.time, limits.maxTime));
+        }
+
+        // The next iteration takes much longer than this one, so don't start it if time is short,
+        // unless looking for a mate, as then only a complete iteration gives a useful result. When
+        // the best move has been stable for a while, it's unlikely to change, so stop even earlier.
+        auto timeShare = stableIterations >= params.stableIterations ? budget / 4 : budget / 2;
+        if (timed && elapsed() > timeShare) break;
+        if (stop()) break;
+        setStopFunction(stop);
+    }
+    setStopFunction({});
+    setGameHistory({});
+    return best;
+}
+}  // namespace search
@@ -0,0 +1,135 @@
+#include <chrono>
+#include <cstdint>
+#include <functional>
+
+#include "common.h"
+#include "config.h"
+#include "eval.h"
+#include "moves.h"
+
+#pragma once
+
+namespace search {
+using Duration = std::chrono::milliseconds;
+
+// Time lost per move to communication with the GUI, which is never used for thinking
+static constexpr Duration kDefaultMoveOverhead{30};
+// Time kept on the clock for emergencies, at most a tenth of the remaining time
+static constexpr Duration kEmergencyReserve{1000};
+// Time between progress reports during an iteration
+static constexpr Duration kDefaultInfoInterval{1000};
+// Time after which the root move being searched is reported
+static constexpr Duration kDefaultCurrentMoveDelay{3000};
+// Maximum depth for iterative deepening
+static constexpr int kMaxDepth = 64;
+
+/**
+ * The state of the clock of the side to move, as given by the UCI "go" command.
+ */
+struct GameClock {
+    Duration time{0};
+    Duration increment{0};
+    int movesToGo = 0;  // Moves until the next time control, or 0 for the rest of the game
+};
+
+/**
+ * Returns the time to spend on the next move: an equal share of the remaining time, not counting
+ * the emergency reserve, plus the increment. The move overhead is subtracted, so the time used
+ * including communication stays within the allocation.
+ */
+Duration allocateTime(const GameClock& clock,
+                      Duration moveOverhead = kDefaultMoveOverhead,
+                      const config::Params& params = {});
+
+/**
+ * Returns the most time to spend on the next move when the search is unstable: a multiple of the
+ * allocated time, but at most a fifth of the remaining time not counting the emergency reserve, and
+ * never less than the allocated time.
+ */
+Duration maximumTime(const GameClock& clock,
+                     Duration moveOverhead = kDefaultMoveOverhead,
+                     const config::Params& params = {});
+
+/**
+ * Statistics of a search in progress, as reported periodically during long iterations.
+ */
+struct Progress {
+    int depth;         // Depth of the iteration in progress
+    int selDepth;      // Maximum depth reached so far, including quiescence search
+    uint64_t nodes;    // Nodes searched so far
+    Duration elapsed;  // Time since the start of the search
+};
+using ProgressFunction = std::function<void(const Progress& progress)>;
+
+/**
+ * Called with the root move about to be searched, and its number in the search order, from 1.
+ */
+using CurrentMoveFunction = std::function<void(Move move, int number)>;
+
+/**
+ * Limits on the search. Node limits are checked along with the stop function of computeBestMove,
+ * so the search may exceed them by up to kPollInterval nodes. As the count doesn't depend on
+ * timing, searches with a node or depth limit are reproducible.
+ */
+struct Limits {
+    Duration time = Duration::max();      // Time allocated, the search may stop earlier
+    Duration maxTime{0};                  // Time the search may extend to when it's unstable
+    Duration moveTime = Duration::max();  // Exact time to search, iterating until it runs out
+    int depth = kMaxDepth;                // Maximum depth to search
+    uint64_t nodes = UINT64_MAX;          // Maximum number of nodes to search
+    int mate = 0;                         // Search for a mate in this many moves, if non-zero
+    MoveVector searchMoves;               // Legal moves to consider in the root, or empty for all
+    StopFunction stop;                    // Stops the search when true, like a user's request
+    config::Params params;                // Tuning of the search, see config.h
+
+    // Called at most once per interval after the first iteration, to report progress
+    ProgressFunction progress;
+    Duration progressInterval = kDefaultInfoInterval;
+
+    // Called before searching each root move, once the search has been running for the delay
+    CurrentMoveFunction currentMove;
+    Duration currentMoveDelay = kDefaultCurrentMoveDelay;
+};
+
+/**
+ * Returns the number of moves until mate for a move evaluated as mate, positive if the side to
+ * move mates and negative if it gets mated, or zero if the evaluation is not a mate.
+ */
+int mateDistance(const EvaluatedMove& move);
+
+/**
+ * A legal move in the root position, with the results of the last completed iteration that
+ * searched it. Root moves are searched best first, in the order of the previous iteration.
+ */
+struct RootMove {
+    ComputedMove move;         // The move and the position after it
+    EvaluatedMove evaluation;  // Evaluation of the move, worst if not searched yet
+    uint64_t nodes = 0;        // Nodes searched for the move
+    MoveVector pv;             // Principal variation, starting with the move
+
+    explicit RootMove(const ComputedMove& move)
+        : move(move), evaluation(move.first, false, false, worstEval, 0), pv{move.first} {}
+};
+
+/**
+ * Called after each completed iteration of the search with the best move found, the depth of the
+ * iteration and the time elapsed since the start of the search.
+ */
+using InfoFunction = std::function<void(const RootMove& best, int depth, Duration elapsed)>;
+
+/**
+ * Searches the position with increasing depth until a limit is reached or a mate is found. The
+ * first iteration always completes, so there is a move unless the game is over. An iteration is
+ * not started if it is unlikely to complete in the remaining time, or would exceed the depth limit.
+ * Once the best move is stable, iterations stop earlier, as more search is unlikely to change it,
+ * and with a single legal move, the first iteration is the last. When the best move changed or its
+ * score dropped, the time is extended up to the maximum time of the limits.
+ * When searching for a mate, the depth is limited to what is needed to find a mate in the given
+ * number of moves, and iterations are started as long as there is time left at all. Moves that
+ * repeat a position of the history, the positions of the game before this one, are draws.
+ */
+EvaluatedMove iterativeDeepening(const Position& position,
+                                 const Limits& limits,
+                                 const InfoFunction& info = {},
+                                 std::vector<Position> history = {});
+}  // namespace search
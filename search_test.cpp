@@ -0,0 +1,250 @@
+#include <algorithm>
+#include <cassert>
+#include <iostream>
+
+#include "fen.h"
+#include "search.h"
+
+using namespace std::chrono_literals;
+
+void testAllocateTime() {
+    // An equal share of the time, minus the reserve, plus the increment, minus the overhead
+    auto share = 59000ms / config::Params().movesToGo;
+    assert(search::allocateTime({60s, 0s, 0}, 0ms) == share);
+    assert(search::allocateTime({60s, 2s, 0}, 0ms) == share + 2s);
+    assert(search::allocateTime({60s, 2s, 0}, 100ms) == share + 1900ms);
+    assert(search::allocateTime({60s, 0s, 10}, 30ms) == 5900ms - 30ms);
+
+    // The reserve is at most a tenth of the time, and the allocation never exceeds the rest
+    assert(search::allocateTime({5s, 0s, 1}, 0ms) == 4500ms);
+    assert(search::allocateTime({1s, 10s, 0}, 50ms) == 850ms);
+
+    // With too little time, or too much overhead, there is nothing to allocate
+    assert(search::allocateTime({20ms, 0s, 1}, 30ms) == 0ms);
+    assert(search::allocateTime({0s, 0s, 0}, 0ms) == 0ms);
+
+    // Unstable searches may take a multiple of the allocation, up to a fifth of the usable time
+    assert(search::maximumTime({60s, 0s, 0}, 0ms) == config::Params().maxTimeFactor * share);
+    assert(search::maximumTime({60s, 0s, 2}, 0ms) == 59000ms / 2);
+    assert(search::maximumTime({60s, 0s, 10}, 30ms) == 59000ms / 5 - 30ms);
+    assert(search::maximumTime({0s, 0s, 0}, 0ms) == 0ms);
+
+    // Tuned parameters change the number of moves to plan for and the maximum factor
+    config::Params params;
+    params.movesToGo = 59;
+    params.maxTimeFactor = 2;
+    assert(search::allocateTime({60s, 0s, 0}, 0ms, params) == 1000ms);
+    assert(search::maximumTime({60s, 0s, 0}, 0ms, params) == 2000ms);
+
+    std::cout << "All allocateTime tests passed!" << std::endl;
+}
+
+void testIterativeDeepening() {
+    // Stops at the first mate found, and reports each iteration
+    auto position = fen::parsePosition("6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1");
+    int iterations = 0;
+    auto info = [&](const search::RootMove&, int depth, search::Duration) {
+        assert(depth == ++iterations);
+    };
+    auto best = search::iterativeDeepening(position, {}, info);
+    assert(std::string(best.move) == "e7g7" && best.evaluation == bestEval);
+    assert(iterations == 1);
+
+    // Respects the time limit, but always completes the first iteration
+    position = fen::parsePosition(fen::initialPosition);
+    auto start = std::chrono::steady_clock::now();
+    search::Limits limits;
+    limits.time = 1ms;
+    best = search::iterativeDeepening(position, limits);
+    assert(best.move);
+    limits.time = 200ms;
+    best = search::iterativeDeepening(position, limits);
+    assert(best.move && std::chrono::steady_clock::now() - start < 400ms);
+
+    // A forced move needs only one iteration, even with plenty of time
+    position = fen::parsePosition("k7/8/8/8/8/8/1r6/K7 w - - 0 1");
+    iterations = 0;
+    limits.time = 10s;
+    best = search::iterativeDeepening(position, limits, info);
+    assert(std::string(best.move) == "a1b2" && iterations == 1);
+
+    // No moves when the game is over
+    position = fen::parsePosition("k7/8/1Q6/8/8/8/8/7K b - - 0 1");
+    assert(!search::iterativeDeepening(position, {}).move);
+
+    std::cout << "All iterativeDeepening tests passed!" << std::endl;
+}
+
+void testDepthAndNodes() {
+    auto position = fen::parsePosition(fen::initialPosition);
+    int iterations = 0;
+    auto info = [&](const search::RootMove&, int depth, search::Duration) { iterations = depth; };
+    search::Limits limits;
+    limits.depth = 2;
+    assert(search::iterativeDeepening(position, limits, info).move && iterations == 2);
+    limits.depth = 0;  // The first iteration always completes
+    assert(search::iterativeDeepening(position, limits, info).move && iterations == 1);
+
+    // Node limited searches are reproducible
+    limits = {};
+    limits.nodes = 5000;
+    uint64_t searched[2];
+    EvaluatedMove best[2];
+    for (int i = 0; i < 2; ++i) {
+        clearHashTable();
+        auto startNodes = nodeCount;
+        best[i] = search::iterativeDeepening(position, limits, info);
+        searched[i] = nodeCount - startNodes;
+    }
+    assert(searched[0] == searched[1] && std::string(best[0]) == std::string(best[1]));
+    assert(searched[0] >= limits.nodes && searched[0] < limits.nodes + kPollInterval);
+    assert(iterations == 3);
+
+    std::cout << "All depth and nodes tests passed!" << std::endl;
+}
+
+void testRootMoves() {
+    // Reports the principal variation of the best move, for the full depth of the search
+    auto position = fen::parsePosition(fen::initialPosition);
+    search::Limits limits;
+    limits.depth = 3;
+    std::vector<std::string> pv;
+    uint64_t nodes = 0;
+    auto info = [&](const search::RootMove& best, int, search::Duration) {
+        pv.clear();
+        for (auto move : best.pv) pv.push_back(std::string(move));
+        nodes = best.nodes;
+        assert(best.pv.front() == best.evaluation.move && best.move.first == best.pv.front());
+    };
+    auto best = search::iterativeDeepening(position, limits, info);
+    assert(std::string(best.move) == pv.front() && pv.size() == 3 && nodes > 0);
+
+    // The variation is made of legal moves, also when the search uses cached results
+    limits.depth = 4;
+    best = search::iterativeDeepening(position, limits, info);
+    assert(std::string(best.move) == pv.front() && pv.size() == 4);
+    for (auto& uci : pv) {
+        auto moves = allLegalMoves(position);
+        auto it = std::find_if(moves.begin(), moves.end(), [&](auto& computedMove) {
+            return std::string(computedMove.first) == uci;
+        });
+        assert(it != moves.end());
+        position = it->second;
+    }
+
+    std::cout << "All root move tests passed!" << std::endl;
+}
+
+void testMate() {
+    // Mate in 2, but there is no mate in 1
+    auto position = fen::parsePosition("4r1k1/7p/2N1N1p1/3p4/3P4/P5B1/1q4PP/5R1K w - - 0 1");
+    search::Limits limits;
+    limits.mate = 1;
+    auto best = search::iterativeDeepening(position, limits);
+    assert(search::mateDistance(best) == 0);
+    limits.mate = 2;
+    best = search::iterativeDeepening(position, limits);
+    assert(std::string(best.move) == "c6e7" && search::mateDistance(best) == 2);
+
+    // Mated in 1, after the only move
+    position = fen::parsePosition("7k/8/6K1/8/8/8/8/1Q6 b - - 0 1");
+    best = search::iterativeDeepening(position, {});
+    assert(best.move && search::mateDistance(best) == -1);
+
+    std::cout << "All mate tests passed!" << std::endl;
+}
+
+void testSearchMoves() {
+    // Qg7 mates, but isn't one of the moves searched
+    auto position = fen::parsePosition("6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1");
+    search::Limits limits;
+    limits.depth = 3;
+    for (auto& [move, newPosition] : allLegalMoves(position))
+        if (std::string(move) == "e7e1" || std::string(move) == "e7a3")
+            limits.searchMoves.push_back(move);
+    assert(limits.searchMoves.size() == 2);
+    auto best = search::iterativeDeepening(position, limits);
+    assert(std::string(best.move) == "e7e1" || std::string(best.move) == "e7a3");
+    assert(search::mateDistance(best) == 0);
+
+    limits.searchMoves.clear();
+    best = search::iterativeDeepening(position, limits);
+    assert(std::string(best.move) == "e7g7" && search::mateDistance(best) == 1);
+
+    std::cout << "All searchmoves tests passed!" << std::endl;
+}
+
+void testRepetition() {
+    // Black is a queen for a rook behind, so is happy to repeat the position by playing Rb8
+    std::vector<Position> history{fen::parsePosition("1r5k/8/8/8/8/8/8/3Q3K w - - 0 1")};
+    for (auto [from, to] : {std::pair{"d1"_sq, "d2"_sq}, {"b8"_sq, "b7"_sq}, {"d2"_sq, "d1"_sq}})
+        history.push_back(applyMove(history.back(), {from, to, Move::QUIET}));
+    auto position = history.back();
+    history.pop_back();
+
+    search::Limits limits;
+    limits.depth = 2;
+    auto best = search::iterativeDeepening(position, limits, {}, history);
+    assert(std::string(best.move) == "b7b8" && best.evaluation == drawEval);
+
+    // Without the history, there is no repetition
+    best = search::iterativeDeepening(position, limits);
+    assert(best.evaluation < drawEval);
+
+    std::cout << "All repetition tests passed!" << std::endl;
+}
+
+void testProgress() {
+    // Reports progress during iterations after the first, with captures searched past the depth
+    auto position = fen::parsePosition(fen::initialPosition);
+    search::Limits limits;
+    limits.depth = 3;
+    limits.progressInterval = 0ms;
+    int reports = 0;
+    uint64_t nodes = 0;
+    limits.progress = [&](const search::Progress& progress) {
+        assert(progress.depth >= 1 && progress.depth <= 3);
+        assert(progress.selDepth >= progress.depth && progress.nodes >= nodes);
+        nodes = progress.nodes;
+        ++reports;
+    };
+    search::iterativeDeepening(position, limits);
+    assert(reports > 0 && selDepth > 4);
+
+    std::cout << "All progress tests passed!" << std::endl;
+}
+
+void testCurrentMove() {
+    // Reports each root move in the order searched, but only after the delay
+    auto position = fen::parsePosition(fen::initialPosition);
+    search::Limits limits;
+    limits.depth = 2;
+    int calls = 0, last = 0;
+    limits.currentMove = [&](Move move, int number) {
+        assert(move && number == last % 20 + 1);
+        last = number;
+        ++calls;
+    };
+    search::iterativeDeepening(position, limits);
+    assert(calls == 0);
+
+    limits.currentMoveDelay = 0ms;
+    search::iterativeDeepening(position, limits);
+    assert(calls == 40 && last == 20);
+
+    std::cout << "All current move tests passed!" << std::endl;
+}
+
+int main() {
+    testAllocateTime();
+    testIterativeDeepening();
+    testDepthAndNodes();
+    testRootMoves();
+    testMate();
+    testSearchMoves();
+    testRepetition();
+    testProgress();
+    testCurrentMove();
+    std::cout << "All search tests passed!" << std::endl;
+    return 0;
+}
@@ -0,0 +1,370 @@
+#include <algorithm>
+#include <array>
+#include <filesystem>
+#include <fstream>
+#include <map>
+
+#include "moves.h"
+#include "tablebase.h"
+
+namespace tablebase {
+namespace {
+// Piece types in the order of signatures
+static constexpr std::array<PieceType, kNumPiecesTypes> kSignatureOrder = {PieceType::KING,
+                                                                          PieceType::QUEEN,
+                                                                          PieceType::ROOK,
+                                                                          PieceType::BISHOP,
+                                                                          PieceType::KNIGHT,
+                                                                          PieceType::PAWN};
+
+// The symmetries of the board: bit 0 mirrors it horizontally, bit 1 flips it vertically, and bit 2
+// flips it in the a1-h8 diagonal. With pawns, only the horizontal mirror keeps positions the same.
+static constexpr size_t kNumTransforms = 8;
+static constexpr size_t kNumPawnTransforms = 2;
+using Transform = std::array<uint8_t, kNumSquares>;
+
+std::array<Transform, kNumTransforms> computeTransforms() {
+    std::array<Transform, kNumTransforms> transforms;
+    for (size_t transform = 0; transform < kNumTransforms; ++transform) {
+        for (int square = 0; square < kNumSquares; ++square) {
+            auto squares = SquareSet(Square(square));
+            if (transform & 1) squares = squares.mirrorHorizontal();
+            if (transform & 2) squares = squares.flipVertical();
+            if (transform & 4) squares = squares.flipDiagonal();
+            transforms[transform][square] = (*squares.begin()).index();
+        }
+    }
+    return transforms;
+}
+const auto transforms = computeTransforms();
+
+// The file format starts with this, followed by the signature and number of positions on one line
+static const std::string kMagic = "gbchess-wdl";
+
+std::map<std::string, Table> tables;  // Tables for probing, by signature
+
+/**
+ * Returns the signature with the colors swapped, so "KQvKR" becomes "KRvKQ".
+ */
+std::string swapColors(const std::string& signature) {
+    auto separator = signature.find('v');
+    return signature.substr(separator + 1) + "v" + signature.substr(0, separator);
+}
+
+/**
+ * Returns the pieces of the signature, in the order of the table index: the kings, then the other
+ * white pieces, and then the other black pieces, each in signature order.
+ */
+std::vector<Piece> signaturePieces(const std::string& signature) {
+    std::vector<Piece> pieces = {Piece::WHITE_KING, Piece::BLACK_KING};
+    auto color = Color::WHITE;
+    for (char c : signature) {
+        if (c == 'v')
+            color = Color::BLACK;
+        else if (c != 'K')
+            pieces.push_back(addColor(toPieceType(c), color));
+    }
+    return pieces;
+}
+
+/**
+ * Returns the positions from which the side that moved last could have reached the position,
+ * without capturing or promoting. Positions where the side to move would be in check are illegal,
+ * and excluded.
+ */
+std::vector<Position> predecessors(const Position& position) {
+    std::vector<Position> result;
+    auto& board = position.board;
+    auto mover = !position.activeColor;
+    auto occupied = SquareSet::occupancy(board);
+    auto king = *SquareSet::find(board, addColor(PieceType::KING, position.activeColor)).begin();
+    for (auto to : occupied) {
+        auto piece = board[to];
+        if (color(piece) != mover) continue;
+
+        SquareSet froms;
+        if (type(piece) == PieceType::PAWN) {
+            // Pawns move back a rank, or two from their fourth rank, but never to their first
+            int back = mover == Color::WHITE ? -1 : 1;
+            int fourthRank = mover == Color::WHITE ? 3 : kNumRanks - 4;
+            Square one(to.rank() + back, to.file());
+            bool onFirstRank = one.rank() == (mover == Color::WHITE ? 0 : kNumRanks - 1);
+            if (!occupied.contains(one) && !onFirstRank) {
+                froms.insert(one);
+                if (to.rank() == fourthRank) {
+                    Square two(one.rank() + back, one.file());
+                    if (!occupied.contains(two)) froms.insert(two);
+                }
+            }
+        } else {
+            for (auto from : possibleMoves(piece, to))
+                if (!occupied.contains(from) && (SquareSet::path(from, to) & occupied).empty())
+                    froms.insert(from);
+        }
+
+        for (auto from : froms) {
+            auto previous = position;
+            previous.board[from] = piece;
+            previous.board[to] = Piece::NONE;
+            previous.activeColor = mover;
+            // The side to move in the position can't have been in check before the move
+            if (!isAttacked(previous.board, king, mover)) result.push_back(previous);
+        }
+    }
+    return result;
+}
+}  // namespace
+
+std::string signature(const Board& board) {
+    std::array<std::string, 2> sides;
+    for (auto color : {Color::WHITE, Color::BLACK})
+        for (auto type : kSignatureOrder)
+            sides[int(color)].append(SquareSet::find(board, addColor(type, color)).size(),
+                                     to_char(type, Color::WHITE));
+    return sides[0] + "v" + sides[1];
+}
+
+bool isValidSignature(const std::string& signature) {
+    auto separator = signature.find('v');
+    if (separator == std::string::npos || signature.size() - 1 > kMaxPieces) return false;
+    if (signature == "KvK") return false;
+    if (std::count(signature.begin(), signature.end(), 'K') != 2) return false;
+
+    // The signature of a board with the pieces lists them in order, starting with the kings
+    Board board;
+    int square = 0;
+    auto side = Color::WHITE;
+    for (auto c : signature) {
+        auto piece = toPiece(c);
+        if (c == 'v')
+            side = Color::BLACK;
+        else if (piece == Piece::NONE || color(piece) != Color::WHITE)
+            return false;
+        else
+            board[Square(square++)] = addColor(type(piece), side);
+    }
+    return tablebase::signature(board) == signature;
+}
+
+Table::Table(const std::string& signature)
+    : _signature(signature), _pieces(signaturePieces(signature)) {
+    assert(isValidSignature(signature));
+    _pawns = signature.find('P') != std::string::npos;
+    _kingIndices.fill(-1);
+    for (int square = 0; square < kNumSquares; ++square) {
+        Square sq(square);
+        bool left = sq.file() < kNumFiles / 2;
+        if (!(_pawns ? left : left && sq.rank() <= sq.file())) continue;
+        _kingIndices[square] = _kingSquares.size();
+        _kingSquares.push_back(square);
+    }
+    _size = 2 * _kingSquares.size();
+    for (size_t piece = 1; piece < _pieces.size(); ++piece) _size *= kNumSquares;
+    _entries.resize((_size + 3) / 4);
+}
+
+size_t Table::index(const Position& position) const {
+    std::array<uint8_t, kMaxPieces> squares;
+    for (size_t piece = 0; piece < _pieces.size();) {
+        // Identical pieces are the next ones in the list, in order of their squares
+        for (auto square : SquareSet::find(position.board, _pieces[piece])) {
+            assert(piece < _pieces.size() && _pieces[piece] == position.board[square]);
+            squares[piece++] = square.index();
+        }
+    }
+
+    auto best = _size;
+    for (size_t transform = 0; transform < (_pawns ? kNumPawnTransforms : kNumTransforms);
+         ++transform) {
+        auto& to = transforms[transform];
+        auto king = _kingIndices[to[squares[0]]];
+        if (king < 0) continue;
+
+        std::array<uint8_t, kMaxPieces> transformed;
+        for (size_t piece = 0; piece < _pieces.size(); ++piece)
+            transformed[piece] = to[squares[piece]];
+        // With at most four pieces, at most two are identical, so a single swap orders them
+        for (size_t piece = 2; piece < _pieces.size(); ++piece)
+            if (_pieces[piece] == _pieces[piece - 1] && transformed[piece] < transformed[piece - 1])
+                std::swap(transformed[piece], transformed[piece - 1]);
+
+        size_t index = size_t(position.activeColor) * _kingSquares.size() + king;
+        for (size_t piece = 1; piece < _pieces.size(); ++piece)
+            index = index * kNumSquares + transformed[piece];
+        best = std::min(best, index);
+    }
+    return best;
+}
+
+std::optional<Position> Table::position(size_t index) const {
+    assert(index < _size);
+    Position position{};
+    position.fullmoveNumber = 1;
+    auto remaining = index;
+    for (size_t piece = _pieces.size() - 1; piece > 0; --piece) {
+        Square square(remaining % kNumSquares);
+        remaining /= kNumSquares;
+        bool backRank = square.rank() == 0 || square.rank() == kNumRanks - 1;
+        if (position.board[square] != Piece::NONE) return std::nullopt;
+        if (type(_pieces[piece]) == PieceType::PAWN && backRank) return std::nullopt;
+        position.board[square] = _pieces[piece];
+    }
+    Square king = _kingSquares[remaining % _kingSquares.size()];
+    if (position.board[king] != Piece::NONE) return std::nullopt;
+    position.board[king] = Piece::WHITE_KING;
+    position.activeColor = Color(remaining / _kingSquares.size());
+
+    // The side not to move can't be in check
+    auto opponentKing = addColor(PieceType::KING, !position.activeColor);
+    auto square = *SquareSet::find(position.board, opponentKing).begin();
+    if (isAttacked(position.board, square, position.activeColor)) return std::nullopt;
+
+    if (this->index(position) != index) return std::nullopt;
+    return position;
+}
+
+void Table::set(size_t index, WDL wdl) {
+    auto& entry = _entries[index / 4];
+    auto shift = index % 4 * 2;
+    entry = (entry & ~(3 << shift)) | uint8_t(wdl) << shift;
+}
+
+bool Table::read(std::istream& in) {
+    std::string magic, signature;
+    size_t size;
+    if (!(in >> magic >> signature >> size) || in.get() != '\n') return false;
+    if (magic != kMagic || signature != _signature || size != _size) return false;
+
+    std::vector<uint8_t> entries(_entries.size());
+    if (!in.read(reinterpret_cast<char*>(entries.data()), entries.size())) return false;
+    _entries = std::move(entries);
+    return true;
+}
+
+void Table::write(std::ostream& out) const {
+    out << kMagic << " " << _signature << " " << _size << "\n";
+    out.write(reinterpret_cast<const char*>(_entries.data()), _entries.size());
+}
+
+std::optional<Table> generate(const std::string& signature) {
+    if (!isValidSignature(signature)) return std::nullopt;
+    Table table(signature);
+
+    // Positions are unknown until proven won or lost, with the number of moves left that don't
+    // lose, not counting moves leaving the table
+    enum State : uint8_t { ILLEGAL, UNKNOWN, WON, LOST, DRAWN };
+    std::vector<State> states(table.size(), ILLEGAL);
+    std::vector<uint8_t> remaining(table.size(), 0);
+    std::vector<bool> canDraw(table.size(), false);  // By a move leaving the table
+    std::vector<size_t> resolved;  // Won and lost positions, in the order found
+
+    for (size_t index = 0; index < table.size(); ++index) {
+        auto position = table.position(index);
+        if (!position) continue;
+
+        auto moves = allLegalMoves(*position);
+        auto numPieces = SquareSet::occupancy(position->board).size();
+        bool win = false;
+        bool draw = false;
+        std::vector<size_t> successors;
+        for (auto& [move, next] : moves) {
+            if (!move.isPromotion() && SquareSet::occupancy(next.board).size() == numPieces) {
+                successors.push_back(table.index(next));
+                continue;
+            }
+            // Captures and promotions change the material, so find the outcome in another table
+            auto wdl = probe(next);
+            if (!wdl) return std::nullopt;
+            win |= *wdl == WDL::LOSS;
+            draw |= *wdl == WDL::DRAW;
+        }
+        // Moves to positions that are the same up to symmetry count only once, as when unmoving
+        std::sort(successors.begin(), successors.end());
+        successors.erase(std::unique(successors.begin(), successors.end()), successors.end());
+
+        if (moves.empty())
+            states[index] = isInCheck(*position) ? LOST : DRAWN;
+        else if (win)
+            states[index] = WON;
+        else if (successors.empty())
+            states[index] = draw ? DRAWN : LOST;
+        else
+            states[index] = UNKNOWN;
+        remaining[index] = successors.size();
+        canDraw[index] = draw;
+        if (states[index] == WON || states[index] == LOST) resolved.push_back(index);
+    }
+
+    // A position is won if a move reaches a lost position, and lost once all moves reach won ones
+    for (size_t next = 0; next < resolved.size(); ++next) {
+        auto index = resolved[next];
+        std::vector<size_t> previous;
+        for (auto& position : predecessors(*table.position(index)))
+            previous.push_back(table.index(position));
+        std::sort(previous.begin(), previous.end());
+        previous.erase(std::unique(previous.begin(), previous.end()), previous.end());
+
+        for (auto before : previous) {
+            if (states[before] != UNKNOWN) continue;
+            if (states[index] == LOST)
+                states[before] = WON;
+            else if (--remaining[before] == 0)
+                states[before] = canDraw[before] ? DRAWN : LOST;
+            if (states[before] != UNKNOWN && states[before] != DRAWN) resolved.push_back(before);
+        }
+    }
+
+    for (size_t index = 0; index < table.size(); ++index)
+        table.set(index, states[index] == WON ? WDL::WIN : states[index] == LOST ? WDL::LOSS
+                                                                                   : WDL::DRAW);
+    return table;
+}
+
+void add(Table table) {
+    auto signature = table.signature();
+    tables.insert_or_assign(signature, std::move(table));
+}
+
+void clear() {
+    tables.clear();
+}
+
+int load(const std::string& directory) {
+    int count = 0;
+    std::error_code error;
+    for (auto& entry : std::filesystem::directory_iterator(directory, error)) {
+        auto path = entry.path();
+        auto signature = path.stem().string();
+        if (path.extension() != ".wdl" || !isValidSignature(signature)) continue;
+        Table table(signature);
+        std::ifstream file(path, std::ios::binary);
+        if (!table.read(file)) continue;
+        add(std::move(table));
+        ++count;
+    }
+    return count;
+}
+
+std::optional<WDL> probe(const Position& position) {
+    // Without castling rights, chess960 positions play as in standard chess
+    if (position.variant == Variant::ATOMIC) return std::nullopt;
+    if (position.castlingAvailability != CastlingMask::NONE) return std::nullopt;
+    if (SquareSet::occupancy(position.board).size() > kMaxPieces) return std::nullopt;
+    if (position.enPassantTarget) {
+        auto normalized = position;
+        normalizeEnPassant(normalized);
+        if (normalized.enPassantTarget) return std::nullopt;
+    }
+
+    auto signature = tablebase::signature(position.board);
+    if (signature == "KvK") return WDL::DRAW;
+    if (auto it = tables.find(signature); it != tables.end())
+        return it->second[it->second.index(position)];
+    if (auto it = tables.find(swapColors(signature)); it != tables.end()) {
+        auto mirrored = mirror(position);
+        return it->second[it->second.index(mirrored)];
+    }
+    return std::nullopt;
+}
+
+}  // namespace tablebase
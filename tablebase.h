@@ -0,0 +1,123 @@
+#include <array>
+#include <cstdint>
+#include <iostream>
+#include <optional>
+#include <string>
+#include <vector>
+
+#include "common.h"
+
+#pragma once
+
+/**
+ * Endgame tablebases with the outcome, win, draw or loss (WDL), of every position with a few
+ * pieces, assuming perfect play. Tables are generated by retrograde analysis, see generate, and
+ * cover all positions with the same material, ignoring castling, en passant and the fifty-move
+ * rule. Positions that are the same up to a symmetry of the board share an entry: the white king
+ * is moved to the a1-d1-d4 triangle by flipping and mirroring the board, or only to the a-d files
+ * when there are pawns.
+ */
+namespace tablebase {
+
+static constexpr size_t kMaxPieces = 4;  // Including the kings
+
+/**
+ * The outcome with perfect play, from the perspective of the side to move.
+ */
+enum class WDL : uint8_t { DRAW, WIN, LOSS };
+
+/**
+ * Returns the material on the board, like "KQvKR", with the white pieces first, and each side
+ * listing the king followed by its other pieces from queen to pawn.
+ */
+std::string signature(const Board& board);
+
+/**
+ * Returns true if the signature is in the format above, has at most kMaxPieces pieces, and isn't
+ * just the kings, which are always a draw.
+ */
+bool isValidSignature(const std::string& signature);
+
+/**
+ * The outcomes of all positions with the material of a signature, stored in two bits per position.
+ * The index of a position consists of the side to move, the square of the white king after moving
+ * it to the part of the board given by the symmetries, and the squares of the other pieces, with
+ * identical pieces in order of their squares. The symmetry giving the lowest index is used.
+ */
+class Table {
+public:
+    /**
+     * Creates a table for the signature, which must be valid, with all positions drawn.
+     */
+    explicit Table(const std::string& signature);
+
+    const std::string& signature() const { return _signature; }
+    size_t size() const { return _size; }
+
+    /**
+     * Returns the index of the position, which must have the material of the table.
+     */
+    size_t index(const Position& position) const;
+
+    /**
+     * Returns the position with the index, or std::nullopt if it isn't legal, or another index is
+     * used for it, as for positions that aren't the result of the symmetry with the lowest index.
+     */
+    std::optional<Position> position(size_t index) const;
+
+    WDL operator[](size_t index) const {
+        return WDL(_entries[index / 4] >> (index % 4 * 2) & 3);
+    }
+    void set(size_t index, WDL wdl);
+
+    /**
+     * Reads a table written by write. Returns false, leaving the table unchanged, if the input isn't
+     * a table for the same signature.
+     */
+    bool read(std::istream& in);
+    void write(std::ostream& out) const;
+
+private:
+    std::string _signature;
+    std::vector<Piece> _pieces;         // In the order of the index, starting with the kings
+    bool _pawns;                        // Whether the table only uses the horizontal symmetry
+    std::vector<uint8_t> _kingSquares;  // Squares the white king is moved to, by index
+    std::array<int8_t, kNumSquares> _kingIndices;  // Index of each square, or -1 if not used
+    size_t _size;
+    std::vector<uint8_t> _entries;  // Four positions per byte
+};
+
+/**
+ * Generates the table for the signature by retrograde analysis: starting from the mates, and the
+ * positions where a capture or promotion reaches a won or lost ending, outcomes are propagated
+ * backward by unmoving pieces until nothing changes. The remaining positions are drawn. The tables
+ * for the endings reached by captures and promotions must have been added. Returns std::nullopt
+ * if the signature is invalid or any of these tables is missing.
+ */
+std::optional<Table> generate(const std::string& signature);
+
+/**
+ * Adds the table for probing, replacing any table with the same signature.
+ */
+void add(Table table);
+
+/**
+ * Removes all tables.
+ */
+void clear();
+
+/**
+ * Adds the tables in the directory, stored by write in files named after their signatures, like
+ * "KQvK.wdl". Returns the number of tables added.
+ */
+int load(const std::string& directory);
+
+/**
+ * Returns the outcome of the position, if there is a table for its material, or the material of
+ * the position with the colors swapped. Positions with only the kings are always drawn. Returns
+ * std::nullopt for positions with castling rights or a legal en passant capture, and for atomic
+ * chess.
+ */
+std::optional<WDL> probe(const Position& position);
+
+}  // namespace tablebase
@@ -0,0 +1,162 @@
+#include <cassert>
+#include <iostream>
+#include <random>
+#include <sstream>
+#include <string>
+
+#include "fen.h"
+#include "moves.h"
+#include "tablebase.h"
+
+using tablebase::WDL;
+
+namespace {
+std::optional<WDL> probe(const std::string& fen) {
+    return tablebase::probe(fen::parsePosition(fen));
+}
+
+/**
+ * Returns the outcome of the position found by looking one move ahead in the tables.
+ */
+WDL search(const Position& position) {
+    auto moves = allLegalMoves(position);
+    if (moves.empty()) return isInCheck(position) ? WDL::LOSS : WDL::DRAW;
+    auto best = WDL::LOSS;
+    for (auto& [move, next] : moves) {
+        auto wdl = *tablebase::probe(next);
+        if (wdl == WDL::LOSS) return WDL::WIN;
+        if (wdl == WDL::DRAW) best = WDL::DRAW;
+    }
+    return best;
+}
+}  // namespace
+
+void testSignature() {
+    auto board = fen::parsePosition("8/8/4k3/3r4/8/2QK4/8/8 w - - 0 1").board;
+    assert(tablebase::signature(board) == "KQvKR");
+    board = fen::parsePosition("8/3p4/4k3/8/8/2NKB3/8/8 w - - 0 1").board;
+    assert(tablebase::signature(board) == "KBNvKP");
+
+    for (auto valid : {"KQvK", "KvKP", "KRvKB", "KBNvK", "KPvKP"})
+        assert(tablebase::isValidSignature(valid));
+    for (auto invalid :
+         {"KvK", "KQ", "KNBvK", "KQvKR v", "KRRvKR", "KkvK", "QKvK", "KvQ", "KQvKQK"})
+        assert(!tablebase::isValidSignature(invalid));
+    std::cout << "All signature tests passed!" << std::endl;
+}
+
+void testIndex() {
+    // Positions that are the same up to symmetry share an index, others don't
+    tablebase::Table table("KRvKB");
+    auto position = fen::parsePosition("8/8/4k3/3b4/8/2RK4/8/8 w - - 0 1");
+    auto index = table.index(position);
+    auto mirrored = fen::parsePosition("8/8/3k4/4b3/8/4KR2/8/8 w - - 0 1");
+    auto flipped = fen::parsePosition("8/8/2RK4/8/3b4/4k3/8/8 w - - 0 1");
+    auto diagonal = fen::parsePosition("8/8/8/5k2/2K1b3/2R5/8/8 w - - 0 1");
+    assert(table.index(mirrored) == index && table.index(flipped) == index);
+    assert(table.index(diagonal) == index);
+    position.activeColor = Color::BLACK;
+    assert(table.index(position) != index);
+
+    // Pawns only allow mirroring the board horizontally
+    tablebase::Table pawns("KPvKP");
+    position = fen::parsePosition("8/8/4k3/3p4/8/2PK4/8/8 w - - 0 1");
+    index = pawns.index(position);
+    assert(pawns.index(fen::parsePosition("8/8/3k4/4p3/8/4KP2/8/8 w - - 0 1")) == index);
+    assert(pawns.index(fen::parsePosition("8/8/2PK4/8/3p4/4k3/8/8 w - - 0 1")) != index);
+
+    // Decoding an index gives a position with the same index, if it is legal and uses the index
+    std::mt19937 gen(42);
+    for (auto signature : {"KRvKB", "KBBvK", "KPvKP"}) {
+        tablebase::Table table(signature);
+        int legal = 0;
+        for (int i = 0; i < 10'000; ++i) {
+            auto index = std::uniform_int_distribution<size_t>(0, table.size() - 1)(gen);
+            auto position = table.position(index);
+            if (!position) continue;
+            ++legal;
+            assert(table.index(*position) == index);
+            assert(tablebase::signature(position->board) == signature);
+        }
+        assert(legal > 1'000);
+    }
+    std::cout << "All index tests passed!" << std::endl;
+}
+
+void testGenerate() {
+    tablebase::clear();
+    assert(!tablebase::generate("KPvK"));  // Promotions need the tables for the pieces
+    for (auto signature : {"KQvK", "KRvK", "KBvK", "KNvK", "KPvK"}) {
+        auto table = tablebase::generate(signature);
+        assert(table && table->signature() == signature);
+        tablebase::add(std::move(*table));
+    }
+
+    // All positions with a queen or rook are won for its side to move, unless it can be captured
+    // or black is stalemated
+    assert(probe("8/8/4k3/8/8/2QK4/8/8 w - - 0 1") == WDL::WIN);
+    assert(probe("8/8/4k3/8/8/2QK4/8/8 b - - 0 1") == WDL::LOSS);
+    assert(probe("8/8/8/8/8/8/1q6/K6k w - - 0 1") == WDL::DRAW);  // Capturing the queen
+    assert(probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1") == WDL::DRAW);  // Stalemate
+    assert(probe("k7/8/1K6/8/8/8/8/7R b - - 0 1") == WDL::LOSS);
+    assert(probe("k7/8/1K6/8/8/8/8/7r w - - 0 1") == WDL::LOSS);  // Colors swapped
+
+    // Lone minor pieces can't win
+    assert(probe("8/8/4k3/8/8/2BK4/8/8 w - - 0 1") == WDL::DRAW);
+    assert(probe("8/8/4k3/8/8/2nK4/8/8 b - - 0 1") == WDL::DRAW);
+
+    // With king and pawn versus king, it matters who has the opposition
+    assert(probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1") == WDL::WIN);
+    assert(probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1") == WDL::LOSS);
+    assert(probe("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1") == WDL::WIN);
+    assert(probe("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1") == WDL::DRAW);  // Stalemate
+    assert(probe("7k/8/6K1/7P/8/8/8/8 w - - 0 1") == WDL::DRAW);  // Rook pawn
+    assert(probe("8/8/8/8/4p3/8/8/4K2k w - - 0 1") == WDL::DRAW);
+    assert(probe("8/8/8/8/8/4k3/4p3/6K1 b - - 0 1") == WDL::WIN);  // Colors swapped
+
+    // The outcome of a position follows from the outcomes after each move
+    std::mt19937 gen(7);
+    tablebase::Table kpk("KPvK");
+    for (int i = 0; i < 2'000; ++i) {
+        auto index = std::uniform_int_distribution<size_t>(0, kpk.size() - 1)(gen);
+        if (auto position = kpk.position(index))
+            assert(tablebase::probe(*position) == search(*position));
+    }
+
+    // Only the kings is a draw, while unknown material, castling and en passant aren't probed
+    assert(probe("8/8/4k3/8/8/3K4/8/8 w - - 0 1") == WDL::DRAW);
+    assert(!probe("8/8/4k3/8/8/2RK2r1/8/8 w - - 0 1"));
+    assert(!probe("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"));
+    assert(!probe("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"));
+    assert(!probe(fen::initialPosition));
+    tablebase::clear();
+    std::cout << "All generate tests passed!" << std::endl;
+}
+
+void testReadWrite() {
+    auto table = *tablebase::generate("KQvK");
+    std::stringstream out;
+    table.write(out);
+
+    tablebase::Table copy("KQvK");
+    std::istringstream in(out.str());
+    assert(copy.read(in));
+    for (size_t index = 0; index < table.size(); ++index) assert(copy[index] == table[index]);
+
+    // Tables for other material, or truncated ones, are rejected
+    tablebase::Table other("KRvK");
+    std::istringstream wrong(out.str());
+    assert(!other.read(wrong));
+    std::istringstream truncated(out.str().substr(0, out.str().size() / 2));
+    assert(!copy.read(truncated));
+    std::cout << "All read/write tests passed!" << std::endl;
+}
+
+int main() {
+    testSignature();
+    testIndex();
+    testGenerate();
+    testReadWrite();
+    std::cout << "All tablebase tests passed!" << std::endl;
+    return 0;
+}
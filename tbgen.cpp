@@ -0,0 +1,69 @@
+#include <chrono>
+#include <cstdlib>  // For std::exit
+#include <filesystem>
+#include <fstream>
+#include <iostream>
+#include <string>
+#include <vector>
+
+#include "tablebase.h"
+
+/**
+ * Generates endgame tablebases, see tablebase.h, and writes them to the directory given as first
+ * argument, one file per signature, like "KQvK.wdl". Without further arguments, generates all
+ * 3-piece tables and a selection of 4-piece ones. Tables are generated in the order given, and each
+ * one can use the tables already in the directory, or generated before it, for the endings reached
+ * by captures and promotions.
+ */
+
+// All 3-piece endings, followed by the 4-piece endings that are most common in practice, with
+// white the stronger side, as tables are also used with the colors swapped. Each table only depends
+// on tables earlier in the list.
+static const std::vector<std::string> kDefaultSignatures = {
+    "KQvK",  "KRvK",  "KBvK",  "KNvK",  "KPvK",  "KQvKQ", "KQvKR", "KQvKB", "KQvKN",
+    "KRvKR", "KRvKB", "KRvKN", "KBBvK", "KBNvK", "KNNvK", "KQvKP", "KRvKP"};
+
+int main(int argc, char** argv) {
+    if (argc < 2) {
+        std::cerr << "Usage: " << argv[0] << " <directory> [signature...]" << std::endl;
+        std::exit(1);
+    }
+    std::filesystem::path directory = argv[1];
+    std::error_code error;
+    std::filesystem::create_directories(directory, error);
+    tablebase::load(directory.string());
+
+    auto signatures = argc > 2 ? std::vector<std::string>(argv + 2, argv + argc) : kDefaultSignatures;
+    for (auto& signature : signatures) {
+        auto start = std::chrono::steady_clock::now();
+        auto table = tablebase::generate(signature);
+        if (!table) {
+            std::cerr << "Cannot generate " << signature
+                      << ": invalid, or tables reached by captures or promotions are missing"
+                      << std::endl;
+            std::exit(1);
+        }
+        auto path = directory / (signature + ".wdl");
+        std::ofstream file(path, std::ios::binary);
+        table->write(file);
+        if (!file) {
+            std::cerr << "Cannot write " << path.string() << std::endl;
+            std::exit(1);
+        }
+
+        size_t wins = 0, draws = 0, losses = 0;
+        for (size_t index = 0; index < table->size(); ++index) {
+            auto wdl = (*table)[index];
+            wins += wdl == tablebase::WDL::WIN;
+            draws += wdl == tablebase::WDL::DRAW;
+            losses += wdl == tablebase::WDL::LOSS;
+        }
+        auto elapsed = std::chrono::duration_cast<std::chrono::milliseconds>(
+            std::chrono::steady_clock::now() - start);
+        std::cout << signature << ": " << table->size() << " positions, " << wins << " wins, "
+                  << draws << " draws, " << losses << " losses, " << elapsed.count() << " ms"
+                  << std::endl;
+        tablebase::add(std::move(*table));
+    }
+    return 0;
+}
@@ -0,0 +1,63 @@
+#include <cassert>
+#include <random>
+
+#include "common.h"
+#include "fen.h"
+#include "moves.h"
+
+#pragma once
+
+/**
+ * Helpers for tests that check properties holding in any position, rather than specific results.
+ * Tests generate positions with randomPosition and check them with the invariants below, which
+ * assert on failure. All randomness comes from the generator, so failures are reproducible.
+ */
+namespace testkit {
+
+/**
+ * Returns the position reached by playing up to the given number of random legal moves from the
+ * start position of the variant. Stops early if the game ends.
+ */
+inline Position randomPosition(std::mt19937& gen,
+                               int plies,
+                               const Position& start = fen::parsePosition(fen::initialPosition)) {
+    auto position = start;
+    for (int ply = 0; ply < plies; ++ply) {
+        auto moves = allLegalMoves(position);
+        if (moves.empty()) break;
+        position = moves[std::uniform_int_distribution<size_t>(0, moves.size() - 1)(gen)].second;
+    }
+    return position;
+}
+
+/**
+ * Checks that the position survives a round trip through FEN, and has a valid en passant target.
+ */
+inline void checkPosition(const Position& position) {
+    auto fen = fen::to_string(position);
+    assert(fen::to_string(fen::parsePosition(fen)) == fen);
+    assert(isValidEnPassantTarget(position));
+}
+
+/**
+ * Checks the moves generated for the position: each legal move results in the position that
+ * applyMove returns, with the same board as applying it to the board alone in standard chess, and
+ * doesn't leave the king of the side that moved in check. The resulting positions are checked with
+ * checkPosition as well.
+ */
+inline void checkMoves(const Position& position) {
+    checkPosition(position);
+    for (auto& [move, newPosition] : allLegalMoves(position)) {
+        assert(fen::to_string(applyMove(position, move)) == fen::to_string(newPosition));
+        if (position.variant == Variant::STANDARD) {
+            auto board = position.board;
+            applyMove(board, move);
+            assert(board == newPosition.board);
+        }
+        auto moved = newPosition;
+        moved.activeColor = position.activeColor;
+        assert(!isInCheck(moved));
+        checkPosition(newPosition);
+    }
+}
+}  // namespace testkit
@@ -0,0 +1,93 @@
+#include <cctype>
+
+#include "timecontrol.h"
+
+namespace {
+/**
+ * Parses a non-negative integer, advancing the position past it. Returns -1 if there is none.
+ */
+long parseNumber(const std::string& str, size_t& pos) {
+    if (pos >= str.size() || !std::isdigit(str[pos])) return -1;
+    long number = 0;
+    while (pos < str.size() && std::isdigit(str[pos]) && number < 1'000'000'000)
+        number = number * 10 + (str[pos++] - '0');
+    return number;
+}
+
+std::string seconds(TimeControl::Duration duration) {
+    return std::to_string(std::chrono::duration_cast<std::chrono::seconds>(duration).count());
+}
+}  // namespace
+
+bool TimeControl::parse(const std::string& str) {
+    if (str == "-") return _stages.clear(), true;
+
+    std::vector<Stage> stages;
+    size_t pos = 0;
+    while (true) {
+        Stage stage;
+        auto number = parseNumber(str, pos);
+        if (pos < str.size() && str[pos] == '/') {
+            stage.moves = number;
+            number = parseNumber(str, ++pos);
+            if (stage.moves <= 0) return false;
+        }
+        if (number < 0) return false;
+        stage.time = std::chrono::seconds(number);
+
+        if (pos < str.size() && str[pos] == '+') {
+            auto increment = parseNumber(str, ++pos);
+            if (increment < 0) return false;
+            stage.increment = std::chrono::seconds(increment);
+        }
+        stages.push_back(stage);
+        if (pos == str.size()) break;
+
+        // Only the last stage can be for the rest of the game
+        if (str[pos++] != ':' || !stage.moves) return false;
+    }
+    _stages = std::move(stages);
+    return true;
+}
+
+std::string TimeControl::to_string() const {
+    if (unlimited()) return "-";
+    std::string str;
+    for (auto& stage : _stages) {
+        if (!str.empty()) str += ':';
+        if (stage.moves) str += std::to_string(stage.moves) + '/';
+        str += seconds(stage.time);
+        if (stage.increment.count()) str += '+' + seconds(stage.increment);
+    }
+    return str;
+}
+
+std::pair<size_t, int> TimeControl::locate(int moves) const {
+    size_t index = 0;
+    while (_stages[index].moves && moves >= _stages[index].moves) {
+        moves -= _stages[index].moves;
+        if (index + 1 < _stages.size()) ++index;  // Otherwise, the last stage repeats
+    }
+    return {index, moves};
+}
+
+const TimeControl::Stage& TimeControl::stage(int moves) const {
+    static const Stage kUnlimitedStage;
+    return unlimited() ? kUnlimitedStage : _stages[locate(moves).first];
+}
+
+int TimeControl::movesToGo(int moves) const {
+    if (unlimited()) return 0;
+    auto [index, made] = locate(moves);
+    return _stages[index].moves ? _stages[index].moves - made : 0;
+}
+
+TimeControl::Duration TimeControl::timeAdded(int move) const {
+    if (unlimited()) return Duration(0);
+    auto [index, made] = locate(move - 1);
+    auto& stage = _stages[index];
+    auto added = stage.increment;
+    if (stage.moves && made + 1 == stage.moves)
+        added += _stages[index + 1 < _stages.size() ? index + 1 : index].time;
+    return added;
+}
@@ -0,0 +1,73 @@
+#include <chrono>
+#include <string>
+#include <utility>
+#include <vector>
+
+#pragma once
+
+/**
+ * A time control consisting of one or more stages, each giving time for a number of moves or for
+ * the rest of the game, with an optional increment per move. The last stage repeats if it is for
+ * a number of moves, as in "40/7200". Without any stages the time is unlimited.
+ */
+class TimeControl {
+public:
+    using Duration = std::chrono::milliseconds;
+
+    struct Stage {
+        int moves = 0;  // Moves to make in the time of the stage, or 0 for the rest of the game
+        Duration time{0};
+        Duration increment{0};
+    };
+
+    TimeControl() = default;
+    TimeControl(std::vector<Stage> stages) : _stages(std::move(stages)) {}
+
+    /**
+     * Parses a time control in the format of the PGN TimeControl tag, with times in seconds:
+     * "-" for no time control, "300" for sudden death, "180+2" with increment, and stages for a
+     * number of moves separated by colons, like "40/5400+30:1800+30". Returns false, leaving
+     * the time control unchanged, if the string is invalid or the time control is unknown ("?").
+     */
+    bool parse(const std::string& str);
+
+    /**
+     * Returns the time control in the format of the PGN TimeControl tag.
+     */
+    std::string to_string() const;
+
+    bool unlimited() const { return _stages.empty(); }
+    const std::vector<Stage>& stages() const { return _stages; }
+
+    /**
+     * Returns the time on the clock at the start of the game.
+     */
+    Duration initialTime() const { return unlimited() ? Duration(0) : _stages.front().time; }
+
+    /**
+     * Returns the stage in effect for a player that made the given number of moves. An unlimited
+     * time control has a single stage for the rest of the game, without any time or increment.
+     */
+    const Stage& stage(int moves) const;
+
+    /**
+     * Returns the number of moves to make before the next time control, after the given number
+     * of moves, or 0 if the current stage is for the rest of the game.
+     */
+    int movesToGo(int moves) const;
+
+    /**
+     * Returns the time added to the clock of the player upon completing the given move, counting
+     * from 1: the increment, plus the time of the next stage if the move completes a stage.
+     */
+    Duration timeAdded(int move) const;
+
+private:
+    /**
+     * Returns the index of the stage in effect after the given number of moves, and the number
+     * of moves already made in that stage.
+     */
+    std::pair<size_t, int> locate(int moves) const;
+
+    std::vector<Stage> _stages;
+};
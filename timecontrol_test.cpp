@@ -0,0 +1,65 @@
+#include <cassert>
+#include <iostream>
+
+#include "timecontrol.h"
+
+using namespace std::chrono_literals;
+
+void testParse() {
+    TimeControl tc;
+    assert(tc.parse("300") && tc.stages().size() == 1);
+    assert(tc.stages()[0].moves == 0 && tc.stages()[0].time == 300s);
+    assert(tc.parse("180+2") && tc.stages()[0].increment == 2s);
+    assert(tc.parse("40/5400+30:1800+30") && tc.stages().size() == 2);
+    assert(tc.stages()[0].moves == 40 && tc.stages()[0].time == 90min);
+    assert(tc.stages()[1].moves == 0 && tc.stages()[1].increment == 30s);
+    assert(tc.parse("-") && tc.unlimited());
+
+    for (auto str : {"", "?", "*180", "300:40/600", "40/", "/300", "0/300", "300+", "40/300x"})
+        assert(!tc.parse(str));
+    assert(tc.unlimited());
+
+    std::cout << "All parse tests passed!" << std::endl;
+}
+
+void testToString() {
+    for (auto str : {"-", "300", "180+2", "40/7200", "40/5400+30:1800+30", "40/7200:20/3600:900"}) {
+        TimeControl tc;
+        assert(tc.parse(str) && tc.to_string() == str);
+    }
+    std::cout << "All to_string tests passed!" << std::endl;
+}
+
+void testStages() {
+    TimeControl classical;
+    assert(classical.parse("40/5400+30:1800+30"));
+    assert(classical.initialTime() == 90min);
+    assert(classical.movesToGo(0) == 40 && classical.movesToGo(39) == 1);
+    assert(classical.movesToGo(40) == 0);
+    assert(classical.stage(39).moves == 40 && classical.stage(40).moves == 0);
+    assert(classical.timeAdded(1) == 30s && classical.timeAdded(39) == 30s);
+    assert(classical.timeAdded(40) == 30min + 30s);
+    assert(classical.timeAdded(41) == 30s);
+
+    // The last stage repeats when it is for a number of moves
+    TimeControl repeating(std::vector<TimeControl::Stage>{{40, 2h}});
+    assert(repeating.movesToGo(40) == 40 && repeating.movesToGo(95) == 25);
+    assert(repeating.timeAdded(40) == 2h && repeating.timeAdded(80) == 2h);
+    assert(repeating.timeAdded(41) == 0s);
+
+    TimeControl unlimited;
+    assert(unlimited.initialTime() == 0s && unlimited.movesToGo(10) == 0);
+    assert(unlimited.timeAdded(10) == 0s);
+    auto& stage = unlimited.stage(10);
+    assert(stage.moves == 0 && stage.time == 0s && stage.increment == 0s);
+
+    std::cout << "All stage tests passed!" << std::endl;
+}
+
+int main() {
+    testParse();
+    testToString();
+    testStages();
+    std::cout << "All TimeControl tests passed!" << std::endl;
+    return 0;
+}
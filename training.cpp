@@ -0,0 +1,70 @@
+#include <array>
+#include <istream>
+#include <optional>
+#include <ostream>
+#include <string>
+
+#include "training.h"
+
+namespace training {
+namespace {
+static constexpr uint8_t kNoEnPassant = 0xff;
+}  // namespace
+
+std::optional<Result> toResult(const std::string& result) {
+    if (result == "1-0") return Result::WHITE_WINS;
+    if (result == "0-1") return Result::BLACK_WINS;
+    if (result == "1/2-1/2") return Result::DRAW;
+    return std::nullopt;
+}
+
+void write(std::ostream& out, const Record& record) {
+    std::array<uint8_t, kRecordSize> bytes = {};
+    auto& position = record.position;
+    for (Square square = 0; square != kNumSquares; ++square)
+        bytes[square.index() / 2] |= index(position.board[square]) << square.index() % 2 * 4;
+
+    size_t i = 32;
+    bytes[i++] = uint8_t(position.activeColor) | uint8_t(position.castlingAvailability) << 1;
+    bytes[i++] = position.enPassantTarget ? position.enPassantTarget->index() : kNoEnPassant;
+    bytes[i++] = position.halfmoveClock;
+    bytes[i++] = position.fullmoveNumber & 0xff;
+    bytes[i++] = position.fullmoveNumber >> 8;
+    bytes[i++] = uint16_t(record.score) & 0xff;
+    bytes[i++] = uint16_t(record.score) >> 8;
+    bytes[i++] = uint8_t(record.result);
+    out.write(reinterpret_cast<const char*>(bytes.data()), bytes.size());
+}
+
+bool read(std::istream& in, Record& record) {
+    std::array<uint8_t, kRecordSize> bytes;
+    if (!in.read(reinterpret_cast<char*>(bytes.data()), bytes.size())) return false;
+
+    Record read;
+    auto& position = read.position;
+    for (Square square = 0; square != kNumSquares; ++square) {
+        auto piece = bytes[square.index() / 2] >> square.index() % 2 * 4 & 0xf;
+        if (piece >= kNumPieces) return false;
+        position.board[square] = Piece(piece);
+    }
+
+    size_t i = 32;
+    auto flags = bytes[i++];
+    if (flags >> 5) return false;
+    position.activeColor = Color(flags & 1);
+    position.castlingAvailability = CastlingMask(flags >> 1);
+    auto enPassant = bytes[i++];
+    if (enPassant != kNoEnPassant && enPassant >= kNumSquares) return false;
+    if (enPassant != kNoEnPassant) position.enPassantTarget = Square(enPassant);
+    position.halfmoveClock = bytes[i++];
+    position.fullmoveNumber = bytes[i] | bytes[i + 1] << 8;
+    i += 2;
+    read.score = int16_t(bytes[i] | bytes[i + 1] << 8);
+    i += 2;
+    if (bytes[i] > uint8_t(Result::WHITE_WINS)) return false;
+    read.result = Result(bytes[i]);
+
+    record = read;
+    return true;
+}
+}  // namespace training
@@ -0,0 +1,50 @@
+#include <cstdint>
+#include <iosfwd>
+#include <optional>
+#include <string>
+
+#include "common.h"
+
+#pragma once
+
+/**
+ * Training data for tuning the evaluation: positions with the score of a search and the result of
+ * the game they were played in, as generated by self-play with the datagen tool. Records take a
+ * fixed number of bytes, so files of millions of positions can be read quickly, shuffled, and
+ * split at any record boundary.
+ */
+namespace training {
+
+/**
+ * The result of the game from white's point of view, as in PGN.
+ */
+enum class Result : uint8_t { BLACK_WINS, DRAW, WHITE_WINS };
+
+struct Record {
+    Position position;
+    int16_t score = 0;  // In centipawns, from white's point of view
+    Result result = Result::DRAW;
+};
+
+// Size of a record: the board with 4 bits per square, the active color and castling rights, the en
+// passant target, the halfmove clock, the fullmove number, the score and the result
+static constexpr size_t kRecordSize = 32 + 1 + 1 + 1 + 2 + 2 + 1;
+
+/**
+ * Returns the result for a PGN result like "1-0", "0-1" or "1/2-1/2", or std::nullopt if the game
+ * is not over.
+ */
+std::optional<Result> toResult(const std::string& result);
+
+/**
+ * Writes the record in kRecordSize bytes, with numbers in little-endian byte order. The variant
+ * isn't written, as training data is for standard chess.
+ */
+void write(std::ostream& out, const Record& record);
+
+/**
+ * Reads a record written by write. Returns false at the end of the input, or if the record is
+ * incomplete or invalid, leaving the record unchanged.
+ */
+bool read(std::istream& in, Record& record);
+}  // namespace training
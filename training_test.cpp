@@ -0,0 +1,68 @@
+#include <cassert>
+#include <iostream>
+#include <random>
+#include <sstream>
+#include <string>
+
+#include "fen.h"
+#include "testkit.h"
+#include "training.h"
+
+using training::Record;
+using training::Result;
+
+void testResult() {
+    assert(training::toResult("1-0") == Result::WHITE_WINS);
+    assert(training::toResult("0-1") == Result::BLACK_WINS);
+    assert(training::toResult("1/2-1/2") == Result::DRAW);
+    assert(!training::toResult("*"));
+    std::cout << "All result tests passed!" << std::endl;
+}
+
+void testReadWrite() {
+    std::stringstream data;
+    Record record{fen::parsePosition("4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3 12 300"),
+                  -1234,
+                  Result::BLACK_WINS};
+    training::write(data, record);
+    assert(data.str().size() == training::kRecordSize);
+
+    // Random positions round-trip, with any score and result
+    std::mt19937 gen(7);
+    std::vector<Record> records;
+    for (int i = 0; i < 100; ++i) {
+        auto position = testkit::randomPosition(gen, i);
+        records.push_back({position, int16_t(gen()), Result(gen() % 3)});
+        training::write(data, records.back());
+    }
+    assert(data.str().size() == 101 * training::kRecordSize);
+
+    Record read;
+    assert(training::read(data, read));
+    assert(fen::to_string(read.position) == "4k3/8/8/2q5/5Pp1/8/7P/4K2R b Kkq f3 12 300");
+    assert(read.score == -1234 && read.result == Result::BLACK_WINS);
+    for (auto& record : records) {
+        assert(training::read(data, read));
+        assert(fen::to_string(read.position) == fen::to_string(record.position));
+        assert(read.score == record.score && read.result == record.result);
+    }
+    assert(!training::read(data, read));
+
+    // Incomplete and invalid records aren't read
+    std::stringstream incomplete(std::string(training::kRecordSize - 1, '\0'));
+    assert(!training::read(incomplete, read));
+    std::string bytes(training::kRecordSize, '\0');
+    bytes[0] = char(0xff);  // No such piece
+    std::stringstream invalid(bytes);
+    assert(!training::read(invalid, read));
+    assert(fen::to_string(read.position) == fen::to_string(records.back().position));
+
+    std::cout << "All read/write tests passed!" << std::endl;
+}
+
+int main() {
+    testResult();
+    testReadWrite();
+    std::cout << "All training tests passed!" << std::endl;
+    return 0;
+}
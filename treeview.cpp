@@ -0,0 +1,99 @@
+#include <algorithm>
+#include <climits>
+#include <cstdlib>  // For std::exit
+#include <fstream>
+#include <iostream>
+#include <sstream>
+#include <string>
+#include <vector>
+
+/**
+ * Reconstructs the search tree from a search log, as written by setSearchLog, and prints the
+ * subtree reached by the given moves, to see how the search arrived at its result.
+ */
+
+struct Node {
+    int depth = 0;
+    std::string move;  // The move leading to this node, or "-" for none
+    std::string hash;
+    std::string best;  // The best move found, or "-" if there are no moves
+    std::string evaluation;
+    bool cached = false;
+    std::vector<std::string> order;  // Moves in the order searched
+    std::vector<Node> children;
+};
+
+/**
+ * Reads the log and returns the nodes at the lowest depth, with all their descendants. The
+ * children of a node are the nodes one deeper read since the previous node at its depth or less.
+ */
+std::vector<Node> readLog(std::istream& in) {
+    std::vector<std::vector<Node>> pending;  // Nodes without parent yet, by depth
+    int minDepth = INT_MAX;
+    std::string line;
+    while (std::getline(in, line)) {
+        std::stringstream ss(line);
+        Node node;
+        if (!(ss >> node.depth >> node.move >> node.hash >> node.best >> node.evaluation) ||
+            node.depth < 0)
+            continue;
+        for (std::string move; ss >> move;) {
+            if (move == "cached")
+                node.cached = true;
+            else
+                node.order.push_back(move);
+        }
+        minDepth = std::min(minDepth, node.depth);
+        if (int(pending.size()) < node.depth + 2) pending.resize(node.depth + 2);
+        node.children = std::move(pending[node.depth + 1]);
+        pending[node.depth + 1].clear();
+        pending[node.depth].push_back(std::move(node));
+    }
+    return minDepth < int(pending.size()) ? std::move(pending[minDepth]) : std::vector<Node>();
+}
+
+void printNode(const Node& node, int indent, int levels) {
+    std::cout << std::string(indent * 4, ' ') << node.move << " (" << node.hash << "): best "
+              << node.best << " " << node.evaluation;
+    if (node.cached) std::cout << ", cached";
+    if (!node.order.empty()) {
+        std::cout << ", searched";
+        for (auto& move : node.order) std::cout << " " << move;
+    }
+    std::cout << std::endl;
+    if (levels > 1)
+        for (auto& child : node.children) printNode(child, indent + 1, levels - 1);
+}
+
+int main(int argc, char** argv) {
+    if (argc < 3) {
+        std::cerr << "Usage: " << argv[0] << " search.log <levels> [move...]" << std::endl;
+        std::exit(1);
+    }
+    std::ifstream log(argv[1]);
+    if (!log) {
+        std::cerr << "Cannot open " << argv[1] << std::endl;
+        std::exit(1);
+    }
+    int levels = std::atoi(argv[2]);
+    auto nodes = readLog(log);
+
+    // Follow the moves down the tree, using the last search of each node, as that is the deepest
+    for (int i = 3; i < argc; ++i) {
+        const Node* found = nullptr;
+        for (auto& node : nodes)
+            if (node.move == argv[i]) found = &node;
+        if (!found) {
+            std::cerr << "Move \"" << argv[i] << "\" not found in search log" << std::endl;
+            std::exit(1);
+        }
+        auto children = found->children;
+        if (i + 1 == argc) {
+            printNode(*found, 0, levels);
+            return 0;
+        }
+        nodes = std::move(children);
+    }
+    for (auto& node : nodes) printNode(node, 0, levels);
+    return 0;
+}
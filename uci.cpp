@@ -0,0 +1,620 @@
+#include <algorithm>
+#include <atomic>
+#include <chrono>
+#include <cstdlib>
+#include <fstream>
+#include <iomanip>
+#include <iostream>
+#include <iterator>
+#include <memory>
+#include <mutex>
+#include <random>
+#include <sstream>
+#include <string>
+#include <thread>
+#include <vector>
+
+#include "analysis.h"
+#include "book.h"
+#include "engine.h"
+#include "eval.h"
+#include "fen.h"
+#include "hint.h"
+#include "moves.h"
+#include "san.h"
+#include "search.h"
+#include "tablebase.h"
+
+/**
+ * A chess engine speaking the Universal Chess Interface (UCI) protocol on stdin and stdout.
+ * See https://www.wbec-ridderkerk.nl/html/UCIProtocol.html for the protocol.
+ */
+
+/**
+ * Writes a line to the GUI. The search thread and the main thread both send output, so lines are
+ * written while holding a lock, to avoid interleaving them.
+ */
+void send(const std::string& line) {
+    static std::mutex mutex;
+    std::lock_guard<std::mutex> lock(mutex);
+    std::cout << line << std::endl;
+}
+
+// Time to search when the "go" command doesn't give any limits
+static constexpr search::Duration kDefaultSearchTime{5000};
+static constexpr search::Duration kMaxMoveOverhead{5000};
+
+struct Options {
+    search::Duration moveOverhead = search::kDefaultMoveOverhead;
+    Variant variant = Variant::STANDARD;
+    bool showWDL = false;  // Report win, draw and loss chances with the score
+    config::Params params;  // Tuning of the search and evaluation, see config.h
+    std::unique_ptr<Engine> engine = makeEngine("alphabeta");
+    bool analyseMode = false;  // Set by the GUI when analyzing rather than playing
+    std::string analysisFile;  // Where the analysis cache is kept, if set
+    std::unique_ptr<AnalysisCache> analysis = std::make_unique<AnalysisCache>();
+    std::unique_ptr<std::ofstream> searchLog;  // For debugging, see setSearchLog
+    bool ownBook = false;  // Play moves from the book instead of searching, when it has any
+    Book book;
+};
+
+/**
+ * Converts a move to UCI long algebraic notation, like "e2e4" or "e7e8q".
+ */
+std::string toUCI(Move move) {
+    if (!move) return "0000";
+    auto str = std::string(move);
+    if (move.isPromotion()) str += to_char(promotionType(move.kind), Color::BLACK);
+    return str;
+}
+
+/**
+ * Converts an evaluation to a UCI score, like "cp 150" or "mate -3".
+ */
+std::string toUCI(const EvaluatedMove& move) {
+    if (auto mate = search::mateDistance(move)) return "mate " + std::to_string(mate);
+    return "cp " + std::to_string(int(move.evaluation * 100));
+}
+
+/**
+ * Converts win, draw and loss chances to "wdl <win> <draw> <loss>", in per mille.
+ */
+std::string toUCI(const WinDrawLoss& wdl) {
+    return "wdl " + std::to_string(wdl.win) + " " + std::to_string(wdl.draw) + " " +
+        std::to_string(wdl.loss);
+}
+
+/**
+ * Converts search statistics to "seldepth <x> nodes <x> nps <x> time <x>" for info lines.
+ */
+std::string toUCI(int selDepth, uint64_t nodes, search::Duration elapsed) {
+    auto nps = nodes * 1000 / std::max(elapsed.count(), int64_t(1));
+    return "seldepth " + std::to_string(selDepth) + " nodes " + std::to_string(nodes) + " nps " +
+        std::to_string(nps) + " time " + std::to_string(elapsed.count());
+}
+
+/**
+ * Returns the legal move in the position matching the move in UCI notation, or an invalid move.
+ */
+Move parseUCIMove(const Position& position, const std::string& str) {
+    for (auto& [move, newPosition] : allLegalMoves(position))
+        if (toUCI(move) == str) return move;
+    return {};
+}
+
+/**
+ * Returns a message for an illegal move in UCI notation, explaining why it is illegal if possible.
+ */
+std::string illegalMove(const Position& position, const std::string& str) {
+    auto message = "illegal move " + str;
+    auto isFile = [](char c) { return c >= 'a' && c < 'a' + kNumFiles; };
+    auto isRank = [](char c) { return c >= '1' && c < '1' + kNumRanks; };
+    if (str.size() < 4 || str.size() > 5 || !isFile(str[0]) || !isRank(str[1]) ||
+        !isFile(str[2]) || !isRank(str[3]))
+        return message;
+
+    std::optional<PieceType> promotion;
+    if (str.size() == 5) {
+        auto index = std::string("nbrq").find(str[4]);
+        if (index == std::string::npos) return message;
+        promotion = PieceType(int(PieceType::KNIGHT) + index);
+    }
+    auto move = inferMove(position.board,
+                          Square(str[1] - '1', str[0] - 'a'),
+                          Square(str[3] - '1', str[2] - 'a'),
+                          promotion);
+    return message + ": " + to_string(checkMove(position, move));
+}
+
+/**
+ * Returns true if the position can be searched: each side has a single king, the side that just
+ * moved isn't in check, and there are no pawns on the first or last rank. Searching positions that
+ * can't occur in a game could capture a king or move pawns off the board.
+ */
+bool isPlayable(const Position& position) {
+    for (auto color : {Color::WHITE, Color::BLACK})
+        if (SquareSet::find(position.board, addColor(PieceType::KING, color)).size() != 1)
+            return false;
+    auto pawns = SquareSet::find(position.board, Piece::WHITE_PAWN) |
+        SquareSet::find(position.board, Piece::BLACK_PAWN);
+    if (!(pawns & (SquareSet::rank(0) | SquareSet::rank(kNumRanks - 1))).empty()) return false;
+    auto moved = position;
+    moved.activeColor = !position.activeColor;
+    return !isInCheck(moved);
+}
+
+/**
+ * Handles "position [fen <fenstring> | startpos] moves <move1> ... <movei>". The history gets the
+ * positions before the moves, for detecting repetitions. Invalid or unplayable positions are
+ * ignored, keeping the current one, and moves are applied up to the first illegal one.
+ */
+void position(std::istream& in,
+              Position& position,
+              std::vector<Position>& history,
+              Variant variant) {
+    std::string token;
+    in >> token;
+    Position newPosition;
+    if (token == "startpos") {
+        newPosition = fen::parsePosition(fen::initialPosition);
+        in >> token;
+    } else if (token == "fen") {
+        std::string fen;
+        while (in >> token && token != "moves") fen += (fen.empty() ? "" : " ") + token;
+        newPosition = fen::parsePosition(fen);
+        newPosition.variant = variant;
+        if (!fen::isValid(fen) || !isPlayable(newPosition)) {
+            send("info string invalid position " + fen);
+            return;
+        }
+        normalizeEnPassant(newPosition);  // Impossible targets would confuse move generation
+    } else {
+        send("info string expected startpos or fen");
+        return;
+    }
+    position = newPosition;
+    position.variant = variant;
+    history.clear();
+    if (token != "moves") return;
+
+    while (in >> token) {
+        auto move = parseUCIMove(position, token);
+        if (!move) {
+            send("info string " + illegalMove(position, token));
+            return;
+        }
+        history.push_back(position);
+        position = applyMove(position, move);
+    }
+}
+
+/**
+ * Handles "eval", a debugging command that isn't part of UCI. Prints the static evaluation of the
+ * current position, with the contribution of the piece on each square in centipawns, the material
+ * of both sides, the scores of passed pawns, king tropism and any pawn race, the game phase and
+ * the evaluation from the point of view of either side.
+ */
+void eval(const Position& position) {
+    auto trace = traceEvaluation(position.board);
+    std::stringstream files;
+    files << "  ";
+    for (int file = 0; file < kNumFiles; ++file) files << std::setw(6) << char('a' + file);
+    send(files.str());
+    for (int rank = kNumRanks - 1; rank >= 0; --rank) {
+        std::stringstream ss;
+        ss << rank + 1 << " ";
+        for (int file = 0; file < kNumFiles; ++file) {
+            Square square(rank, file);
+            if (position.board[square] == Piece::NONE)
+                ss << std::setw(6) << ".";
+            else
+                ss << std::setw(6) << trace.squares[square.index()];
+        }
+        send(ss.str());
+    }
+    std::stringstream ss;
+    ss << std::fixed << std::setprecision(2);
+    ss << "Material: white " << trace.whiteMaterial << ", black " << trace.blackMaterial;
+    send(ss.str());
+    ss.str("");
+    ss << "Passed pawns: " << trace.passedPawns << ", king tropism: " << trace.kingTropism;
+    if (trace.pawnRace) ss << ", pawn race: " << trace.pawnRace;
+    send(ss.str());
+    ss.str("");
+    ss << "Phase: " << trace.phase;
+    send(ss.str());
+    ss.str("");
+    auto evaluation = position.activeColor == Color::WHITE ? trace.evaluation : -trace.evaluation;
+    ss << "Evaluation: " << trace.evaluation << " (white side), " << evaluation
+       << " (side to move)";
+    send(ss.str());
+}
+
+/**
+ * Handles "hint [<depth>]", which isn't part of UCI. Searches the current position to the depth,
+ * 3 by default, and prints the best move in SAN with an explanation, for players wanting advice.
+ */
+void hint(std::istream& in, const Position& position) {
+    int depth;
+    if (!(in >> depth)) depth = 3;
+    auto hint = ::hint(position, depth);
+    if (!hint.best.move) {
+        send("info string no legal moves");
+        return;
+    }
+    send("info string hint " + san::to_string(position, hint.best.move) + ": " +
+         hint.explanation);
+}
+
+/**
+ * Handles "setoption name <id> [value <x>]".
+ */
+void setoption(std::istream& in, Options& options) {
+    std::string token, name, value;
+    in >> token;  // "name"
+    while (in >> token && token != "value") name += (name.empty() ? "" : " ") + token;
+    in >> value;
+
+    if (name == "UCI_Variant") {
+        auto variant = options.variant;
+        if (value == "chess")
+            options.variant = Variant::STANDARD;
+        else if (value == "atomic")
+            options.variant = Variant::ATOMIC;
+        else
+            send("info string unknown variant " + value);
+        if (options.variant != variant) clearHashTable();  // Cached results are for other rules
+    } else if (name == "UCI_Chess960") {
+        // Castling moves are then sent as the king taking its own rook, like "e1h1"
+        auto variant = options.variant;
+        if (value == "true")
+            options.variant = Variant::CHESS960;
+        else if (options.variant == Variant::CHESS960)
+            options.variant = Variant::STANDARD;
+        if (options.variant != variant) clearHashTable();
+    } else if (name == "UCI_ShowWDL") {
+        options.showWDL = value == "true";
+    } else if (name == "UCI_AnalyseMode") {
+        options.analyseMode = value == "true";
+    } else if (name == "Analysis File") {
+        // Analysis is saved to the file after each search in analysis mode, so it can be resumed
+        options.analysisFile = value;
+        options.analysis = std::make_unique<AnalysisCache>();
+        std::ifstream file(value);
+        if (file && !options.analysis->read(file))
+            send("info string cannot load analysis file " + value);
+    } else if (name == "OwnBook") {
+        options.ownBook = value == "true";
+    } else if (name == "Book File") {
+        options.book = {};
+        std::ifstream file(value);
+        if (!value.empty() && (!file || !options.book.read(file)))
+            send("info string cannot load book file " + value);
+    } else if (name == "Ponder") {
+        // The GUI decides when to ponder, so there is nothing to set
+    } else if (name == "Search Log") {
+        setSearchLog(nullptr);
+        options.searchLog.reset();
+        if (value.empty()) return;
+        options.searchLog = std::make_unique<std::ofstream>(value);
+        if (*options.searchLog)
+            setSearchLog(options.searchLog.get());
+        else
+            send("info string cannot open search log " + value);
+    } else if (name == "Piece Values") {
+        std::ifstream file(value);
+        if (!file || !loadPieceValues(file)) send("info string cannot load piece values " + value);
+        clearHashTable();  // Cached evaluations use the old values
+    } else if (name == "Config File") {
+        // Parameters not in the file get their defaults, including the piece values
+        config::Params params;
+        std::ifstream file(value);
+        if (!value.empty() && (!file || !config::loadParams(file, params)))
+            send("info string cannot load config file " + value);
+        else
+            options.params = params;
+        setPieceValues(options.params);
+        clearHashTable();
+    } else if (name == "Tablebase Path") {
+        tablebase::clear();
+        if (!value.empty()) {
+            auto count = tablebase::load(value);
+            send("info string loaded " + std::to_string(count) + " tablebases from " + value);
+        }
+        clearHashTable();  // Cached results may not use the tables
+    } else if (name == "Engine") {
+        if (auto engine = makeEngine(value))
+            options.engine = std::move(engine);
+        else
+            send("info string unknown engine " + value);
+    } else if (name == "Move Overhead") {
+        auto overhead = search::Duration(std::atoi(value.c_str()));
+        options.moveOverhead = std::clamp(overhead, search::Duration(0), kMaxMoveOverhead);
+    } else {
+        send("info string unknown option " + name);
+    }
+}
+
+/**
+ * Runs searches in a separate thread, so commands like "stop" are handled while searching. Each
+ * search prints exactly one best move, when done or when stopped. Infinite searches don't print
+ * the best move until stopped, even if they're done searching. Pondering searches are infinite
+ * until "ponderhit", after which they get the time allocated by the "go" command, counting from
+ * the ponderhit.
+ */
+class SearchThread {
+    using Clock = std::chrono::steady_clock;
+
+    std::thread thread;
+    Engine* engine = nullptr;  // Engine of the current search, owned by the options
+    std::atomic<bool> stopRequested = false;
+    std::atomic<bool> pondering = false;
+    Clock::time_point ponderhitTime;  // Written before pondering is cleared
+
+public:
+    ~SearchThread() { stop(); }
+
+    void start(const Position& position,
+               const std::vector<Position>& history,
+               const Options& options,
+               search::Limits limits,
+               bool infinite,
+               bool ponder) {
+        stop();
+        stopRequested = false;
+        ponderhitTime = Clock::now();  // Searches that aren't pondering start timing right away
+        pondering = ponder;
+        auto ponderTime = limits.time;
+        if (ponder) limits.time = search::Duration::max();
+        limits.stop = [this, ponderTime]() {
+            if (stopRequested) return true;
+            if (pondering || ponderTime == search::Duration::max()) return false;
+            return Clock::now() - ponderhitTime >= ponderTime;
+        };
+        limits.progress = [](const search::Progress& progress) {
+            send("info depth " + std::to_string(progress.depth) + " " +
+                 toUCI(progress.selDepth, progress.nodes, progress.elapsed));
+        };
+        limits.currentMove = [](Move move, int number) {
+            send("info currmove " + toUCI(move) + " currmovenumber " + std::to_string(number));
+        };
+        bool showWDL = options.showWDL;
+        engine = options.engine.get();
+        auto analysis = options.analyseMode && !options.analysisFile.empty()
+            ? options.analysis.get()
+            : nullptr;
+        auto analysisFile = options.analysisFile;
+        thread = std::thread([this, position, history, limits, infinite, showWDL, analysis,
+                              analysisFile]() {
+            auto startNodes = nodeCount;
+            auto scoreAndPV = [&](const EvaluatedMove& evaluation, const MoveVector& moves) {
+                std::string pv;
+                for (auto move : moves) pv += " " + toUCI(move);
+                auto score = toUCI(evaluation);
+                auto wdl = winDrawLoss(evaluation.evaluation, position.board);
+                if (showWDL) score += " " + toUCI(wdl);
+                return "score " + score + " pv" + pv;
+            };
+            auto info = [&](const search::RootMove& best, int depth, search::Duration elapsed) {
+                send("info depth " + std::to_string(depth) + " " +
+                     toUCI(selDepth, nodeCount - startNodes, elapsed) + " " +
+                     scoreAndPV(best.evaluation, best.pv));
+            };
+
+            // Report earlier analysis first, and use it instead of searching if deep enough
+            Engine::Result result;
+            auto cached = analysis ? analysis->find(position) : nullptr;
+            if (cached) {
+                send("info depth " + std::to_string(cached->depth) + " " +
+                     scoreAndPV(cached->best, cached->pv) + " string cached");
+            }
+            if (cached && cached->depth >= limits.depth && !limits.mate) {
+                result = {cached->best, cached->pv, 0, cached->depth};
+            } else {
+                engine->setPosition(position, history);
+                result = engine->go(limits, info);
+            }
+            if (analysis && result.best.move &&
+                analysis->store(position, {result.depth, result.best, result.pv})) {
+                std::ofstream file(analysisFile);
+                analysis->write(file);
+                if (!file) send("info string cannot save analysis file " + analysisFile);
+            }
+
+            while ((infinite || pondering) && !stopRequested)
+                std::this_thread::sleep_for(std::chrono::milliseconds(1));
+            send("bestmove " + toUCI(result.best.move));
+        });
+    }
+
+    /**
+     * The opponent played the expected move, so continue the search as a normal one.
+     */
+    void ponderhit() {
+        if (!pondering) return;
+        ponderhitTime = Clock::now();
+        pondering = false;
+    }
+
+    /**
+     * Stops the current search, if any, and waits for it to print its best move.
+     */
+    void stop() {
+        stopRequested = true;
+        if (engine) engine->stop();
+        if (thread.joinable()) thread.join();
+        engine = nullptr;  // The options may replace it now
+    }
+};
+
+bool isGoKeyword(const std::string& token) {
+    for (auto keyword : {"searchmoves", "ponder", "wtime", "btime", "winc", "binc", "movestogo",
+                         "depth", "nodes", "mate", "movetime", "infinite"})
+        if (token == keyword) return true;
+    return false;
+}
+
+/**
+ * Handles "go", with the clocks of both sides or other search limits, starting the search. With
+ * "searchmoves", only the listed legal moves are searched. If none are legal, all moves are. With
+ * "ponder", the position is after the expected reply of the opponent, and the clocks are for after
+ * that move, so the time allocation only applies after "ponderhit". With the OwnBook option, a
+ * standard chess position in the book gets a book move right away, unless the GUI is analyzing,
+ * pondering or restricting the moves.
+ */
+void go(std::istream& in,
+        const Position& position,
+        const std::vector<Position>& history,
+        const Options& options,
+        SearchThread& searchThread) {
+    search::GameClock clocks[2];  // White and black
+    search::Limits limits;
+    limits.params = options.params;
+    bool limited = false;
+    bool infinite = false;
+    bool ponder = false;
+    std::vector<std::string> tokens{std::istream_iterator<std::string>(in), {}};
+    for (size_t i = 0; i < tokens.size(); ++i) {
+        auto& token = tokens[i];
+        if (token == "infinite") {
+            infinite = limited = true;
+            continue;
+        }
+        if (token == "ponder") {
+            ponder = true;
+            continue;
+        }
+        if (token == "searchmoves") {
+            // Moves continue until the next keyword, but are normally last
+            while (i + 1 < tokens.size() && !isGoKeyword(tokens[i + 1])) {
+                if (auto move = parseUCIMove(position, tokens[++i]))
+                    limits.searchMoves.push_back(move);
+                else
+                    send("info string " + illegalMove(position, tokens[i]));
+            }
+            continue;
+        }
+        uint64_t value = 0;
+        if (i + 1 < tokens.size()) value = std::strtoull(tokens[++i].c_str(), nullptr, 10);
+        if (token == "wtime") clocks[0].time = search::Duration(value);
+        if (token == "btime") clocks[1].time = search::Duration(value);
+        if (token == "winc") clocks[0].increment = search::Duration(value);
+        if (token == "binc") clocks[1].increment = search::Duration(value);
+        if (token == "movestogo") clocks[0].movesToGo = clocks[1].movesToGo = value;
+        if (token == "depth") limits.depth = value, limited = true;
+        if (token == "nodes") limits.nodes = value, limited = true;
+        if (token == "mate") limits.mate = value, limited = true;
+        if (token == "movetime") {
+            auto moveTime = search::Duration(value) - options.moveOverhead;
+            limits.moveTime = std::max(moveTime, search::Duration(0));
+            limited = true;
+        }
+    }
+
+    auto& clock = clocks[position.activeColor == Color::WHITE ? 0 : 1];
+    if (clock.time.count() && !infinite) {
+        limits.time = search::allocateTime(clock, options.moveOverhead, options.params);
+        limits.maxTime = search::maximumTime(clock, options.moveOverhead, options.params);
+    } else if (!limited) {
+        limits.time = kDefaultSearchTime;
+    }
+
+    if (options.ownBook && options.variant == Variant::STANDARD && !options.analyseMode &&
+        !infinite && !ponder && limits.searchMoves.empty()) {
+        static std::mt19937 random(std::random_device{}());
+        auto move = options.book.choose(position, std::uniform_real_distribution<float>()(random));
+        if (move) {
+            searchThread.stop();  // The previous search prints its best move first
+            send("info string book move " + san::to_string(position, move));
+            send("bestmove " + toUCI(move));
+            return;
+        }
+    }
+
+    searchThread.start(position, history, options, limits, infinite, ponder);
+}
+
+bool isCommand(const std::string& token) {
+    for (auto command : {"uci", "debug", "isready", "setoption", "register", "ucinewgame",
+                         "position", "go", "stop", "ponderhit", "quit", "eval", "hint"})
+        if (token == command) return true;
+    return false;
+}
+
+/**
+ * Reads commands from standard input until "quit" or the end of input. As the protocol requires,
+ * unknown tokens before a command are skipped, so "joho debug on" is "debug on", and lines may end
+ * in a carriage return. Commands that arrive at the wrong time, like "ponderhit" or "stop" without
+ * a search, are ignored. Commands changing the position or options stop any search in progress,
+ * which then prints its best move.
+ */
+int main() {
+    Options options;
+    SearchThread searchThread;
+    auto current = fen::parsePosition(fen::initialPosition);
+    std::vector<Position> history;  // Positions before the current one
+    std::string line;
+    while (std::getline(std::cin, line)) {
+        if (!line.empty() && line.back() == '\r') line.pop_back();
+        std::stringstream in(line);
+        std::string command, unknown;
+        while (in >> command && !isCommand(command))
+            unknown += (unknown.empty() ? "" : " ") + command;
+        if (!isCommand(command)) {
+            if (!unknown.empty()) send("info string unknown command " + unknown);
+            continue;
+        }
+        if (command == "uci") {
+            send("id name gbchess");
+            send("id author Geert Bosch");
+            send("option name Move Overhead type spin default " +
+                 std::to_string(search::kDefaultMoveOverhead.count()) + " min 0 max " +
+                 std::to_string(kMaxMoveOverhead.count()));
+            send("option name Ponder type check default false");
+            send("option name OwnBook type check default false");
+            send("option name Book File type string default <empty>");
+            send("option name UCI_Variant type combo default chess var chess var atomic");
+            send("option name UCI_Chess960 type check default false");
+            send("option name UCI_ShowWDL type check default false");
+            send("option name UCI_AnalyseMode type check default false");
+            send("option name Analysis File type string default <empty>");
+            send("option name Search Log type string default <empty>");
+            send("option name Piece Values type string default <empty>");
+            send("option name Config File type string default <empty>");
+            send("option name Tablebase Path type string default <empty>");
+            send("option name Engine type combo default alphabeta var alphabeta var greedy var "
+                 "random");
+            send("uciok");
+        } else if (command == "isready") {
+            send("readyok");
+        } else if (command == "ucinewgame") {
+            searchThread.stop();
+            current = fen::parsePosition(fen::initialPosition);
+            current.variant = options.variant;
+            history.clear();
+            clearHashTable();
+        } else if (command == "position") {
+            searchThread.stop();
+            position(in, current, history, options.variant);
+        } else if (command == "setoption") {
+            searchThread.stop();  // Options like the piece values are in use by the search
+            setoption(in, options);
+        } else if (command == "debug" || command == "register") {
+            // There is no debug output, and no registration needed
+        } else if (command == "eval") {
+            eval(current);
+        } else if (command == "hint") {
+            searchThread.stop();
+            hint(in, current);
+        } else if (command == "go") {
+            go(in, current, history, options, searchThread);
+        } else if (command == "ponderhit") {
+            searchThread.ponderhit();
+        } else if (command == "stop") {
+            searchThread.stop();
+        } else if (command == "quit") {
+            break;
+        }
+    }
+    return 0;
+}
@@ -0,0 +1,249 @@
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include <cassert>
+#include <csignal>
+#include <cstdio>
+#include <cstdlib>
+#include <fstream>
+#include <iostream>
+#include <string>
+#include <vector>
+
+/**
+ * Drives the engine through scripted UCI sessions over pipes, checking its responses. Responses the
+ * protocol requires, like "uciok", "readyok" and "bestmove", must be exactly as expected, with no
+ * extra or missing ones. Other expected lines, like info strings, must appear in order among the
+ * output. An expected line ending in '*' matches any line starting with the text before it.
+ */
+
+static constexpr unsigned kTimeoutSeconds = 60;  // For the whole test, in case the engine hangs
+
+struct Session {
+    const char* name;
+    std::vector<std::string> input;
+    std::vector<std::string> expected;
+};
+
+/**
+ * A running engine, with pipes for its standard input and output.
+ */
+class Engine {
+    pid_t pid;
+    FILE* in;
+    FILE* out;
+
+public:
+    explicit Engine(const char* path) {
+        int toEngine[2], fromEngine[2];
+        if (pipe(toEngine) || pipe(fromEngine)) std::perror("pipe"), std::exit(1);
+        pid = fork();
+        if (pid < 0) std::perror("fork"), std::exit(1);
+        if (pid == 0) {
+            dup2(toEngine[0], STDIN_FILENO);
+            dup2(fromEngine[1], STDOUT_FILENO);
+            for (int fd : {toEngine[0], toEngine[1], fromEngine[0], fromEngine[1]}) close(fd);
+            execl(path, path, nullptr);
+            std::perror(path);
+            std::_Exit(1);
+        }
+        close(toEngine[0]);
+        close(fromEngine[1]);
+        in = fdopen(toEngine[1], "w");
+        out = fdopen(fromEngine[0], "r");
+    }
+
+    void send(const std::string& line) {
+        std::fputs((line + "\n").c_str(), in);
+        std::fflush(in);
+    }
+
+    /**
+     * Reads a line of output into line, returning false at the end of the output.
+     */
+    bool receive(std::string& line) {
+        line.clear();
+        for (int ch; (ch = std::fgetc(out)) != EOF;) {
+            if (ch == '\n') return true;
+            line += char(ch);
+        }
+        return !line.empty();
+    }
+
+    /**
+     * Closes the input of the engine, and returns the rest of its output once it exits.
+     */
+    std::vector<std::string> finish() {
+        std::fclose(in);
+        std::vector<std::string> lines;
+        for (std::string line; receive(line);) lines.push_back(line);
+        std::fclose(out);
+        int status;
+        waitpid(pid, &status, 0);
+        assert(WIFEXITED(status) && WEXITSTATUS(status) == 0);
+        return lines;
+    }
+};
+
+bool matches(const std::string& expected, const std::string& line) {
+    if (!expected.empty() && expected.back() == '*')
+        return line.compare(0, expected.size() - 1, expected, 0, expected.size() - 1) == 0;
+    return line == expected;
+}
+
+bool isRequired(const std::string& line) {
+    auto word = line.substr(0, line.find(' '));
+    return word == "id" || word == "uciok" || word == "readyok" || word == "bestmove";
+}
+
+/**
+ * Returns the response a command waits for before sending the next one, if any, so the engine
+ * finishes a search before the next command arrives, as a GUI would wait.
+ */
+std::string response(const std::string& command) {
+    if (command == "uci") return "uciok";
+    if (command == "isready") return "readyok";
+    bool go = command.rfind("go", 0) == 0;
+    bool waits = command.find("infinite") != std::string::npos ||
+        command.find("ponder") != std::string::npos;
+    return go && !waits ? "bestmove" : "";
+}
+
+std::vector<std::string> run(const char* path, const Session& session) {
+    Engine engine(path);
+    std::vector<std::string> output;
+    for (auto& command : session.input) {
+        engine.send(command);
+        auto wait = response(command);
+        for (std::string line; !wait.empty() && engine.receive(line);) {
+            output.push_back(line);
+            if (line.rfind(wait, 0) == 0) break;
+        }
+    }
+    for (auto& line : engine.finish()) output.push_back(line);
+    return output;
+}
+
+bool check(const Session& session, const std::vector<std::string>& output) {
+    std::vector<std::string> required, expectedRequired;
+    for (auto& line : output)
+        if (isRequired(line)) required.push_back(line);
+    for (auto& line : session.expected)
+        if (isRequired(line)) expectedRequired.push_back(line);
+    bool ok = required.size() == expectedRequired.size();
+    for (size_t i = 0; ok && i < required.size(); ++i)
+        ok = matches(expectedRequired[i], required[i]);
+
+    auto next = output.begin();
+    for (auto& expected : session.expected) {
+        while (next != output.end() && !matches(expected, *next)) ++next;
+        if (next == output.end()) {
+            ok = false;
+            break;
+        }
+        ++next;
+    }
+    if (ok) return true;
+
+    std::cerr << "Session \"" << session.name << "\" failed\nExpected:\n";
+    for (auto& line : session.expected) std::cerr << "    " << line << "\n";
+    std::cerr << "Output:\n";
+    for (auto& line : output) std::cerr << "    " << line << "\n";
+    return false;
+}
+
+static const Session kSessions[] = {
+    {"handshake",
+     {"uci", "isready"},
+     {"id name gbchess", "id author Geert Bosch", "uciok", "readyok"}},
+    {"search",
+     {"position fen 6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1", "go depth 3"},
+     {"info depth 1 *", "bestmove e7g7"}},
+    {"timed search goes past depth 1",
+     {"position startpos", "go wtime 10000 btime 10000"},
+     {"info depth 2 *", "bestmove *"}},
+    {"search without position",
+     {"ucinewgame", "go depth 1"},
+     {"bestmove *"}},
+    {"game over",
+     {"position fen k7/8/1Q6/8/8/8/8/7K b - - 0 1", "go depth 1"},
+     {"bestmove 0000"}},
+    {"infinite search until stop",
+     {"position fen 6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1", "go infinite", "isready", "stop"},
+     {"readyok", "bestmove e7g7"}},
+    {"quit during search",
+     {"position fen 6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1", "go infinite", "quit", "isready"},
+     {"bestmove e7g7"}},
+    {"ponderhit and stop without search",
+     {"ponderhit", "stop", "isready"},
+     {"readyok"}},
+    {"unknown tokens and commands",
+     {"joho isready", "", "   ", "foo bar", "isready\r", "debug on", "register later"},
+     {"readyok", "info string unknown command foo bar", "readyok"}},
+    {"invalid positions keep the current one",
+     {"position fen 6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1",
+      "position fen 8/8/8 w - - 0 1",
+      "position fen 8/8/8/8/8/8/8/8 w - - 0 1",
+      "position fen 4k3/8/8/8/8/8/8/4R1K1 w - - 0 1",
+      "position",
+      "position moves e2e4",
+      "go depth 2"},
+     {"info string invalid position 8/8/8 w - - 0 1",
+      "info string invalid position 8/8/8/8/8/8/8/8 w - - 0 1",
+      "info string invalid position 4k3/8/8/8/8/8/8/4R1K1 w - - 0 1",
+      "info string expected startpos or fen",
+      "info string expected startpos or fen",
+      "bestmove e7g7"}},
+    {"moves up to the first illegal one",
+     {"position startpos moves e2e4 e7e5 e1e3 g1f3", "eval"},
+     {"info string illegal move e1e3: *", "Evaluation: 0.00 (white side), 0.00 (side to move)"}},
+    {"baseline engines",
+     {"setoption name Engine value greedy",
+      "position fen 4k3/8/8/3q4/8/8/2P5/3RK3 w - - 0 1",
+      "go depth 5",
+      "setoption name Engine value mcts",
+      "setoption name Engine value random",
+      "position fen 4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+      "go depth 5 searchmoves h1h8"},
+     {"bestmove d1d5", "info string unknown engine mcts", "bestmove h1h8"}},
+    {"analysis is saved",
+     {"setoption name UCI_AnalyseMode value true",
+      "setoption name Analysis File value analysis.tmp",
+      "position fen 6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1",
+      "go depth 1"},
+     {"bestmove e7g7"}},
+    {"analysis is resumed",
+     {"setoption name UCI_AnalyseMode value true",
+      "setoption name Analysis File value analysis.tmp",
+      "position fen 6k1/4Q3/5K2/8/8/8/8/8 w - - 0 1",
+      "go depth 1"},
+     {"info depth 1 score mate 1 pv e7g7 string cached", "bestmove e7g7"}},
+    {"book move",
+     {"setoption name OwnBook value true",
+      "setoption name Book File value book.tmp",
+      "position startpos",
+      "go depth 1",
+      "position startpos moves d2d4",
+      "go depth 1"},
+     {"info string book move d4", "bestmove d2d4", "info depth 1 *", "bestmove *"}},
+    {"missing book file",
+     {"setoption name Book File value missing.tmp", "isready"},
+     {"info string cannot load book file missing.tmp", "readyok"}},
+    {"unknown option",
+     {"setoption name Foo Bar value 1", "isready"},
+     {"info string unknown option Foo Bar", "readyok"}},
+};
+
+int main(int argc, char** argv) {
+    auto path = argc > 1 ? argv[1] : "./gbchess";
+    std::signal(SIGPIPE, SIG_IGN);  // Fail on the assertions instead, if the engine exits early
+    alarm(kTimeoutSeconds);
+
+    std::remove("analysis.tmp");  // Written by the analysis sessions
+    std::ofstream("book.tmp") << "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - d4 1 0 0\n";
+    bool ok = true;
+    for (auto& session : kSessions) ok = check(session, run(path, session)) && ok;
+    if (!ok) return 1;
+    std::cout << "All UCI tests passed!" << std::endl;
+    return 0;
+}